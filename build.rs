@@ -0,0 +1,144 @@
+//! Validates the WGSL shaders at compile time, so a typo fails the build with the location
+//! of the error, instead of panicking when the shader module is created.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use naga::{
+    front::wgsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+    ShaderStage,
+};
+
+#[path = "src/entry_points.rs"]
+mod entry_points;
+
+/// The `to_srgb` function that's appended to the shaders that call it at runtime
+const SRGB_SHADER: &str = include_str!("src/srgb.wgsl");
+
+/// Finds every WGSL file in the directory and its subdirectories
+fn find_shaders(dir: &Path, shaders: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir).unwrap_or_else(|error| panic!("{}: {error}", dir.display()));
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_shaders(&path, shaders);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "wgsl")
+        {
+            shaders.push(path);
+        }
+    }
+}
+
+/// The entry points a shader needs, and the stage of each of them
+fn required_entry_points(name: &str) -> Vec<(&'static str, ShaderStage)> {
+    // The first entry point of a render shader is the vertex shader, the others are fragment
+    // shaders. Compute shaders only have compute entry points.
+    if let Some((_, required)) = entry_points::SHADERS
+        .iter()
+        .find(|(shader, _)| *shader == name)
+    {
+        required
+            .iter()
+            .enumerate()
+            .map(|(index, &entry_point)| {
+                let stage = if index == 0 {
+                    ShaderStage::Vertex
+                } else {
+                    ShaderStage::Fragment
+                };
+                (entry_point, stage)
+            })
+            .collect()
+    } else if let Some((_, required)) = entry_points::COMPUTE_SHADERS
+        .iter()
+        .find(|(shader, _)| *shader == name)
+    {
+        required
+            .iter()
+            .map(|&entry_point| (entry_point, ShaderStage::Compute))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Parses and validates a shader, and checks that it has the entry points the pipelines
+/// need. Returns the errors in the same format wgpu reports them in.
+fn check_shader(path: &Path, name: &str) -> Result<(), String> {
+    let mut source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let path = path.display().to_string();
+
+    // WGSL can't include other files, so the shaders that call to_srgb without declaring it
+    // get it appended, like they do at runtime
+    if source.contains("to_srgb(") && !source.contains("fn to_srgb(") {
+        source = format!("{source}\n{SRGB_SHADER}");
+    }
+
+    let module =
+        wgsl::parse_str(&source).map_err(|error| error.emit_to_string_with_path(&source, &path))?;
+
+    // The push constants are only declared at runtime, so no extra capabilities are needed
+    Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|error| error.emit_to_string_with_path(&source, &path))?;
+
+    let missing = required_entry_points(name)
+        .into_iter()
+        .filter(|&(entry_point, stage)| {
+            !module
+                .entry_points
+                .iter()
+                .any(|entry| entry.name == entry_point && entry.stage == stage)
+        })
+        .map(|(entry_point, _)| entry_point)
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{path} is missing the entry points {}",
+            missing.join(", ")
+        ))
+    }
+}
+
+fn main() {
+    // Check the shaders again when any of them, or the entry points, change
+    println!("cargo:rerun-if-changed=src");
+
+    let mut shaders = Vec::new();
+    find_shaders(Path::new("src"), &mut shaders);
+
+    let mut failed = false;
+    for path in &shaders {
+        let name = path
+            .strip_prefix("src")
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if let Err(error) = check_shader(path, &name) {
+            eprintln!("{error}");
+            failed = true;
+        }
+    }
+
+    // Every shader the pipelines need has to exist
+    for (name, _) in entry_points::SHADERS
+        .iter()
+        .chain(entry_points::COMPUTE_SHADERS)
+    {
+        if !Path::new("src").join(name).exists() {
+            eprintln!("src/{name} doesn't exist");
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
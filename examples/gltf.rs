@@ -0,0 +1,27 @@
+//! Runs the demo with a glTF model instead of the cube.
+//! The model is the first argument, relative to the resource directory, or `cube.glb`:
+//!
+//! `cargo run --example gltf --features gltf -- cube.glb`
+
+use learn_wgpu::{app::StateBuilder, run_app, state::State};
+
+fn main() {
+    env_logger::init();
+    let file_name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "cube.glb".to_owned());
+
+    let result = pollster::block_on(run_app(StateBuilder::new(), |ctx| {
+        let mut state = State::new(ctx);
+
+        // The cube is drawn if the model couldn't be loaded
+        if let Err(error) = pollster::block_on(state.load_model(ctx, &file_name)) {
+            log::error!("Couldn't load {file_name}: {error}");
+        }
+        state
+    }));
+    if let Err(error) = result {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
+}
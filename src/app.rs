@@ -0,0 +1,886 @@
+use std::{future::Future, time::Duration};
+
+use thiserror::Error;
+use web_time::Instant;
+use wgpu::{
+    Adapter, AdapterInfo, Backend, Backends, CommandEncoder, CommandEncoderDescriptor,
+    CompositeAlphaMode, Device, DeviceDescriptor, Features, Instance, InstanceDescriptor, Limits,
+    PowerPreference, PresentMode, Queue, RequestAdapterOptions, RequestDeviceError, Surface,
+    SurfaceConfiguration, SurfaceError, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor,
+};
+use winit::{
+    dpi::PhysicalSize,
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event_loop::EventLoop,
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window, WindowBuilder},
+};
+
+use crate::time::{FixedTimestep, FrameLimiter, FrameStats};
+
+/// The reasons the state couldn't be initialized
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("couldn't create the window: {0}")]
+    WindowCreation(#[from] winit::error::OsError),
+    #[error("couldn't create a surface for the window: {0}")]
+    SurfaceCreation(#[from] wgpu::CreateSurfaceError),
+    #[error("no graphics adapter is compatible with the surface")]
+    NoAdapter,
+    #[error("couldn't get a device from the graphics adapter: {0}")]
+    NoDevice(#[from] RequestDeviceError),
+    #[error("the surface doesn't support any texture format, present mode, or alpha mode")]
+    UnsupportedSurface,
+}
+
+/// On the web, errors are passed to JavaScript as strings
+#[cfg(target_arch = "wasm32")]
+impl From<StateError> for wasm_bindgen::JsValue {
+    fn from(error: StateError) -> Self {
+        Self::from_str(&error.to_string())
+    }
+}
+
+/// Configures the window and the GPU before the app is created.
+/// Anything that isn't set uses the defaults of winit and wgpu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateBuilder {
+    title: String,
+    inner_size: Option<PhysicalSize<u32>>,
+    present_mode: Option<PresentMode>,
+    backends: Backends,
+    power_preference: PowerPreference,
+    adapter_name: Option<String>,
+    fullscreen: Option<FullscreenMode>,
+    srgb: bool,
+    fixed_timestep: Option<FixedTimestep>,
+    frame_limiter: Option<FrameLimiter>,
+}
+
+impl StateBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            title: "Learn wgpu".to_owned(),
+            inner_size: None,
+            present_mode: None,
+            backends: Backends::all(),
+            power_preference: PowerPreference::HighPerformance,
+            adapter_name: None,
+            fullscreen: None,
+            srgb: true,
+            fixed_timestep: None,
+            frame_limiter: None,
+        }
+    }
+
+    /// The text in the title bar of the window
+    #[must_use]
+    pub fn title(mut self, title: &str) -> Self {
+        title.clone_into(&mut self.title);
+        self
+    }
+
+    /// The size of the inside of the window in pixels, the OS picks one if it isn't set
+    #[must_use]
+    pub const fn inner_size(mut self, width: u32, height: u32) -> Self {
+        self.inner_size = Some(PhysicalSize::new(width, height));
+        self
+    }
+
+    /// How frames are synced with the display.
+    /// Falls back to the first supported mode with a warning if the surface doesn't support it.
+    #[must_use]
+    pub const fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = Some(present_mode);
+        self
+    }
+
+    /// The graphics APIs wgpu is allowed to use.
+    /// The `WGPU_BACKEND` environment variable overrides them, see [`backends_from_env`].
+    #[must_use]
+    pub const fn backends(mut self, backends: Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    /// Whether a low power or a high performance adapter is preferred, high performance by
+    /// default. The `WGPU_POWER_PREF` environment variable (`low` or `high`) overrides it.
+    #[must_use]
+    pub const fn power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Uses the first adapter whose name contains the text, ignoring case, instead of the one
+    /// picked by the power preference. The `LEARN_WGPU_ADAPTER` environment variable overrides it.
+    /// [`Context::enumerate_adapters`] lists the names of the available adapters.
+    #[must_use]
+    pub fn adapter_name_contains(mut self, name: &str) -> Self {
+        self.adapter_name = Some(name.to_owned());
+        self
+    }
+
+    /// Starts the window in fullscreen. F11 switches between the window and this mode,
+    /// which is borderless fullscreen if it isn't set.
+    #[must_use]
+    pub const fn fullscreen(mut self, mode: FullscreenMode) -> Self {
+        self.fullscreen = Some(mode);
+        self
+    }
+
+    /// Whether an sRGB surface format is preferred, which is the default.
+    /// Disabling it forces a non-sRGB format if the surface supports one,
+    /// which is useful to check that colors are gamma corrected correctly.
+    #[must_use]
+    pub const fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Updates the app `ticks_per_second` times every second with the same time step, instead
+    /// of once per frame with the time since the previous frame.
+    /// [`Context::interpolation_alpha`] tells how far a frame is between two updates.
+    #[must_use]
+    pub fn fixed_timestep(mut self, ticks_per_second: u32) -> Self {
+        self.fixed_timestep = Some(FixedTimestep::new(ticks_per_second));
+        self
+    }
+
+    /// Renders at most `fps` frames per second, for present modes without vertical sync.
+    /// A frame rate that isn't positive doesn't limit anything.
+    /// [`Context::set_target_fps`] changes it while the app runs.
+    #[must_use]
+    pub fn target_fps(mut self, fps: f32) -> Self {
+        self.frame_limiter = FrameLimiter::new(fps);
+        self
+    }
+
+    /// Creates the window, and initializes the GPU to draw to it
+    ///
+    /// # Errors
+    /// Returns an error if the window, surface, adapter, or device couldn't be created
+    #[allow(clippy::future_not_send)]
+    pub async fn build<T>(self, event_loop: &EventLoop<T>) -> Result<Context, StateError> {
+        let mut window_builder = WindowBuilder::new().with_title(&self.title);
+        if let Some(size) = self.inner_size {
+            window_builder = window_builder.with_inner_size(size);
+        }
+        if let Some(mode) = self.fullscreen {
+            let monitor = event_loop
+                .primary_monitor()
+                .or_else(|| event_loop.available_monitors().next());
+            window_builder = window_builder.with_fullscreen(Some(mode.to_fullscreen(monitor)));
+        }
+        let window = window_builder.build(event_loop)?;
+        Context::new(window, &self).await
+    }
+
+    /// Initializes the GPU without a window, to render to textures instead of the screen.
+    /// The inner size is used as the size of the frames, 800x600 if it isn't set.
+    ///
+    /// # Errors
+    /// Returns an error if the adapter or device couldn't be created
+    #[allow(clippy::future_not_send)]
+    pub async fn build_headless(self) -> Result<Context, StateError> {
+        Context::new_headless(&self).await
+    }
+
+    /// The backends from `WGPU_BACKEND` if it's set to valid ones, the configured ones otherwise
+    fn selected_backends(&self) -> Backends {
+        backends_from_env().unwrap_or(self.backends)
+    }
+}
+
+impl Default for StateBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How the window covers the screen in fullscreen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// A window without borders covering the monitor, switching to it is fast
+    #[default]
+    Borderless,
+
+    /// The app takes over the monitor with the largest, fastest video mode it supports.
+    /// Falls back to borderless if the monitor or its video modes aren't known, like on the web.
+    Exclusive,
+}
+
+impl FullscreenMode {
+    /// The fullscreen setting of winit for this mode on the monitor
+    fn to_fullscreen(self, monitor: Option<MonitorHandle>) -> Fullscreen {
+        if self == Self::Exclusive {
+            // Prefer the highest resolution, then the highest refresh rate and bit depth
+            let best_mode = monitor.as_ref().and_then(|monitor| {
+                monitor.video_modes().max_by_key(|mode| {
+                    let size = mode.size();
+                    (
+                        u64::from(size.width) * u64::from(size.height),
+                        mode.refresh_rate_millihertz(),
+                        mode.bit_depth(),
+                    )
+                })
+            });
+            if let Some(mode) = best_mode {
+                return Fullscreen::Exclusive(mode);
+            }
+            log::warn!("No video modes found for exclusive fullscreen, using borderless");
+        }
+        Fullscreen::Borderless(monitor)
+    }
+}
+
+/// The surface doesn't support the requested present mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("the surface doesn't support {0:?}")]
+pub struct UnsupportedPresentMode(pub PresentMode);
+
+/// The present modes the V key cycles through: `VSync`, triple buffering, and no syncing at all
+const PRESENT_MODE_CYCLE: [PresentMode; 3] = [
+    PresentMode::Fifo,
+    PresentMode::Mailbox,
+    PresentMode::Immediate,
+];
+
+/// Picks the mode after `current` in [`PRESENT_MODE_CYCLE`] that is supported, wrapping around
+/// at the end. Fifo is supported everywhere, so it's used when no other mode is supported.
+fn next_present_mode(supported: &[PresentMode], current: PresentMode) -> PresentMode {
+    let start = PRESENT_MODE_CYCLE
+        .iter()
+        .position(|&mode| mode == current)
+        .map_or(0, |index| index + 1);
+    (0..PRESENT_MODE_CYCLE.len())
+        .map(|offset| PRESENT_MODE_CYCLE[(start + offset) % PRESENT_MODE_CYCLE.len()])
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(PresentMode::Fifo)
+}
+
+/// Reads the backends from the `WGPU_BACKEND` environment variable.
+/// Forcing a graphics API can work around driver bugs.
+///
+/// It accepts a comma separated list of `vulkan`, `dx12`, `dx11`, `metal`, `gl`, `webgpu`,
+/// `primary`, and `all`, ignoring case.
+///
+/// Returns `None` if the variable isn't set. An unrecognized value is logged as a warning
+/// and `None` is returned as well, so the backends of the builder are used.
+#[must_use]
+pub fn backends_from_env() -> Option<Backends> {
+    let value = std::env::var("WGPU_BACKEND").ok()?;
+
+    let mut backends = Backends::empty();
+    for name in value.split(',').map(str::trim) {
+        backends |= match name.to_lowercase().as_str() {
+            "vulkan" | "vk" => Backends::VULKAN,
+            "dx12" | "d3d12" => Backends::DX12,
+            "dx11" | "d3d11" => Backends::DX11,
+            "metal" | "mtl" => Backends::METAL,
+            "gl" | "gles" | "opengl" | "webgl" => Backends::GL,
+            "webgpu" => Backends::BROWSER_WEBGPU,
+            "primary" => Backends::PRIMARY,
+            "all" => Backends::all(),
+            _ => {
+                log::warn!("Unknown backend \"{name}\" in WGPU_BACKEND, using the default ones");
+                return None;
+            }
+        };
+    }
+    Some(backends)
+}
+
+/// Everything an app needs to draw to the window.
+/// A headless context doesn't have a window or surface, it can only render to textures.
+pub struct Context {
+    surface: Option<Surface>,
+
+    /// The GPU, kept to query which features and formats it supports
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+    pub config: SurfaceConfiguration,
+    pub size: PhysicalSize<u32>,
+
+    /// The window must be declared after the surface so
+    /// it gets dropped after after it as the surface contains
+    /// unsafe references to the window's resources.
+    window: Option<Window>,
+
+    /// Whether the window is fullscreen, and the fullscreen mode F11 switches to
+    is_fullscreen: bool,
+    fullscreen_mode: FullscreenMode,
+
+    /// Whether the window is minimized or completely covered, nothing is rendered then
+    is_minimized: bool,
+    is_occluded: bool,
+
+    /// Splits the frame time into updates of the same length, if it was enabled in the builder
+    pub fixed_timestep: Option<FixedTimestep>,
+
+    /// Waits out the rest of every frame to cap the frame rate, if there is a target frame rate
+    frame_limiter: Option<FrameLimiter>,
+
+    /// The times of the most recent frames, as the event loop measured them
+    frame_stats: FrameStats,
+}
+
+impl Context {
+    /// Creates the instance, and the surface of the window that will be drawn to.
+    /// The surface must not outlive the window.
+    fn create_surface(
+        window: &Window,
+        backends: Backends,
+    ) -> Result<(Instance, Surface), StateError> {
+        // The instance is a handle to our GPU
+        // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
+        let instance = Instance::new(InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+
+        // # Safety
+        //
+        // The surface needs to live as long as the window that created it.
+        // The context owns the window, so this should be safe.
+        // The surface is the part of the window we draw to.
+        let surface = unsafe { instance.create_surface(window) }?;
+        Ok((instance, surface))
+    }
+
+    /// Lists the name, backend, and device type of every adapter the backends can use
+    #[must_use]
+    pub fn enumerate_adapters(backends: Backends) -> Vec<AdapterInfo> {
+        let instance = Instance::new(InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        instance
+            .enumerate_adapters(backends)
+            .map(|adapter| adapter.get_info())
+            .collect()
+    }
+
+    /// Finds the first adapter whose name contains `name`, ignoring case.
+    /// Returns `None` if there is no such adapter, or it can't draw to the surface.
+    fn find_adapter_by_name(
+        instance: &Instance,
+        backends: Backends,
+        surface: Option<&Surface>,
+        name: &str,
+    ) -> Option<Adapter> {
+        let lowercase_name = name.to_lowercase();
+        let Some(adapter) = instance.enumerate_adapters(backends).find(|adapter| {
+            adapter
+                .get_info()
+                .name
+                .to_lowercase()
+                .contains(&lowercase_name)
+        }) else {
+            log::warn!("No adapter matches \"{name}\", using the default one");
+            return None;
+        };
+
+        if surface.is_some_and(|surface| !adapter.is_surface_supported(surface)) {
+            log::warn!(
+                "{} can't draw to the window, using the default adapter",
+                adapter.get_info().name
+            );
+            return None;
+        }
+        Some(adapter)
+    }
+
+    async fn create_adapter(
+        instance: &Instance,
+        surface: Option<&Surface>,
+        options: &StateBuilder,
+    ) -> Result<Adapter, StateError> {
+        // Let the user pick a GPU without recompiling, for example the integrated one on a laptop
+        let adapter_name = std::env::var("LEARN_WGPU_ADAPTER")
+            .ok()
+            .or_else(|| options.adapter_name.clone());
+        if let Some(adapter) = adapter_name.and_then(|name| {
+            Self::find_adapter_by_name(instance, options.selected_backends(), surface, &name)
+        }) {
+            let info = adapter.get_info();
+            log::info!("Using {} ({:?})", info.name, info.backend);
+            return Ok(adapter);
+        }
+        let power_preference =
+            wgpu::util::power_preference_from_env().unwrap_or(options.power_preference);
+
+        // Create an adapter to interact directly with the GPU
+        // You can also use enumerate_adapters to iterate through possible adapters
+        let request = |power_preference| {
+            instance.request_adapter(&RequestAdapterOptions {
+                // LowPower is favored when there is no HighPerformance option
+                power_preference,
+
+                // The adapter should be compatible with the selected surface, if there is one
+                compatible_surface: surface,
+
+                // Don't force an adapter, the application won't run without compatible hardware
+                force_fallback_adapter: false,
+            })
+        };
+        let mut adapter = request(power_preference).await;
+
+        // Some systems don't report a high performance adapter, try the low power one instead
+        if adapter.is_none() && power_preference == PowerPreference::HighPerformance {
+            log::warn!("No high performance adapter found, trying a low power one");
+            adapter = request(PowerPreference::LowPower).await;
+        }
+
+        let adapter = adapter.ok_or(StateError::NoAdapter)?;
+        let info = adapter.get_info();
+        log::info!("Using {} ({:?})", info.name, info.backend);
+        Ok(adapter)
+    }
+
+    async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), StateError> {
+        let mut features =
+            adapter.features() & (Features::POLYGON_MODE_LINE | Features::PUSH_CONSTANTS);
+
+        // OpenGL has no push constants, wgpu emulates them with uniforms which breaks easily.
+        // The draw constants are passed in a uniform buffer there instead.
+        if adapter.get_info().backend == Backend::Gl {
+            features.remove(Features::PUSH_CONSTANTS);
+        }
+
+        // 128 bytes of push constants are available everywhere they're supported
+        let max_push_constant_size = if features.contains(Features::PUSH_CONSTANTS) {
+            adapter.limits().max_push_constant_size.min(128)
+        } else {
+            0
+        };
+
+        Ok(adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: None,
+
+                    // Extra features, only the ones the adapter supports can be requested.
+                    // Drawing lines instead of filled triangles is used for wireframes,
+                    // push constants pass small data to the shaders without a buffer.
+                    features,
+
+                    // WebGL doesn't support all of wgpu's features, so if
+                    // we're building for the web, we'll have to disable some.
+                    limits: Limits {
+                        max_push_constant_size,
+                        ..if cfg!(target_arch = "wasm32") {
+                            Limits::downlevel_webgl2_defaults()
+                        } else {
+                            Limits::default()
+                        }
+                    },
+                },
+                None,
+            )
+            .await?)
+    }
+
+    /// Selects the format and modes for the surface
+    fn create_surface_config(
+        surface: &Surface,
+        adapter: &Adapter,
+        size: PhysicalSize<u32>,
+        requested_present_mode: Option<PresentMode>,
+        prefer_srgb: bool,
+    ) -> Result<SurfaceConfiguration, StateError> {
+        // Retrieve the capabilities of the surface
+        let surface_caps = surface.get_capabilities(adapter);
+
+        // An sRGB surface converts the linear colors of the shaders to sRGB when they're written.
+        // Without one, the colors would come out darker, so the app has to gamma correct them
+        // itself. Not every surface supports both, so fall back to the first format.
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb() == prefer_srgb)
+            .or_else(|| surface_caps.formats.first().copied())
+            .ok_or(StateError::UnsupportedSurface)?;
+
+        // A surface the adapter can't present to doesn't have any modes
+        let (Some(&present_mode), Some(&alpha_mode)) = (
+            surface_caps.present_modes.first(),
+            surface_caps.alpha_modes.first(),
+        ) else {
+            return Err(StateError::UnsupportedSurface);
+        };
+
+        // Configuring the surface with an unsupported present mode would panic
+        let present_mode = match requested_present_mode {
+            Some(mode) if surface_caps.present_modes.contains(&mode) => mode,
+            Some(mode) => {
+                log::warn!("{mode:?} isn't supported by the surface, using {present_mode:?}");
+                present_mode
+            }
+            None => present_mode,
+        };
+
+        Ok(SurfaceConfiguration {
+            // How surface textures will be used, in this case to write to the screen.
+            usage: TextureUsages::RENDER_ATTACHMENT,
+
+            // How surface textures will be stored on the GPU.
+            format: surface_format,
+
+            // The dimensions of the surface texture in pixels, should always be larger than 0.
+            width: size.width,
+            height: size.height,
+
+            // How to sync the surface with the display, the first option is used by default.
+            // PresentMode::Fifo will cap the display rate at the display's framerate (like VSync).
+            // PresentMode::Fifo is supported on all platforms.
+            // PresentMode::AutoVsync and PresentMode::AutoNoVsync have fallback support to work
+            // on all platforms.
+            // PresentMode can also be selected at run-time with surface_caps.present_modes.
+            present_mode,
+
+            // How the alpha modes will be handled during compositing.
+            alpha_mode,
+
+            // List of TextureFormats that can be used to create TextureViews
+            view_formats: vec![],
+        })
+    }
+
+    /// Creating some of the wgpu types requires async code.
+    /// Use [`StateBuilder`] to create the window as well.
+    ///
+    /// # Errors
+    /// Returns an error if no surface, adapter, device, or surface format could be created
+    pub async fn new(window: Window, options: &StateBuilder) -> Result<Self, StateError> {
+        let size = window.inner_size();
+        let (instance, surface) = Self::create_surface(&window, options.selected_backends())?;
+
+        // Create an adapter
+        let adapter = Self::create_adapter(&instance, Some(&surface), options).await?;
+
+        let (device, queue) = Self::request_device(&adapter).await?;
+
+        // Create a configuration for the surface.
+        // This will define how the surface creates its underlying surface textures.
+        let config = Self::create_surface_config(
+            &surface,
+            &adapter,
+            size,
+            options.present_mode,
+            options.srgb,
+        )?;
+
+        // Apply the configurations
+        surface.configure(&device, &config);
+
+        Ok(Self {
+            surface: Some(surface),
+            adapter,
+            device,
+            queue,
+            config,
+            size,
+            window: Some(window),
+            is_fullscreen: options.fullscreen.is_some(),
+            fullscreen_mode: options.fullscreen.unwrap_or_default(),
+            is_minimized: false,
+            is_occluded: false,
+            fixed_timestep: options.fixed_timestep,
+            frame_limiter: options.frame_limiter,
+            frame_stats: FrameStats::default(),
+        })
+    }
+
+    /// Initializes the GPU without a surface. The configuration describes the textures to
+    /// render to, so pipelines can be created the same way as for a window.
+    /// Use [`StateBuilder::build_headless`] to create it from the builder's options.
+    ///
+    /// # Errors
+    /// Returns an error if no adapter or device could be created
+    pub async fn new_headless(options: &StateBuilder) -> Result<Self, StateError> {
+        let size = options.inner_size.unwrap_or(PhysicalSize::new(800, 600));
+        let instance = Instance::new(InstanceDescriptor {
+            backends: options.selected_backends(),
+            ..Default::default()
+        });
+        let adapter = Self::create_adapter(&instance, None, options).await?;
+        let (device, queue) = Self::request_device(&adapter).await?;
+
+        // Every adapter can render to RGBA textures, and they can be read back without
+        // swapping channels. Present and alpha modes are ignored without a surface.
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format: if options.srgb {
+                TextureFormat::Rgba8UnormSrgb
+            } else {
+                TextureFormat::Rgba8Unorm
+            },
+            width: size.width,
+            height: size.height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Ok(Self {
+            surface: None,
+            adapter,
+            device,
+            queue,
+            config,
+            size,
+            window: None,
+            is_fullscreen: false,
+            fullscreen_mode: FullscreenMode::Borderless,
+            is_minimized: false,
+            is_occluded: false,
+            fixed_timestep: options.fixed_timestep,
+            frame_limiter: options.frame_limiter,
+            frame_stats: FrameStats::default(),
+        })
+    }
+
+    /// How far the current frame is between the previous and the next fixed update, from
+    /// 0.0 to 1.0. Always 1.0 without a fixed timestep, as every frame is updated.
+    #[must_use]
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.fixed_timestep
+            .as_ref()
+            .map_or(1.0, FixedTimestep::alpha)
+    }
+
+    /// The window that is drawn to, `None` if the context is headless
+    pub const fn window(&self) -> Option<&Window> {
+        self.window.as_ref()
+    }
+
+    /// Whether the window can be seen, so it's worth rendering.
+    /// A headless context is always visible, as it renders to textures.
+    #[must_use]
+    pub const fn is_visible(&self) -> bool {
+        !self.is_minimized && !self.is_occluded
+    }
+
+    /// The most frames per second that are rendered, `None` if the frame rate isn't capped
+    #[must_use]
+    pub fn target_fps(&self) -> Option<f32> {
+        self.frame_limiter.as_ref().map(FrameLimiter::fps)
+    }
+
+    /// Caps the frame rate at `fps` frames per second, or stops capping it with `None`.
+    /// A frame rate that isn't positive doesn't limit anything either.
+    pub fn set_target_fps(&mut self, fps: Option<f32>) {
+        self.frame_limiter = fps.and_then(FrameLimiter::new);
+    }
+
+    /// Waits until the frame that started at `frame_start` took as long as the target frame
+    /// rate allows. Does nothing without a target frame rate, or on the web, where the browser
+    /// decides when frames are drawn and the page can't be blocked.
+    pub fn limit_frame_rate(&self, frame_start: Instant) {
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+        if let Some(limiter) = &self.frame_limiter {
+            limiter.wait(frame_start);
+        }
+    }
+
+    /// The frame times of the last frames, to show or log how smooth rendering is.
+    /// Headless contexts only have the frames that were recorded with [`Self::record_frame`].
+    #[must_use]
+    pub const fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// Records a frame that took `frame_time`, the event loop records every frame it renders
+    pub fn record_frame(&mut self, frame_time: Duration) {
+        self.frame_stats.record(frame_time);
+    }
+
+    /// Marks the window as completely covered by other windows or not
+    pub const fn set_occluded(&mut self, occluded: bool) {
+        self.is_occluded = occluded;
+    }
+
+    /// Reconfigures the surface for the new size of the window.
+    /// Returns whether the size changed, a size of 0 is ignored as the surface can't be empty.
+    /// The window is minimized until it gets a size again.
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) -> bool {
+        self.is_minimized = new_size.width == 0 || new_size.height == 0;
+        if self.is_minimized {
+            return false;
+        }
+
+        // Store the new size
+        self.size = new_size;
+        self.config.width = new_size.width;
+
+        // Reconfigure the surface for the new size
+        self.config.height = new_size.height;
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+        true
+    }
+
+    /// Switches between a window and fullscreen on the current monitor.
+    /// The window gets a Resized event afterwards, which resizes the surface.
+    pub fn toggle_fullscreen(&mut self) {
+        if let Some(window) = &self.window {
+            self.is_fullscreen = !self.is_fullscreen;
+            window.set_fullscreen(
+                self.is_fullscreen
+                    .then(|| self.fullscreen_mode.to_fullscreen(window.current_monitor())),
+            );
+        }
+    }
+
+    /// Reconfigures the surface to sync frames with the display in a different way.
+    /// The mode is stored in the surface configuration, so resizing keeps it.
+    ///
+    /// # Errors
+    /// Returns an error if the surface doesn't support the mode or the context is headless,
+    /// the old mode is kept
+    pub fn set_present_mode(&mut self, mode: PresentMode) -> Result<(), UnsupportedPresentMode> {
+        let Some(surface) = &self.surface else {
+            return Err(UnsupportedPresentMode(mode));
+        };
+        if !surface
+            .get_capabilities(&self.adapter)
+            .present_modes
+            .contains(&mode)
+        {
+            return Err(UnsupportedPresentMode(mode));
+        }
+
+        self.config.present_mode = mode;
+        surface.configure(&self.device, &self.config);
+        Ok(())
+    }
+
+    /// Switches to the next supported mode out of Fifo, Mailbox, and Immediate
+    pub fn cycle_present_mode(&mut self) {
+        let supported = self.surface.as_ref().map_or_else(Vec::new, |surface| {
+            surface.get_capabilities(&self.adapter).present_modes
+        });
+        let mode = next_present_mode(&supported, self.config.present_mode);
+        match self.set_present_mode(mode) {
+            Ok(()) => log::info!("Present mode: {mode:?}"),
+            Err(error) => log::warn!("{error}"),
+        }
+    }
+
+    /// Lets the app draw a frame to the next surface texture, and displays it
+    ///
+    /// # Errors
+    /// Returns an error if no render surface could be retrieved,
+    /// a headless context reports its surface as lost
+    pub fn render_frame(&mut self, app: &mut impl App) -> Result<(), SurfaceError> {
+        // Wait for the surface to provide a surface texture to render to
+        let output = self
+            .surface
+            .as_ref()
+            .ok_or(SurfaceError::Lost)?
+            .get_current_texture()?;
+
+        // Create a texture view with default settings.
+        let view = output
+            .texture
+            .create_view(&TextureViewDescriptor::default());
+
+        // Create a command encoder to create the actual commands to send to the gpu.
+        // The encoder builds a command buffer that we can then send to the GPU.
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        app.render(self, &view, &mut encoder);
+
+        // Submit will accept anything that implements IntoIter.
+        // Send the render pass(es) to the GPU
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // Display the image
+        output.present();
+
+        Ok(())
+    }
+}
+
+/// An application that draws to the window, driven by [`crate::run_app`]
+pub trait App {
+    /// Called once after the app was created, before the first frame.
+    /// Creating the app can't wait for resources that are fetched, like on the web,
+    /// so they're loaded here instead.
+    fn load<'a>(&'a mut self, _ctx: &'a Context) -> impl Future<Output = ()> + 'a {
+        async {}
+    }
+
+    /// Called once per frame before rendering, `dt` is the time since the previous frame.
+    /// With a fixed timestep, it's called as often as needed to keep up with the tick rate,
+    /// and `dt` is always the length of a tick.
+    fn update(&mut self, ctx: &mut Context, dt: Duration);
+
+    /// Records the commands to draw a frame to the view of the surface texture
+    fn render(&mut self, ctx: &mut Context, view: &TextureView, encoder: &mut CommandEncoder);
+
+    /// Handles a window event. Returns whether the event was used, unused events are
+    /// handled by the event loop (closing the window, resizing).
+    fn input(&mut self, ctx: &mut Context, event: &WindowEvent) -> bool;
+
+    /// Handles raw device input, like mouse movement. Returns whether the event was used.
+    fn device_input(&mut self, _ctx: &mut Context, _event: &DeviceEvent) -> bool {
+        false
+    }
+
+    /// Called after the surface was resized, to recreate anything that depends on its size
+    fn resize(&mut self, _ctx: &mut Context) {}
+
+    /// Handles a window event the way [`crate::run_app`] does, so the app can be driven by
+    /// another event loop. The app gets to use the event first with [`Self::input`],
+    /// otherwise resizing, rescaling, and covering the window are handled here.
+    /// Returns whether the app should exit, because closing was requested or escape was pressed.
+    fn handle_window_event(&mut self, ctx: &mut Context, event: &WindowEvent) -> bool
+    where
+        Self: Sized,
+    {
+        // The app's inputs come first
+        if self.input(ctx, event) {
+            return false;
+        }
+
+        // Check what event happened
+        match event {
+            // If the window resized, update the states size
+            WindowEvent::Resized(physical_size) => crate::resize(ctx, self, *physical_size),
+
+            // If the scale factor changed, update the states size
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                crate::resize(ctx, self, **new_inner_size);
+            }
+
+            // If the window got covered by other windows or uncovered
+            WindowEvent::Occluded(occluded) => ctx.set_occluded(*occluded),
+
+            // If close was requested or escape was pressed, close the application
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                ..
+            } => return true,
+            _ => {}
+        }
+        false
+    }
+}
@@ -0,0 +1,387 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferBindingType, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    FilterMode, FragmentState, LoadOp, MultisampleState, Operations, PipelineLayout,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
+    SamplerDescriptor, ShaderModule, ShaderStages, StoreOp, SurfaceConfiguration, TextureFormat,
+    TextureSampleType, TextureView, TextureViewDimension, VertexState,
+};
+
+use crate::{entry_points, texture::Texture};
+
+/// How the bright parts of the scene glow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// How bright a pixel has to be to glow, from 0 for every pixel to 1 for only white ones
+    pub threshold: f32,
+
+    /// How far the glow spreads, as the distance between the samples of the blur in texels
+    pub strength: f32,
+}
+
+impl BloomSettings {
+    /// The lowest and highest threshold that make sense
+    pub const THRESHOLD_RANGE: (f32, f32) = (0.0, 1.0);
+
+    /// Beyond this, the samples are so far apart that the blur turns into copies of the glow
+    pub const STRENGTH_RANGE: (f32, f32) = (0.0, 4.0);
+
+    /// Clamps both settings to their ranges
+    #[must_use]
+    pub const fn clamped(self) -> Self {
+        Self {
+            threshold: self
+                .threshold
+                .clamp(Self::THRESHOLD_RANGE.0, Self::THRESHOLD_RANGE.1),
+            strength: self
+                .strength
+                .clamp(Self::STRENGTH_RANGE.0, Self::STRENGTH_RANGE.1),
+        }
+    }
+}
+
+/// Lets the light and the brightest parts of the textures glow a little
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.6,
+            strength: 1.5,
+        }
+    }
+}
+
+/// The settings as they're stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct BloomUniform {
+    threshold: f32,
+    strength: f32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [f32; 2],
+}
+
+impl From<BloomSettings> for BloomUniform {
+    fn from(settings: BloomSettings) -> Self {
+        Self {
+            threshold: settings.threshold,
+            strength: settings.strength,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// The textures the bloom is rendered to for a target of a certain size.
+///
+/// They have half the resolution of the target, which makes the blur cheaper and wider.
+/// Replacing it drops the old textures, so they're freed when the target is resized.
+pub struct BloomTarget {
+    /// The bright parts, and the blur passes ping-pong between both textures.
+    /// After the vertical blur, the glow is in the first one again.
+    textures: [Texture; 2],
+
+    /// Reads the scene in the bright pass and the composite
+    scene_bind_group: BindGroup,
+
+    /// Read the textures in the same order
+    bind_groups: [BindGroup; 2],
+}
+
+/// Makes the bright parts of the scene glow: they're blurred in separate passes and added
+/// on top of the scene. Every pass draws a single triangle that covers its target.
+pub struct Bloom {
+    texture_bind_group_layout: BindGroupLayout,
+
+    /// Interpolates between the texels, which smooths the downsampling and the blur
+    sampler: Sampler,
+    settings: BloomSettings,
+    uniform_buffer: Buffer,
+    uniform_bind_group: BindGroup,
+    bright_pipeline: RenderPipeline,
+    blur_horizontal_pipeline: RenderPipeline,
+    blur_vertical_pipeline: RenderPipeline,
+    composite_pipeline: RenderPipeline,
+}
+
+impl Bloom {
+    /// Creates the pipelines of every pass, the composite pass renders to the given format
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        // A texture and its sampler, for the input of every pass and the glow in the composite
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Bloom Texture Bind Group Layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let settings = BloomSettings::default();
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Bloom Buffer"),
+            contents: bytemuck::cast_slice(&[BloomUniform::from(settings)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Bloom Uniform Bind Group Layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let uniform_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Bloom Uniform Bind Group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Bloom Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // The bright pass and the blur passes read one texture,
+        // the composite pass reads the scene and the glow
+        let shader = device.create_shader_module(wgpu::include_wgsl!("bloom.wgsl"));
+        let pass_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&texture_bind_group_layout, &uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let composite_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Bloom Composite Pipeline Layout"),
+            bind_group_layouts: &[
+                &texture_bind_group_layout,
+                &uniform_bind_group_layout,
+                &texture_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |layout, entry_point| {
+            Self::create_pipeline(device, layout, &shader, entry_point, format)
+        };
+
+        Self {
+            bright_pipeline: create_pipeline(&pass_layout, entry_points::BLOOM_BRIGHT),
+            blur_horizontal_pipeline: create_pipeline(
+                &pass_layout,
+                entry_points::BLOOM_BLUR_HORIZONTAL,
+            ),
+            blur_vertical_pipeline: create_pipeline(
+                &pass_layout,
+                entry_points::BLOOM_BLUR_VERTICAL,
+            ),
+            composite_pipeline: create_pipeline(&composite_layout, entry_points::BLOOM_COMPOSITE),
+            texture_bind_group_layout,
+            sampler,
+            settings,
+            uniform_buffer,
+            uniform_bind_group,
+        }
+    }
+
+    /// Creates the pipeline of a pass, it draws a triangle that covers the screen,
+    /// so it doesn't need a vertex buffer or a depth buffer
+    fn create_pipeline(
+        device: &Device,
+        layout: &PipelineLayout,
+        shader: &ShaderModule,
+        entry_point: &str,
+        format: TextureFormat,
+    ) -> RenderPipeline {
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: entry_points::VERTEX,
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point,
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// Binds a texture with the linear sampler, to be read by a pass
+    fn create_bind_group(&self, device: &Device, view: &TextureView, label: &str) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some(label),
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    /// Creates the textures for a target with the size and format of the configuration,
+    /// the scene is read from the given view
+    #[must_use]
+    pub fn create_target(
+        &self,
+        device: &Device,
+        config: &SurfaceConfiguration,
+        scene: &TextureView,
+    ) -> BloomTarget {
+        // Half the resolution, but at least a pixel for tiny windows
+        let half = SurfaceConfiguration {
+            width: (config.width / 2).max(1),
+            height: (config.height / 2).max(1),
+            ..config.clone()
+        };
+        let textures = [
+            Texture::create_render_target(device, &half, config.format, "Bloom Texture 0"),
+            Texture::create_render_target(device, &half, config.format, "Bloom Texture 1"),
+        ];
+        let bind_groups = [
+            self.create_bind_group(device, &textures[0].view, "Bloom Bind Group 0"),
+            self.create_bind_group(device, &textures[1].view, "Bloom Bind Group 1"),
+        ];
+        BloomTarget {
+            scene_bind_group: self.create_bind_group(device, scene, "Bloom Scene Bind Group"),
+            bind_groups,
+            textures,
+        }
+    }
+
+    /// The current threshold and strength
+    #[must_use]
+    pub const fn settings(&self) -> BloomSettings {
+        self.settings
+    }
+
+    /// Changes the threshold and strength, and uploads them to the GPU.
+    /// Settings outside of their ranges are clamped.
+    pub fn set_settings(&mut self, queue: &Queue, settings: BloomSettings) {
+        self.settings = settings.clamped();
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[BloomUniform::from(self.settings)]),
+        );
+    }
+
+    /// Extracts the bright parts of the scene in the target, blurs them, and draws the scene
+    /// with the glow on top to the output
+    pub fn render(&self, encoder: &mut CommandEncoder, target: &BloomTarget, output: &TextureView) {
+        let [first, second] = &target.textures;
+        let [first_bind_group, second_bind_group] = &target.bind_groups;
+
+        // Scene -> first -> second -> first -> output
+        self.draw_pass(
+            encoder,
+            &self.bright_pipeline,
+            &target.scene_bind_group,
+            None,
+            &first.view,
+        );
+        self.draw_pass(
+            encoder,
+            &self.blur_horizontal_pipeline,
+            first_bind_group,
+            None,
+            &second.view,
+        );
+        self.draw_pass(
+            encoder,
+            &self.blur_vertical_pipeline,
+            second_bind_group,
+            None,
+            &first.view,
+        );
+        self.draw_pass(
+            encoder,
+            &self.composite_pipeline,
+            &target.scene_bind_group,
+            Some(first_bind_group),
+            output,
+        );
+    }
+
+    /// Draws a pass that reads the input, and the glow for the composite, to the output
+    fn draw_pass(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        input: &BindGroup,
+        glow: Option<&BindGroup>,
+        output: &TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Bloom Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+
+                // Every pixel is overwritten, so there's nothing to clear
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, input, &[]);
+        render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        if let Some(glow) = glow {
+            render_pass.set_bind_group(2, glow, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,91 @@
+// Bloom: the bright parts of the scene are copied to a texture with half the resolution,
+// blurred horizontally and vertically, and added on top of the scene, so they seem to glow
+
+// Will store the output of the vertex shader
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+
+    // Where to read the input texture, from (0, 0) in the top left to (1, 1) in the bottom right
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// The same triangle that covers the whole screen as in post_process.wgsl
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput{
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+// The texture the pass reads: the scene for the bright pass and the composite,
+// the output of the previous pass for the blur passes
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+// The settings of the bloom, as stored in `BloomUniform`
+struct BloomUniform{
+    // How bright a pixel has to be to glow, between 0 and 1
+    threshold: f32,
+
+    // How far the glow spreads, in texels of the blurred texture between the samples
+    strength: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> bloom: BloomUniform;
+
+// The blurred bright parts, only used by the composite pass
+@group(2) @binding(0)
+var t_bloom: texture_2d<f32>;
+@group(2) @binding(1)
+var s_bloom: sampler;
+
+// Keeps the part of the color above the threshold, so pixels start glowing gradually
+@fragment
+fn fs_bright(in: VertexOutput) -> @location(0) vec4<f32>{
+    let color = textureSample(t_input, s_input, in.tex_coords).rgb;
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let factor = max(luminance - bloom.threshold, 0.0) / max(luminance, 0.0001);
+    return vec4<f32>(color * factor, 1.0);
+}
+
+// Blurs the input in one direction, the blur is separable, so blurring horizontally and then
+// vertically looks the same as blurring in both directions at once, with far fewer samples
+fn blur(tex_coords: vec2<f32>, direction: vec2<f32>) -> vec4<f32>{
+    let texel = direction * bloom.strength / vec2<f32>(textureDimensions(t_input));
+
+    // The weights of a Gaussian blur with 9 samples, the center sample and 4 on each side.
+    // It's a variable, as constant arrays can't be indexed by the loop counter.
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+    var color = textureSample(t_input, s_input, tex_coords).rgb * weights[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = texel * f32(i);
+        color += textureSample(t_input, s_input, tex_coords + offset).rgb * weights[i];
+        color += textureSample(t_input, s_input, tex_coords - offset).rgb * weights[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_blur_horizontal(in: VertexOutput) -> @location(0) vec4<f32>{
+    return blur(in.tex_coords, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_vertical(in: VertexOutput) -> @location(0) vec4<f32>{
+    return blur(in.tex_coords, vec2<f32>(0.0, 1.0));
+}
+
+// Adds the glow on top of the scene
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32>{
+    let scene = textureSample(t_input, s_input, in.tex_coords);
+    let glow = textureSample(t_bloom, s_bloom, in.tex_coords).rgb;
+    return vec4<f32>(scene.rgb + glow, scene.a);
+}
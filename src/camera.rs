@@ -0,0 +1,546 @@
+use bytemuck::{Pod, Zeroable};
+use std::time::Duration;
+
+use cgmath::{
+    Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector2, Vector3, Vector4,
+};
+use winit::event::MouseScrollDelta;
+
+/// Converts OpenGL's normalized device coordinates to wgpu's.
+///
+/// cgmath is built for OpenGL, where the depth of normalized device coordinates
+/// ranges from -1.0 to 1.0. wgpu uses 0.0 to 1.0 instead, so this matrix scales and
+/// translates the depth to the range wgpu expects.
+/// The matrix is written column by column, so `z' = 0.5 * z + 0.5 * w`.
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// Calculates the aspect ratio of a surface with the given size
+#[allow(clippy::cast_precision_loss)]
+#[must_use]
+pub fn aspect_ratio(width: u32, height: u32) -> f32 {
+    // Surface sizes are much smaller than 2^23, so no precision is lost in practice
+    width as f32 / height as f32
+}
+
+/// The highest the camera can look up or down, just below 90 degrees
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.001;
+
+/// How close and how far the camera can zoom to its target.
+/// The far limit keeps the scene well within the far plane of the camera.
+const MIN_ZOOM_DISTANCE: f32 = 1.0;
+const MAX_ZOOM_DISTANCE: f32 = 50.0;
+
+/// Trackpads scroll in pixels, wheels in lines. A line is about this many pixels.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// How the camera flattens the scene onto the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Things further away look smaller, like in the real world.
+    /// The vertical field of view is in degrees.
+    Perspective { fovy: f32, znear: f32, zfar: f32 },
+
+    /// Things look as big at every distance, like in a technical drawing.
+    /// The height is how much of the scene fits vertically, in world units.
+    Orthographic { height: f32, znear: f32, zfar: f32 },
+}
+
+impl Projection {
+    /// Calculates the matrix that projects view coordinates to OpenGL's clip coordinates,
+    /// for a view with the given width divided by the height
+    #[must_use]
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Self::Perspective { fovy, znear, zfar } => {
+                cgmath::perspective(Deg(fovy), aspect, znear, zfar)
+            }
+            Self::Orthographic {
+                height,
+                znear,
+                zfar,
+            } => {
+                // The width follows from the aspect ratio, just like with a perspective
+                let (half_width, half_height) = (height * aspect / 2.0, height / 2.0);
+                cgmath::ortho(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    znear,
+                    zfar,
+                )
+            }
+        }
+    }
+
+    /// Switches between a perspective and an orthographic projection. Things at `distance`
+    /// from the camera stay the same size, so the view doesn't jump.
+    #[must_use]
+    pub fn toggled(self, distance: f32) -> Self {
+        match self {
+            Self::Perspective { fovy, znear, zfar } => Self::Orthographic {
+                height: 2.0 * distance * (Rad::from(Deg(fovy)).0 / 2.0).tan(),
+                znear,
+                zfar,
+            },
+            Self::Orthographic {
+                height,
+                znear,
+                zfar,
+            } => Self::Perspective {
+                fovy: Deg::from(Rad(2.0 * (height / 2.0 / distance).atan())).0,
+                znear,
+                zfar,
+            },
+        }
+    }
+}
+
+/// A camera looking at a point in the scene
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The position of the camera
+    pub eye: Point3<f32>,
+
+    /// The point the camera looks at
+    pub target: Point3<f32>,
+
+    /// Which direction is up for the camera
+    pub up: Vector3<f32>,
+
+    /// The width of the view divided by the height, it's used by both projections
+    pub aspect: f32,
+
+    /// How the view is projected, anything closer than its znear or further away than its zfar
+    /// won't be rendered
+    pub projection: Projection,
+}
+
+impl Camera {
+    /// Creates a camera above and in front of the origin, looking at the origin
+    #[must_use]
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            eye: (0.0, 5.0, 10.0).into(),
+            target: (0.0, 0.0, 0.0).into(),
+            up: Vector3::unit_y(),
+            aspect,
+            projection: Projection::Perspective {
+                fovy: 45.0,
+                znear: 0.1,
+                zfar: 100.0,
+            },
+        }
+    }
+
+    /// Switches between a perspective and an orthographic projection,
+    /// the target stays the same size
+    pub fn toggle_projection(&mut self) {
+        self.projection = self
+            .projection
+            .toggled((self.target - self.eye).magnitude());
+    }
+
+    /// Calculates the matrix that transforms world coordinates to clip coordinates
+    #[must_use]
+    pub fn build_view_projection_matrix(&self) -> Matrix4<f32> {
+        // The view matrix moves the world to be at the position and rotation of the camera
+        let view = Matrix4::look_at_rh(self.eye, self.target, self.up);
+
+        // The projection matrix warps the scene to give the effect of depth, or flattens it.
+        // Both are built for OpenGL, so they need the depth correction.
+        let proj = self.projection.matrix(self.aspect);
+
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+
+    /// Like [`Self::build_view_projection_matrix`], but without moving the world to the camera.
+    /// Only the rotation is left, so things infinitely far away like the sky stay put when the
+    /// camera moves, but turn along when it looks around.
+    #[must_use]
+    pub fn build_rotation_projection_matrix(&self) -> Matrix4<f32> {
+        let view = Matrix4::look_to_rh(Point3::origin(), self.target - self.eye, self.up);
+        OPENGL_TO_WGPU_MATRIX * self.projection.matrix(self.aspect) * view
+    }
+
+    /// The ray from the camera through a point of the view, in normalized device coordinates:
+    /// x goes from -1.0 on the left to 1.0 on the right, y from -1.0 at the bottom to 1.0 at
+    /// the top. Returns where the ray starts on the near plane, and its direction.
+    #[must_use]
+    pub fn ray(&self, x: f32, y: f32) -> (Point3<f32>, Vector3<f32>) {
+        // Undo the projection of the points with that position on the near and the far plane
+        let inverse = self
+            .build_view_projection_matrix()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+        let unproject = |depth| Point3::from_homogeneous(inverse * Vector4::new(x, y, depth, 1.0));
+        let near = unproject(0.0);
+        (near, (unproject(1.0) - near).normalize())
+    }
+}
+
+/// The camera data as it is stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct CameraUniform {
+    /// The position of the camera, to calculate the direction light is reflected in.
+    /// Stored as a `vec4`, as a `vec3` would need padding to 16 bytes anyway.
+    pub view_position: [f32; 4],
+
+    /// bytemuck can't cast cgmath types, so the matrix is stored as arrays
+    pub view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    /// Creates a uniform that doesn't transform anything
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            view_position: [0.0; 4],
+            view_proj: Matrix4::identity().into(),
+        }
+    }
+
+    /// Stores the current position and view projection matrix of the camera
+    pub fn update_view_proj(&mut self, camera: &Camera) {
+        self.view_position = camera.eye.to_homogeneous().into();
+        self.view_proj = camera.build_view_projection_matrix().into();
+    }
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A direction the camera moves in, relative to where it's looking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Forward,
+    Backward,
+    Left,
+    Right,
+}
+
+/// Moves a camera around its target based on the keys that are held down
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct CameraController {
+    /// How far the camera moves every second
+    pub speed: f32,
+
+    /// How many radians the camera rotates per unit of mouse movement
+    pub sensitivity: f32,
+
+    /// How far the camera moves towards its target per line scrolled
+    pub zoom_sensitivity: f32,
+
+    /// The mouse movement since the last update, in unspecified units
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+
+    /// The mouse movement while dragging since the last update, to orbit around the target
+    orbit_horizontal: f32,
+    orbit_vertical: f32,
+
+    /// The lines scrolled since the last update, positive is towards the target
+    scroll: f32,
+    is_forward_pressed: bool,
+    is_backward_pressed: bool,
+    is_left_pressed: bool,
+    is_right_pressed: bool,
+}
+
+impl CameraController {
+    #[must_use]
+    pub const fn new(speed: f32, sensitivity: f32) -> Self {
+        Self {
+            speed,
+            sensitivity,
+            zoom_sensitivity: 0.5,
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            orbit_horizontal: 0.0,
+            orbit_vertical: 0.0,
+            scroll: 0.0,
+            is_forward_pressed: false,
+            is_backward_pressed: false,
+            is_left_pressed: false,
+            is_right_pressed: false,
+        }
+    }
+
+    /// Starts or stops moving the camera in a direction, in the next updates.
+    /// Releasing a key should stop the movement, so the camera doesn't keep going.
+    pub const fn set_moving(&mut self, direction: Direction, is_moving: bool) {
+        match direction {
+            Direction::Forward => self.is_forward_pressed = is_moving,
+            Direction::Backward => self.is_backward_pressed = is_moving,
+            Direction::Left => self.is_left_pressed = is_moving,
+            Direction::Right => self.is_right_pressed = is_moving,
+        }
+    }
+
+    /// Stores raw mouse movement, so the camera can look around in the next update
+    pub fn process_mouse(&mut self, dx: f64, dy: f64) {
+        // Mouse movement is small enough to fit in an f32
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.rotate_horizontal += dx as f32;
+            self.rotate_vertical += dy as f32;
+        }
+    }
+
+    /// Stores raw mouse movement while dragging, so the camera orbits its target in the next update
+    pub fn process_drag(&mut self, dx: f64, dy: f64) {
+        // Mouse movement is small enough to fit in an f32
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.orbit_horizontal += dx as f32;
+            self.orbit_vertical += dy as f32;
+        }
+    }
+
+    /// Stores the scrolled distance, so the camera zooms in the next update.
+    /// Both mouse wheels (lines) and trackpads (pixels) are supported.
+    pub fn process_scroll(&mut self, delta: &MouseScrollDelta) {
+        // Scrolled distances are small enough to fit in an f32
+        #[allow(clippy::cast_possible_truncation)]
+        match delta {
+            MouseScrollDelta::LineDelta(_, lines) => self.scroll += lines,
+            MouseScrollDelta::PixelDelta(position) => {
+                self.scroll += (position.y / PIXELS_PER_LINE) as f32;
+            }
+        }
+    }
+
+    /// Moves the camera in the directions of the keys that are held down.
+    /// The distance moved is scaled by the time since the last update, so the
+    /// speed doesn't depend on the frame rate.
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        // Combine the held keys into a direction, opposite keys cancel each other out
+        let mut direction = Vector2::new(
+            axis(self.is_right_pressed, self.is_left_pressed),
+            axis(self.is_forward_pressed, self.is_backward_pressed),
+        );
+
+        // Moving diagonally shouldn't be faster than moving straight
+        if direction.magnitude2() > 1.0 {
+            direction = direction.normalize();
+        }
+
+        // The distance to move in this update
+        let amount = direction * self.speed * dt.as_secs_f32();
+
+        let forward = camera.target - camera.eye;
+        let forward_norm = forward.normalize();
+        let forward_mag = forward.magnitude();
+
+        // Move towards or away from the target.
+        // Stop before reaching the target, as the camera would glitch when it's in the center.
+        if amount.y < 0.0 || forward_mag > amount.y {
+            camera.eye += forward_norm * amount.y;
+        }
+
+        // The right direction is perpendicular to the forward and up direction
+        let right = forward_norm.cross(camera.up);
+
+        // Recalculate the distance in case the camera moved forward or backward
+        let forward = camera.target - camera.eye;
+        let forward_mag = forward.magnitude();
+
+        // Rotate around the target, while keeping the same distance to it
+        if amount.x != 0.0 {
+            camera.eye = camera.target - (forward + right * amount.x).normalize() * forward_mag;
+        }
+
+        self.orbit(camera);
+        self.zoom(camera);
+        self.look_around(camera);
+    }
+
+    /// Moves the camera towards or away from its target by the scrolled distance
+    fn zoom(&mut self, camera: &mut Camera) {
+        if self.scroll == 0.0 {
+            return;
+        }
+
+        // Keep the camera in front of the target, and the target within view
+        let offset = camera.eye - camera.target;
+        let distance = self
+            .scroll
+            .mul_add(-self.zoom_sensitivity, offset.magnitude())
+            .clamp(MIN_ZOOM_DISTANCE, MAX_ZOOM_DISTANCE);
+        camera.eye = camera.target + offset.normalize() * distance;
+
+        // The scrolling has been used
+        self.scroll = 0.0;
+    }
+
+    /// Moves the camera around its target by the mouse movement while dragging,
+    /// keeping the same distance to the target
+    fn orbit(&mut self, camera: &mut Camera) {
+        if self.orbit_horizontal == 0.0 && self.orbit_vertical == 0.0 {
+            return;
+        }
+
+        // Convert the position relative to the target to a yaw, a pitch, and a radius
+        let offset = camera.eye - camera.target;
+        let radius = offset.magnitude();
+        let yaw = offset.z.atan2(offset.x);
+        let pitch = (offset.y / radius).asin();
+
+        // Dragging right moves the camera right around the target, dragging down moves it up.
+        // The pitch is kept just below the poles, as the camera would flip over there.
+        let yaw = self.orbit_horizontal.mul_add(-self.sensitivity, yaw);
+        let pitch = self
+            .orbit_vertical
+            .mul_add(self.sensitivity, pitch)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+
+        // Place the camera at the new angles, still looking at the target
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let direction = Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw);
+        camera.eye = camera.target + direction * radius;
+
+        // The movement has been used
+        self.orbit_horizontal = 0.0;
+        self.orbit_vertical = 0.0;
+    }
+
+    /// Rotates the camera around its own position by the mouse movement since the last update
+    fn look_around(&mut self, camera: &mut Camera) {
+        if self.rotate_horizontal == 0.0 && self.rotate_vertical == 0.0 {
+            return;
+        }
+
+        // Convert the view direction to a yaw (around the y-axis) and a pitch (up and down)
+        let forward = camera.target - camera.eye;
+        let distance = forward.magnitude();
+        let direction = forward / distance;
+        let yaw = direction.z.atan2(direction.x);
+        let pitch = direction.y.asin();
+
+        // Moving the mouse right turns right, moving it down looks down.
+        // The pitch is kept just below straight up or down, as the camera would flip over there.
+        let yaw = self.rotate_horizontal.mul_add(self.sensitivity, yaw);
+        let pitch = self
+            .rotate_vertical
+            .mul_add(-self.sensitivity, pitch)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+
+        // Look in the new direction, at the same distance as before
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let direction = Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw);
+        camera.target = camera.eye + direction * distance;
+
+        // The movement has been used
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+    }
+}
+
+/// Converts a pair of opposite keys to a direction on an axis: 1.0, -1.0, or 0.0
+const fn axis(positive: bool, negative: bool) -> f32 {
+    match (positive, negative) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Widescreen and the older, squarer screens
+    const ASPECT_RATIOS: [f32; 2] = [16.0 / 9.0, 4.0 / 3.0];
+
+    /// Projects a point in the world to normalized device coordinates
+    fn project(camera: &Camera, point: Point3<f32>) -> Point3<f32> {
+        Point3::from_homogeneous(camera.build_view_projection_matrix() * point.to_homogeneous())
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{actual} isn't close to {expected}"
+        );
+    }
+
+    /// The camera with both projections, for every aspect ratio
+    fn cameras() -> impl Iterator<Item = Camera> {
+        ASPECT_RATIOS.into_iter().flat_map(|aspect| {
+            let perspective = Camera::new(aspect);
+            let mut orthographic = perspective;
+            orthographic.toggle_projection();
+            [perspective, orthographic]
+        })
+    }
+
+    #[test]
+    fn aspect_ratio_divides_width_by_height() {
+        assert_close(aspect_ratio(1920, 1080), 16.0 / 9.0);
+        assert_close(aspect_ratio(1024, 768), 4.0 / 3.0);
+    }
+
+    #[test]
+    fn target_stays_centered() {
+        for camera in cameras() {
+            let target = project(&camera, camera.target);
+            assert_close(target.x, 0.0);
+            assert_close(target.y, 0.0);
+
+            // wgpu clips everything outside of 0.0 to 1.0 in depth
+            assert!(
+                (0.0..=1.0).contains(&target.z),
+                "{camera:?} clips its target"
+            );
+        }
+    }
+
+    #[test]
+    fn wider_views_shrink_horizontally_only() {
+        // A point beside and above the target, it keeps its distance to the camera
+        let offset = Vector3::new(1.0, 1.0, 0.0);
+        for projection in cameras().take(2).map(|camera| camera.projection) {
+            let [wide, narrow] = ASPECT_RATIOS.map(|aspect| {
+                let camera = Camera {
+                    aspect,
+                    projection,
+                    ..Camera::new(aspect)
+                };
+                let point = project(&camera, camera.target + offset);
+
+                // The view is stretched to fit the width, so x scales with 1 / aspect
+                (point.x * aspect, point.y)
+            });
+            assert_close(wide.0, narrow.0);
+            assert_close(wide.1, narrow.1);
+        }
+    }
+
+    #[test]
+    fn orthographic_edges_follow_the_aspect_ratio() {
+        let projection = Projection::Orthographic {
+            height: 2.0,
+            znear: 0.1,
+            zfar: 100.0,
+        };
+        for aspect in ASPECT_RATIOS {
+            // The top right corner of the view, in front of the camera
+            let corner = projection.matrix(aspect) * Vector4::new(aspect, 1.0, -1.0, 1.0);
+            assert_close(corner.x / corner.w, 1.0);
+            assert_close(corner.y / corner.w, 1.0);
+        }
+    }
+}
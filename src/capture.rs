@@ -0,0 +1,217 @@
+use std::{
+    future::Future,
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+use image::{ImageError, RgbaImage};
+use thiserror::Error;
+use wgpu::{
+    BufferAsyncError, BufferDescriptor, BufferSlice, BufferUsages, CommandEncoderDescriptor,
+    Device, Extent3d, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Maintain, MapMode,
+    Origin3d, Queue, TextureAspect, TextureFormat, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::shader::PipelineError;
+
+/// The reasons a texture couldn't be rendered or read back from the GPU
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("can't convert {0:?} to RGBA bytes")]
+    UnsupportedFormat(TextureFormat),
+    #[error("couldn't map the buffer: {0}")]
+    Map(#[from] BufferAsyncError),
+    #[error("the device was lost before the buffer was mapped")]
+    DeviceLost,
+    #[error("couldn't save the image: {0}")]
+    Save(#[from] ImageError),
+    #[error(transparent)]
+    Pipeline(#[from] PipelineError),
+}
+
+/// The number of bytes per row of an RGBA texture when it's copied to a buffer.
+/// wgpu requires every row to start at a multiple of 256 bytes, so rows are padded.
+#[must_use]
+pub const fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * 4;
+    unpadded.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// Copies a texture with 4 bytes per pixel to the CPU, and returns the pixels as RGBA bytes
+/// without padding. This blocks until the GPU is done, so it's only supported natively.
+///
+/// # Errors
+/// Returns an error if the format doesn't have 8-bit RGBA or BGRA channels,
+/// or the buffer couldn't be mapped
+pub fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, CaptureError> {
+    // Natively, the buffer is already mapped when the future is polled for the first time
+    pollster::block_on(read_texture_async(device, queue, texture))
+}
+
+/// Like [`read_texture`], but waits for the GPU without blocking, so it works on the web too.
+/// The browser maps the buffer in the background, natively it's done before it returns.
+///
+/// # Errors
+/// Returns an error if the format doesn't have 8-bit RGBA or BGRA channels,
+/// or the buffer couldn't be mapped
+pub async fn read_texture_async(
+    device: &Device,
+    queue: &Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, CaptureError> {
+    // Surfaces are often BGRA, the channels have to be swapped to get RGBA
+    let swap_red_blue = match texture.format() {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+        format => return Err(CaptureError::UnsupportedFormat(format)),
+    };
+
+    let width = texture.width();
+    let height = texture.height();
+    let padded_bytes_per_row = padded_bytes_per_row(width);
+
+    // MAP_READ allows the CPU to read the buffer once the copy is done
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("Capture Buffer"),
+        size: u64::from(padded_bytes_per_row) * u64::from(height),
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("Capture Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    // Wait for the GPU to finish the copy, and the buffer to be mapped.
+    // Polling does nothing on the web, the browser does it by itself.
+    let slice = buffer.slice(..);
+    let mapped = map_read(&slice);
+    device.poll(Maintain::Wait);
+    mapped.await?;
+
+    // Remove the padding at the end of every row
+    let row_length = width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_length * height as usize);
+    for row in slice
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+    {
+        pixels.extend_from_slice(&row[..row_length]);
+    }
+    buffer.unmap();
+
+    if swap_red_blue {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(pixels)
+}
+
+/// What the callback of `map_async` shares with the future that waits for it
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// Starts mapping the slice for reading, and returns a future that resolves when it's mapped.
+/// wgpu reports it with a callback, which wakes the task waiting on the future.
+pub(crate) fn map_read(slice: &BufferSlice<'_>) -> impl Future<Output = Result<(), CaptureError>> {
+    let state = Arc::new(Mutex::new(MapState::default()));
+    let callback_state = Arc::clone(&state);
+    slice.map_async(MapMode::Read, move |result| {
+        let mut state = callback_state.lock().unwrap();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+
+    std::future::poll_fn(move |cx| {
+        // Without the callback, only this future has the state left, so it never resolves
+        let dropped = Arc::strong_count(&state) == 1;
+        let mut state = state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result.map_err(CaptureError::from)),
+            None if dropped => Poll::Ready(Err(CaptureError::DeviceLost)),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    })
+}
+
+/// Encodes RGBA bytes as a PNG file
+///
+/// # Errors
+/// Returns an error if the number of bytes doesn't match the size, or the file couldn't be written
+pub fn save_png(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+) -> Result<(), ImageError> {
+    let image = RgbaImage::from_raw(width, height, rgba).ok_or_else(|| {
+        ImageError::Parameter(image::error::ParameterError::from_kind(
+            image::error::ParameterErrorKind::DimensionMismatch,
+        ))
+    })?;
+    image.save(path)
+}
+
+/// Downloads the current contents of the canvas as a PNG file. On the web, the GPU can't be
+/// waited on, so the browser encodes the canvas instead of reading the surface back.
+///
+/// # Errors
+/// Returns an error if the canvas couldn't be encoded, or the download link couldn't be created
+#[cfg(target_arch = "wasm32")]
+pub fn download_canvas(
+    window: &winit::window::Window,
+    file_name: &str,
+) -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast;
+    use winit::platform::web::WindowExtWebSys;
+
+    let url = window.canvas().to_data_url_with_type("image/png")?;
+
+    // Clicking a link with a download attribute saves the URL as a file
+    let document = web_sys::window()
+        .and_then(|win| win.document())
+        .ok_or("the page doesn't have a document")?;
+    let link = document
+        .create_element("a")?
+        .dyn_into::<web_sys::HtmlAnchorElement>()?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    Ok(())
+}
@@ -0,0 +1,351 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use thiserror::Error;
+use wgpu::Color;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+/// Converts a linear color channel to sRGB, the encoding displays expect.
+/// sRGB surfaces do this in hardware when they're written to.
+#[must_use]
+pub fn to_srgb(linear: f64) -> f64 {
+    // sRGB is linear close to black, and follows a power curve everywhere else
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055f64.mul_add(linear.powf(1.0 / 2.4), -0.055)
+    }
+}
+
+/// Converts an sRGB color channel back to linear, which is what blending and lighting expect.
+/// sRGB textures do this in hardware when they're sampled.
+#[must_use]
+pub fn from_srgb(srgb: f64) -> f64 {
+    if srgb <= 0.040_45 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts the color channels of a linear color to sRGB, alpha isn't gamma encoded
+#[must_use]
+pub fn color_to_srgb(color: Color) -> Color {
+    Color {
+        r: to_srgb(color.r),
+        g: to_srgb(color.g),
+        b: to_srgb(color.b),
+        a: color.a,
+    }
+}
+
+/// Creates a linear color from 8-bit sRGB channels, like the ones in image editors and CSS.
+///
+/// The channels are converted to linear, so the color looks the same on an sRGB surface.
+/// Alpha isn't gamma encoded, so it's only scaled to 0.0 to 1.0.
+#[must_use]
+pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Color {
+    let channel = |value: u8| f64::from(value) / 255.0;
+    Color {
+        r: from_srgb(channel(r)),
+        g: from_srgb(channel(g)),
+        b: from_srgb(channel(b)),
+        a: channel(a),
+    }
+}
+
+/// Converts a linear color to 8-bit sRGB channels, the inverse of [`from_rgba8`].
+/// Channels outside of 0.0 to 1.0 are clamped.
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn to_rgba8(color: Color) -> [u8; 4] {
+    let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let srgb = color_to_srgb(color);
+    [
+        channel(srgb.r),
+        channel(srgb.g),
+        channel(srgb.b),
+        channel(srgb.a),
+    ]
+}
+
+/// The reasons a hex color couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseColorError {
+    #[error("a hex color has 3, 4, 6, or 8 digits, not {0}")]
+    InvalidLength(usize),
+    #[error("'{0}' isn't a hex digit")]
+    InvalidDigit(char),
+}
+
+/// Parses a hex color like `#1a2b3c` to a linear color, the `#` is optional.
+/// The shorthand `#abc` repeats every digit, and a fourth or eighth digit sets the alpha.
+///
+/// # Errors
+/// Returns an error if the color doesn't have 3, 4, 6, or 8 digits, or one isn't a hex digit
+pub fn from_hex(hex: &str) -> Result<Color, ParseColorError> {
+    let hex = hex.trim();
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let values = digits
+        .chars()
+        .map(|digit| {
+            digit
+                .to_digit(16)
+                .ok_or(ParseColorError::InvalidDigit(digit))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Every value is a single hex digit, so it fits in 4 bits
+    #[allow(clippy::cast_possible_truncation)]
+    let channels = match values.len() {
+        // Shorthand colors repeat every digit, so #abc is #aabbcc
+        3 | 4 => values.iter().map(|&v| (v * 0x11) as u8).collect::<Vec<_>>(),
+        6 | 8 => values
+            .chunks(2)
+            .map(|pair| (pair[0] << 4 | pair[1]) as u8)
+            .collect(),
+        length => return Err(ParseColorError::InvalidLength(length)),
+    };
+
+    // Colors without an alpha channel are opaque
+    let alpha = channels.get(3).copied().unwrap_or(u8::MAX);
+    Ok(from_rgba8(channels[0], channels[1], channels[2], alpha))
+}
+
+/// Reads the startup background color from the `LEARN_WGPU_CLEAR_COLOR` environment variable.
+///
+/// The value is a hex color like `#1a2b3c`. Returns `None` if the variable isn't set.
+/// An invalid color is logged as a warning and `None` is returned as well,
+/// so the default color is used.
+#[must_use]
+pub fn background_color_from_env() -> Option<Color> {
+    let value = std::env::var("LEARN_WGPU_CLEAR_COLOR").ok()?;
+    from_hex(&value)
+        .map_err(|error| log::warn!("Invalid LEARN_WGPU_CLEAR_COLOR \"{value}\": {error}"))
+        .ok()
+}
+
+/// Converts a color from HSV to RGB. The hue is in degrees, the saturation and value range
+/// from 0.0 to 1.0. Like color pickers, the result is sRGB encoded.
+#[must_use]
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Color {
+    // The hue is split into 6 sectors of 60 degrees, the sector decides which channel is the
+    // strongest, and the position within the sector how strong the second channel is
+    let chroma = value * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector {
+        s if s < 1.0 => (chroma, second, 0.0),
+        s if s < 2.0 => (second, chroma, 0.0),
+        s if s < 3.0 => (0.0, chroma, second),
+        s if s < 4.0 => (0.0, second, chroma),
+        s if s < 5.0 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+
+    // Brighten every channel equally to reach the value
+    let offset = value - chroma;
+    Color {
+        r: r + offset,
+        g: g + offset,
+        b: b + offset,
+        a: 1.0,
+    }
+}
+
+/// Converts a color from HSV to linear RGB, so it looks like the HSV color on the surface
+/// after gamma correction. The hue is in degrees, the saturation and value range from 0.0 to 1.0.
+#[must_use]
+pub fn hsv_to_linear(hue: f64, saturation: f64, value: f64) -> Color {
+    let srgb = hsv_to_rgb(hue, saturation, value);
+    Color {
+        r: from_srgb(srgb.r),
+        g: from_srgb(srgb.g),
+        b: from_srgb(srgb.b),
+        a: srgb.a,
+    }
+}
+
+/// Maps the cursor position in a window of the given size to a background color.
+/// Red increases to the right, green to the bottom, and blue is highest in the top left corner.
+///
+/// Both the position and the size are in physical pixels, so the scale factor cancels out.
+/// Positions outside the window are clamped to its edges.
+#[must_use]
+pub fn cursor_to_color(position: PhysicalPosition<f64>, size: PhysicalSize<u32>) -> Color {
+    // Calculate the normalized x and y positions, a minimized window has no size
+    let x = (position.x / f64::from(size.width.max(1))).clamp(0.0, 1.0);
+    let y = (position.y / f64::from(size.height.max(1))).clamp(0.0, 1.0);
+    Color {
+        r: x,
+        g: y,
+        b: 1.0 - x.midpoint(y),
+        a: 1.0,
+    }
+}
+
+/// How the shaders have to write colors to the surface, as it is stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+pub struct ColorUniform {
+    /// 1 if the surface isn't sRGB, so the shader has to gamma correct its output itself.
+    /// WGSL doesn't allow booleans in uniform buffers.
+    pub gamma_correct: u32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [u32; 3],
+}
+
+impl ColorUniform {
+    /// Creates the uniform for a surface with or without an sRGB format
+    #[must_use]
+    pub fn new(is_srgb: bool) -> Self {
+        Self {
+            gamma_correct: u32::from(!is_srgb),
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// The `to_srgb` function of the shaders that gamma correct their output themselves
+const SRGB_SHADER: &str = include_str!("srgb.wgsl");
+
+/// Adds `to_srgb` to a shader that calls it, so every shader converts colors the same way.
+/// It's appended, so the line numbers of errors in the shader stay the same.
+#[must_use]
+pub fn shader_source(source: &str) -> Cow<'static, str> {
+    Cow::Owned(format!("{source}\n{SRGB_SHADER}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A window that's wider than it's high, so mixed up axes are caught
+    const SIZE: PhysicalSize<u32> = PhysicalSize::new(200, 100);
+
+    const fn rgb(r: f64, g: f64, b: f64) -> Color {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    /// Parses a hex color, and converts it back to the 8-bit sRGB channels it was written with
+    fn hex_to_rgba8(hex: &str) -> [u8; 4] {
+        to_rgba8(from_hex(hex).unwrap())
+    }
+
+    #[test]
+    fn hex_with_six_digits() {
+        assert_eq!(hex_to_rgba8("#1a2b3c"), [0x1a, 0x2b, 0x3c, 0xff]);
+        assert_eq!(from_hex("1a2b3c"), from_hex("#1a2b3c"));
+    }
+
+    #[test]
+    fn hex_shorthand_repeats_every_digit() {
+        assert_eq!(hex_to_rgba8("#abc"), [0xaa, 0xbb, 0xcc, 0xff]);
+        assert_eq!(from_hex("#abc"), from_hex("#aabbcc"));
+    }
+
+    #[test]
+    fn hex_with_alpha() {
+        assert_eq!(hex_to_rgba8("#abcd"), [0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(hex_to_rgba8("#1a2b3c80"), [0x1a, 0x2b, 0x3c, 0x80]);
+    }
+
+    #[test]
+    fn hex_with_wrong_length() {
+        assert_eq!(from_hex(""), Err(ParseColorError::InvalidLength(0)));
+        assert_eq!(from_hex("#12345"), Err(ParseColorError::InvalidLength(5)));
+        assert_eq!(
+            from_hex("#1a2b3c4d5"),
+            Err(ParseColorError::InvalidLength(9))
+        );
+    }
+
+    #[test]
+    fn hex_with_invalid_digit() {
+        assert_eq!(from_hex("#12g456"), Err(ParseColorError::InvalidDigit('g')));
+        assert_eq!(from_hex("##abc"), Err(ParseColorError::InvalidDigit('#')));
+    }
+
+    #[test]
+    fn hsv_primary_hues() {
+        assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), rgb(1.0, 0.0, 0.0));
+        assert_eq!(hsv_to_rgb(120.0, 1.0, 1.0), rgb(0.0, 1.0, 0.0));
+        assert_eq!(hsv_to_rgb(240.0, 1.0, 1.0), rgb(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn hsv_secondary_hues() {
+        assert_eq!(hsv_to_rgb(60.0, 1.0, 1.0), rgb(1.0, 1.0, 0.0));
+        assert_eq!(hsv_to_rgb(180.0, 1.0, 1.0), rgb(0.0, 1.0, 1.0));
+        assert_eq!(hsv_to_rgb(300.0, 1.0, 1.0), rgb(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn hsv_hue_wraps_around() {
+        assert_eq!(hsv_to_rgb(360.0, 1.0, 1.0), hsv_to_rgb(0.0, 1.0, 1.0));
+        assert_eq!(hsv_to_rgb(480.0, 1.0, 1.0), hsv_to_rgb(120.0, 1.0, 1.0));
+        assert_eq!(hsv_to_rgb(-120.0, 1.0, 1.0), hsv_to_rgb(240.0, 1.0, 1.0));
+        assert_eq!(hsv_to_rgb(-60.0, 1.0, 1.0), hsv_to_rgb(300.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn hsv_without_saturation_is_grey() {
+        for hue in [0.0, 90.0, 200.0, 330.0] {
+            assert_eq!(hsv_to_rgb(hue, 0.0, 0.4), rgb(0.4, 0.4, 0.4));
+        }
+    }
+
+    #[test]
+    fn hsv_to_linear_decodes_srgb() {
+        for hue in [0.0, 45.0, 150.0, 275.0] {
+            let srgb = hsv_to_rgb(hue, 0.7, 0.8);
+            let linear = hsv_to_linear(hue, 0.7, 0.8);
+            assert_eq!(
+                linear,
+                rgb(from_srgb(srgb.r), from_srgb(srgb.g), from_srgb(srgb.b))
+            );
+        }
+    }
+
+    #[test]
+    fn cursor_at_origin_is_blue() {
+        let color = cursor_to_color(PhysicalPosition::new(0.0, 0.0), SIZE);
+        assert_eq!(color, rgb(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn cursor_at_center_is_grey() {
+        let color = cursor_to_color(PhysicalPosition::new(100.0, 50.0), SIZE);
+        assert_eq!(color, rgb(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn cursor_at_edges_is_fully_saturated() {
+        let right = cursor_to_color(PhysicalPosition::new(200.0, 0.0), SIZE);
+        assert_eq!(right, rgb(1.0, 0.0, 0.5));
+        let bottom = cursor_to_color(PhysicalPosition::new(0.0, 100.0), SIZE);
+        assert_eq!(bottom, rgb(0.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn cursor_outside_the_window_is_clamped() {
+        let past_bottom_right = cursor_to_color(PhysicalPosition::new(300.0, 150.0), SIZE);
+        assert_eq!(past_bottom_right, rgb(1.0, 1.0, 0.0));
+        let past_top_left = cursor_to_color(PhysicalPosition::new(-10.0, -5.0), SIZE);
+        assert_eq!(past_top_left, rgb(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn cursor_in_zero_sized_window_is_finite() {
+        let size = PhysicalSize::new(0, 0);
+        assert_eq!(
+            cursor_to_color(PhysicalPosition::new(0.0, 0.0), size),
+            rgb(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            cursor_to_color(PhysicalPosition::new(10.0, 10.0), size),
+            rgb(1.0, 1.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use thiserror::Error;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Adapter, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BufferBindingType, BufferDescriptor, BufferUsages,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    Device, DownlevelFlags, Maintain, PipelineLayoutDescriptor, Queue, ShaderStages,
+};
+
+use crate::{
+    capture::{self, CaptureError},
+    entry_points,
+};
+
+/// The number of invocations in a workgroup, it has to match `@workgroup_size` in compute.wgsl
+pub const WORKGROUP_SIZE: u32 = 64;
+
+/// Whether the adapter can run compute shaders. WebGL and some older OpenGL versions can't.
+#[must_use]
+pub fn is_supported(adapter: &Adapter) -> bool {
+    adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(DownlevelFlags::COMPUTE_SHADERS)
+}
+
+/// The number of workgroups needed to run the shader once for every one of `len` elements.
+/// The last workgroup is only partly used if `len` isn't a multiple of the workgroup size.
+#[must_use]
+pub const fn workgroup_count(len: u32) -> u32 {
+    len.div_ceil(WORKGROUP_SIZE)
+}
+
+/// The number of workgroups to dispatch along x and y for `len` elements.
+///
+/// A dispatch can have at most `max_per_dimension` workgroups along every axis,
+/// so when more are needed, they're spread over several rows along y.
+#[must_use]
+pub const fn dispatch_size(len: u32, max_per_dimension: u32) -> (u32, u32) {
+    let count = workgroup_count(len);
+    if count <= max_per_dimension {
+        (count, 1)
+    } else {
+        (max_per_dimension, count.div_ceil(max_per_dimension))
+    }
+}
+
+/// The reasons the values couldn't be doubled on the GPU
+#[derive(Debug, Error)]
+pub enum ComputeError {
+    #[error("{len} values don't fit in a storage buffer, the device allows at most {max}")]
+    TooManyValues { len: usize, max: usize },
+    #[error(transparent)]
+    Readback(#[from] CaptureError),
+}
+
+/// The compute pipeline that doubles every value in a buffer.
+/// It's created once, and can run on as many values as the device can bind in a single
+/// storage buffer, `max_storage_buffer_binding_size` bytes.
+pub struct DoubleKernel {
+    bind_group_layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+}
+
+impl DoubleKernel {
+    /// Creates the pipeline from compute.wgsl. Check [`is_supported`] first.
+    #[must_use]
+    pub fn new(device: &Device) -> Self {
+        // The shader only accesses the storage buffer, in group 0
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Compute Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        // A compute pipeline only has a single stage, and no render targets
+        let shader = device.create_shader_module(wgpu::include_wgsl!("compute.wgsl"));
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Compute Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Compute Pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: entry_points::COMPUTE,
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// Doubles the values on the GPU, and reads them back.
+    /// This blocks until the GPU is done.
+    ///
+    /// # Errors
+    /// Returns an error if there are more values than fit in a storage buffer binding,
+    /// or the buffer couldn't be read back
+    pub fn run(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        values: &[f32],
+    ) -> Result<Vec<f32>, ComputeError> {
+        // Empty buffers can't be bound
+        if values.is_empty() {
+            return Ok(Vec::new());
+        }
+        let limits = device.limits();
+        let max = limits.max_storage_buffer_binding_size as usize / std::mem::size_of::<f32>();
+        let len = u32::try_from(values.len())
+            .ok()
+            .filter(|_| values.len() <= max)
+            .ok_or(ComputeError::TooManyValues {
+                len: values.len(),
+                max,
+            })?;
+        let size = std::mem::size_of_val(values) as u64;
+
+        // STORAGE allows the shader to change the buffer, COPY_SRC to copy the results out.
+        // Storage buffers can't be mapped, so the results are copied to a buffer that can.
+        let storage_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Compute Storage Buffer"),
+            contents: bytemuck::cast_slice(values),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+        });
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Compute Readback Buffer"),
+            size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: storage_buffer.as_entire_binding(),
+            }],
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Compute Encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+
+            // Enough workgroups to cover every element, the shader skips the ones past the end
+            let (x, y) = dispatch_size(len, limits.max_compute_workgroups_per_dimension);
+            compute_pass.dispatch_workgroups(x, y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&storage_buffer, 0, &readback_buffer, 0, size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        // Wait for the GPU to finish, and the buffer to be mapped
+        let slice = readback_buffer.slice(..);
+        let mapped = capture::map_read(&slice);
+        device.poll(Maintain::Wait);
+        pollster::block_on(mapped).map_err(ComputeError::from)?;
+
+        let values = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        readback_buffer.unmap();
+        Ok(values)
+    }
+}
+
+/// Fills a buffer with twice the index of every element on the GPU, and reads it back.
+/// This blocks until the GPU is done. Check [`is_supported`] first.
+///
+/// # Errors
+/// Returns an error if there are more values than fit in a storage buffer binding,
+/// or the buffer couldn't be read back
+pub fn double_indices(device: &Device, queue: &Queue, len: u32) -> Result<Vec<f32>, ComputeError> {
+    // Indices up to 2^24 fit exactly in a float
+    #[allow(clippy::cast_precision_loss)]
+    let indices: Vec<_> = (0..len).map(|index| index as f32).collect();
+    DoubleKernel::new(device).run(device, queue, &indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{StateBuilder, StateError};
+
+    #[test]
+    fn doubles_every_index() {
+        let ctx = match pollster::block_on(StateBuilder::new().build_headless()) {
+            Ok(ctx) => ctx,
+            Err(StateError::NoAdapter) => return,
+            Err(error) => panic!("couldn't create a headless context: {error}"),
+        };
+        if !is_supported(&ctx.adapter) {
+            return;
+        }
+
+        // Indices this small are exact as floats, so the results can be compared exactly
+        #[allow(clippy::cast_precision_loss)]
+        let expected: Vec<_> = (0..100).map(|index| index as f32 * 2.0).collect();
+        let doubled = double_indices(&ctx.device, &ctx.queue, 100).unwrap();
+        assert_eq!(doubled, expected);
+    }
+}
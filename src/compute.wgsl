@@ -0,0 +1,23 @@
+// A compute shader doesn't draw anything, it runs on the GPU like a function called many times
+// in parallel. This one doubles every element of the buffer in place.
+
+// The buffer with the values, `read_write` allows the shader to change them
+@group(0) @binding(0)
+var<storage, read_write> values: array<f32>;
+
+// Every workgroup runs 64 invocations, the dispatch decides how many workgroups there are.
+// A dispatch has a limited number of workgroups along every axis, so large buffers continue
+// on the next row along y.
+@compute @workgroup_size(64)
+fn cs_main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) workgroups: vec3<u32>,
+){
+    let index = id.x + id.y * workgroups.x * 64u;
+
+    // The last workgroup can have more invocations than there are elements left
+    if index >= arrayLength(&values) {
+        return;
+    }
+    values[index] = values[index] * 2.0;
+}
@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{Device, Features, PushConstantRange, ShaderStages};
+
+/// Small parameters that can change between draws. They're passed as push constants if the
+/// GPU supports them, and through a uniform buffer in group 2 otherwise (like on WebGL).
+///
+/// The scale fills the space after the tint, so the constants are exactly the 16 bytes
+/// uniform buffers need on WebGL, without padding.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct DrawConstants {
+    /// Multiplied with the color of every fragment, in linear space
+    pub tint: [f32; 3],
+
+    /// Scales the models around their origin before they're placed in the world
+    pub scale: f32,
+}
+
+impl DrawConstants {
+    /// The number of bytes the constants take in push constants or the uniform buffer
+    #[allow(clippy::cast_possible_truncation)]
+    pub const SIZE: u32 = std::mem::size_of::<Self>() as u32;
+
+    /// Creates constants that tint and scale the models
+    #[must_use]
+    pub const fn new(tint: [f32; 3], scale: f32) -> Self {
+        Self { tint, scale }
+    }
+}
+
+/// Draws the models unchanged
+impl Default for DrawConstants {
+    fn default() -> Self {
+        Self::new([1.0; 3], 1.0)
+    }
+}
+
+/// How the shader declares the constants when they're stored in a uniform buffer
+const UNIFORM_DECLARATION: &str = "@group(2) @binding(3)\nvar<uniform> draw: DrawConstants;";
+
+/// How the shader declares the constants when they're passed as push constants
+const PUSH_CONSTANT_DECLARATION: &str = "var<push_constant> draw: DrawConstants;";
+
+/// How the GLSL shaders declare the constants, as a uniform block or a push constant block
+#[cfg(feature = "glsl")]
+const GLSL_UNIFORM_DECLARATION: &str = "layout(set = 2, binding = 3) uniform DrawConstants";
+#[cfg(feature = "glsl")]
+const GLSL_PUSH_CONSTANT_DECLARATION: &str = "layout(push_constant) uniform DrawConstants";
+
+/// Whether the device can pass the constants as push constants.
+/// `Context` only requests the feature if the adapter supports it, and never on OpenGL.
+#[must_use]
+pub fn push_constants_supported(device: &Device) -> bool {
+    device.features().contains(Features::PUSH_CONSTANTS)
+        && device.limits().max_push_constant_size >= DrawConstants::SIZE
+}
+
+/// The push constant ranges of pipelines that use the constants.
+/// Empty without push constants, as the uniform buffer is part of a bind group then.
+#[must_use]
+pub fn push_constant_ranges(device: &Device) -> Vec<PushConstantRange> {
+    if push_constants_supported(device) {
+        vec![PushConstantRange {
+            stages: ShaderStages::VERTEX_FRAGMENT,
+            range: 0..DrawConstants::SIZE,
+        }]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Declares the constants in the shader as push constants if the device supports them.
+/// The shader declares them as a uniform by default, as that works everywhere.
+#[must_use]
+pub fn shader_source<'a>(device: &Device, source: Cow<'a, str>) -> Cow<'a, str> {
+    declare_constants(
+        device,
+        source,
+        UNIFORM_DECLARATION,
+        PUSH_CONSTANT_DECLARATION,
+    )
+}
+
+/// Like [`shader_source`], for the GLSL shaders
+#[cfg(feature = "glsl")]
+#[must_use]
+pub fn glsl_shader_source<'a>(device: &Device, source: Cow<'a, str>) -> Cow<'a, str> {
+    declare_constants(
+        device,
+        source,
+        GLSL_UNIFORM_DECLARATION,
+        GLSL_PUSH_CONSTANT_DECLARATION,
+    )
+}
+
+/// Replaces the uniform declaration with the push constant one if the device supports them
+fn declare_constants<'a>(
+    device: &Device,
+    source: Cow<'a, str>,
+    uniform: &str,
+    push_constant: &str,
+) -> Cow<'a, str> {
+    if push_constants_supported(device) {
+        Cow::Owned(source.replace(uniform, push_constant))
+    } else {
+        source
+    }
+}
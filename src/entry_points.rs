@@ -0,0 +1,93 @@
+// The names of the entry points in the WGSL shaders. This file is also included by build.rs,
+// which checks that every shader has the entry points the pipelines are created with,
+// so it can only use the standard library.
+
+/// The vertex shader of every shader
+pub const VERTEX: &str = "vs_main";
+
+/// The fragment shader in shader.wgsl that lights the textured models
+pub const TEXTURE: &str = "fs_main";
+
+/// The fragment shader in shader.wgsl that colors the models by their position
+pub const GRADIENT: &str = "fs_main2";
+
+/// The fragment shader in shader.wgsl that makes the texture pulse over time
+pub const PULSE: &str = "fs_pulse";
+
+/// The fragment shader in shader.wgsl that writes the albedo and normals to the G-buffer
+pub const GBUFFER: &str = "fs_gbuffer";
+
+/// The fragment shader of light.wgsl
+pub const LIGHT: &str = "fs_main";
+
+/// The fragment shader of grid.wgsl, it colors the lines of the grid and the axes
+pub const GRID: &str = "fs_main";
+
+/// The fragment shader of outline.wgsl, it colors the outline around the models
+pub const OUTLINE: &str = "fs_main";
+
+/// The fragment shader of skybox.wgsl, it looks up the cube map in the direction of a pixel
+pub const SKYBOX: &str = "fs_main";
+
+/// The fragment shader of translucent.wgsl, it's drawn with alpha blending
+pub const TRANSLUCENT: &str = "fs_main";
+
+/// The fragment shader of the post-processing pass, it applies the effect to the scene
+pub const POST_PROCESS: &str = "fs_main";
+
+/// The fragment shader of the tonemapping pass, it maps the HDR scene to the surface
+pub const TONEMAP: &str = "fs_main";
+
+/// The bloom passes in bloom.wgsl: the bright parts of the scene are extracted,
+/// blurred in both directions, and added on top of the scene
+pub const BLOOM_BRIGHT: &str = "fs_bright";
+pub const BLOOM_BLUR_HORIZONTAL: &str = "fs_blur_horizontal";
+pub const BLOOM_BLUR_VERTICAL: &str = "fs_blur_vertical";
+pub const BLOOM_COMPOSITE: &str = "fs_composite";
+
+/// The HDR bloom passes in `hdr_bloom.wgsl`: the bright parts of the scene are extracted,
+/// downsampled through a mip chain, and upsampled back while they're added together
+pub const HDR_BLOOM_PREFILTER: &str = "fs_prefilter";
+pub const HDR_BLOOM_DOWNSAMPLE: &str = "fs_downsample";
+pub const HDR_BLOOM_UPSAMPLE: &str = "fs_upsample";
+
+/// The fragment shader of text.wgsl, it blends the glyphs from the cache texture over the frame
+pub const TEXT: &str = "fs_main";
+
+/// The compute shader in compute.wgsl that doubles the values in a buffer
+pub const COMPUTE: &str = "cs_main";
+
+/// Every shader in `src` the pipelines use, and the entry points they need
+pub const SHADERS: &[(&str, &[&str])] = &[
+    ("shader.wgsl", &[VERTEX, TEXTURE, GRADIENT, PULSE, GBUFFER]),
+    ("light.wgsl", &[VERTEX, LIGHT]),
+    ("grid.wgsl", &[VERTEX, GRID]),
+    ("outline.wgsl", &[VERTEX, OUTLINE]),
+    ("skybox.wgsl", &[VERTEX, SKYBOX]),
+    ("translucent.wgsl", &[VERTEX, TRANSLUCENT]),
+    ("post_process.wgsl", &[VERTEX, POST_PROCESS]),
+    ("tonemap.wgsl", &[VERTEX, TONEMAP]),
+    (
+        "bloom.wgsl",
+        &[
+            VERTEX,
+            BLOOM_BRIGHT,
+            BLOOM_BLUR_HORIZONTAL,
+            BLOOM_BLUR_VERTICAL,
+            BLOOM_COMPOSITE,
+        ],
+    ),
+    (
+        "hdr_bloom.wgsl",
+        &[
+            VERTEX,
+            HDR_BLOOM_PREFILTER,
+            HDR_BLOOM_DOWNSAMPLE,
+            HDR_BLOOM_UPSAMPLE,
+        ],
+    ),
+    ("text.wgsl", &[VERTEX, TEXT]),
+];
+
+/// Every compute shader in `src`, and the entry points they need
+pub const COMPUTE_SHADERS: &[(&str, &[&str])] = &[("compute.wgsl", &[COMPUTE])];
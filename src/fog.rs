@@ -0,0 +1,83 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::Color;
+
+/// The thickest exponential fog, the scene is hardly visible past a few units with it
+pub const MAX_DENSITY: f32 = 0.5;
+
+/// How the fog thickens with the distance to the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogSettings {
+    /// Nothing is fogged closer than `start`, and everything is fully fogged past `end`
+    Linear { start: f32, end: f32 },
+
+    /// The visible part of a color drops to `1 / e` every `1 / density` units.
+    /// It never gets completely fogged, but it doesn't need a start and end.
+    Exponential { density: f32 },
+}
+
+impl FogSettings {
+    /// The settings with a density between 0 and [`MAX_DENSITY`], and an end past the start
+    #[must_use]
+    pub fn clamped(self) -> Self {
+        match self {
+            Self::Linear { start, end } => {
+                let start = start.max(0.0);
+                Self::Linear {
+                    start,
+                    end: end.max(start + f32::EPSILON),
+                }
+            }
+            Self::Exponential { density } => Self::Exponential {
+                density: density.clamp(0.0, MAX_DENSITY),
+            },
+        }
+    }
+}
+
+/// The fog as it's stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct FogUniform {
+    /// The linear color distant geometry fades into, the background color
+    color: [f32; 3],
+
+    /// How fast exponential fog thickens, 0 turns the fog off
+    density: f32,
+
+    /// Where linear fog starts and ends
+    start: f32,
+    end: f32,
+
+    /// 0 for exponential fog, 1 for linear fog
+    mode: u32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: u32,
+}
+
+impl FogUniform {
+    /// The fog with the given settings and color. Without settings, the fog is exponential
+    /// with a density of 0, which doesn't fog anything, so no separate pipeline is needed.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn new(settings: Option<FogSettings>, color: Color) -> Self {
+        let mut uniform = Self {
+            color: [color.r as f32, color.g as f32, color.b as f32],
+            density: 0.0,
+            start: 0.0,
+            end: 0.0,
+            mode: 0,
+            _padding: 0,
+        };
+        match settings.map(FogSettings::clamped) {
+            Some(FogSettings::Linear { start, end }) => {
+                uniform.start = start;
+                uniform.end = end;
+                uniform.mode = 1;
+            }
+            Some(FogSettings::Exponential { density }) => uniform.density = density,
+            None => {}
+        }
+        uniform
+    }
+}
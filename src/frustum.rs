@@ -0,0 +1,60 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, Vector3, Vector4};
+
+/// A plane through space, the points on it have a signed distance of 0.
+/// The normal points to the side that's inside the frustum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Creates the plane `x * a + y * b + z * c + d = 0`, scaled so the normal is unit length.
+    /// Otherwise the distances to the plane wouldn't be in world units.
+    fn from_coefficients(coefficients: Vector4<f32>) -> Self {
+        let normal = coefficients.truncate();
+        let length = normal.magnitude();
+        Self {
+            normal: normal / length,
+            distance: coefficients.w / length,
+        }
+    }
+
+    /// How far the point is in front of the plane, negative if it's behind it
+    #[must_use]
+    pub fn signed_distance(&self, point: Point3<f32>) -> f32 {
+        self.normal.dot(Vector3::new(point.x, point.y, point.z)) + self.distance
+    }
+}
+
+/// The part of the world the camera can see, enclosed by 6 planes.
+/// Anything outside of it isn't drawn, so it doesn't have to be sent to the GPU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, and far
+    planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix in wgpu's clip space.
+    ///
+    /// A point is visible if its clip coordinates are between `-w` and `w` for x and y,
+    /// and between 0 and `w` for the depth. Every one of those comparisons is a plane.
+    #[must_use]
+    pub fn from_matrix(view_proj: Matrix4<f32>) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|row| view_proj.row(row));
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z].map(Plane::from_coefficients),
+        }
+    }
+
+    /// Whether any part of the sphere could be visible. Spheres that cross a plane count as
+    /// inside, and so do a few near the corners that are just outside, as that's cheaper to
+    /// test and only draws slightly more than needed.
+    #[must_use]
+    pub fn intersects_sphere(&self, center: Point3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(center) >= -radius)
+    }
+}
@@ -0,0 +1,77 @@
+use wgpu::{
+    BlendState, Color, ColorTargetState, ColorWrites, Device, LoadOp, Operations,
+    RenderPassColorAttachment, StoreOp, SurfaceConfiguration, TextureFormat,
+};
+
+use crate::texture::Texture;
+
+/// The textures a deferred renderer draws the scene to, before the lighting is calculated
+/// from them in a separate pass. Every pixel stores what's needed to light it later.
+pub struct GBuffer {
+    /// The color of the surfaces without lighting
+    pub albedo: Texture,
+
+    /// The normals in world space, mapped from -1.0 to 1.0 to colors from 0.0 to 1.0
+    pub normal: Texture,
+
+    /// Only the closest surfaces are stored, just like when rendering to the screen
+    pub depth: Texture,
+}
+
+impl GBuffer {
+    /// The albedo is a color, so it's stored in sRGB to keep the dark colors precise
+    pub const ALBEDO_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+    /// Normals are directions, so they're stored linearly
+    pub const NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+    /// Creates the textures with the size of the configuration.
+    /// Like the depth texture, they have to be recreated when that size changes.
+    #[must_use]
+    pub fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
+        Self {
+            albedo: Texture::create_render_target(
+                device,
+                config,
+                Self::ALBEDO_FORMAT,
+                "G-buffer Albedo",
+            ),
+            normal: Texture::create_render_target(
+                device,
+                config,
+                Self::NORMAL_FORMAT,
+                "G-buffer Normal",
+            ),
+            depth: Texture::create_depth_texture(device, config, 1, "G-buffer Depth"),
+        }
+    }
+
+    /// The color targets of a pipeline that writes to the G-buffer.
+    /// The fragment shader writes the albedo to `@location(0)` and the normal to `@location(1)`.
+    #[must_use]
+    pub fn color_targets() -> [Option<ColorTargetState>; 2] {
+        [Self::ALBEDO_FORMAT, Self::NORMAL_FORMAT].map(|format| {
+            Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            })
+        })
+    }
+
+    /// The color attachments of a render pass that writes to the G-buffer, in the same order
+    /// as the targets. Both are cleared, pixels without geometry have no color or normal.
+    #[must_use]
+    pub fn color_attachments(&self) -> [Option<RenderPassColorAttachment<'_>>; 2] {
+        [&self.albedo, &self.normal].map(|texture| {
+            Some(RenderPassColorAttachment {
+                view: &texture.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })
+        })
+    }
+}
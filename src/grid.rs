@@ -0,0 +1,118 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Buffer, BufferAddress, BufferUsages, Device, RenderPass, VertexAttribute, VertexBufferLayout,
+    VertexStepMode,
+};
+
+/// The linear color of the grid lines
+const GRID_COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+/// The linear colors of the x-, y-, and z-axis
+const AXIS_COLORS: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// The end of a line, as it is stored in the vertex buffer of the grid
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+
+    /// The linear color of the line at this end
+    pub color: [f32; 3],
+}
+
+impl LineVertex {
+    /// `@location(0)` is the position, `@location(1)` the color
+    const ATTRIBUTES: [VertexAttribute; 2] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+    ];
+
+    /// Describes how a buffer of line vertices is laid out in memory
+    #[must_use]
+    pub const fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Gray lines on the ground around the origin, and a red, green, and blue line along the
+/// positive x-, y-, and z-axis, to see where things are and which way they face
+#[derive(Debug)]
+pub struct Grid {
+    vertex_buffer: Buffer,
+    vertex_count: u32,
+}
+
+impl Grid {
+    /// Uploads the lines of a grid of `size` by `size` cells, `spacing` units wide
+    #[must_use]
+    pub fn new(device: &Device, size: u16, spacing: f32) -> Self {
+        let vertices = Self::vertices(size, spacing);
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Grid Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        // A grid of u16::MAX cells has about a quarter million vertices
+        #[allow(clippy::cast_possible_truncation)]
+        Self {
+            vertex_buffer,
+            vertex_count: vertices.len() as u32,
+        }
+    }
+
+    /// Every pair of vertices is a line. The grid is centered on the origin at a height of 0,
+    /// the axes are as long as half the grid, and at least a single unit.
+    #[must_use]
+    pub fn vertices(size: u16, spacing: f32) -> Vec<LineVertex> {
+        let half_width = f32::from(size) * spacing / 2.0;
+        let axis_length = half_width.max(1.0);
+
+        // The axes come first. The depth test only passes for pixels that are closer than what's
+        // already drawn, so they stay visible where they overlap the grid lines.
+        let axes = AXIS_COLORS
+            .into_iter()
+            .enumerate()
+            .flat_map(|(axis, color)| {
+                let mut end = [0.0; 3];
+                end[axis] = axis_length;
+                [
+                    LineVertex {
+                        position: [0.0; 3],
+                        color,
+                    },
+                    LineVertex {
+                        position: end,
+                        color,
+                    },
+                ]
+            });
+
+        // Every line along the x-axis is followed by the line along the z-axis at the same offset
+        let lines = (0..=size).flat_map(|line| {
+            let offset = f32::from(line).mul_add(spacing, -half_width);
+            [
+                [-half_width, 0.0, offset],
+                [half_width, 0.0, offset],
+                [offset, 0.0, -half_width],
+                [offset, 0.0, half_width],
+            ]
+            .map(|position| LineVertex {
+                position,
+                color: GRID_COLOR,
+            })
+        });
+
+        axes.chain(lines).collect()
+    }
+
+    /// Draws every line, the render pass has to use a pipeline with a line list topology
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
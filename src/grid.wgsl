@@ -0,0 +1,46 @@
+// Draws the lines of the grid and the axes in their own colors, they aren't lit.
+// The bind groups match shader.wgsl, so they don't have to be switched.
+
+// The camera data, as stored in `CameraUniform`
+struct CameraUniform{
+    view_position: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// How colors have to be written to the surface, as stored in `ColorUniform`
+struct ColorUniform{
+    gamma_correct: u32,
+};
+
+@group(2) @binding(1)
+var<uniform> color_info: ColorUniform;
+
+// The layout has to match `LineVertex::desc`
+struct VertexInput{
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput{
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
+    out.color = model.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
+    if color_info.gamma_correct != 0u {
+        return vec4<f32>(to_srgb(in.color), 1.0);
+    }
+    return vec4<f32>(in.color, 1.0);
+}
@@ -0,0 +1,130 @@
+use egui::{epaint::ClippedShape, TexturesDelta, ViewportId};
+use egui_wgpu::{Renderer, ScreenDescriptor};
+use wgpu::{
+    CommandEncoder, Device, LoadOp, Operations, Queue, RenderPassColorAttachment,
+    RenderPassDescriptor, StoreOp, SurfaceConfiguration, TextureFormat, TextureView,
+};
+use winit::{event::WindowEvent, window::Window};
+
+/// Draws an immediate mode GUI with egui on top of the frame.
+/// The UI is built again every update, and the last one is drawn when a frame is rendered.
+pub struct EguiRenderer {
+    context: egui::Context,
+
+    /// Translates the window events to egui input
+    state: egui_winit::State,
+
+    /// Uploads the shapes and textures of the GUI, and draws them
+    renderer: Renderer,
+
+    /// What the last UI that was built looks like, in points
+    shapes: Vec<ClippedShape>,
+    pixels_per_point: f32,
+
+    /// The textures that changed since the last frame, like the font atlas.
+    /// Several UIs can be built between frames, so the changes are collected until they're drawn.
+    textures_delta: TexturesDelta,
+}
+
+impl EguiRenderer {
+    /// Creates a GUI for the window, it's drawn to textures with the given format
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn new(device: &Device, format: TextureFormat, window: &Window) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+
+        // The GUI is drawn after the multisampled frame is resolved, without depth
+        let renderer = Renderer::new(device, format, None, 1);
+
+        Self {
+            context,
+            state,
+            renderer,
+            shapes: Vec::new(),
+            pixels_per_point: window.scale_factor() as f32,
+            textures_delta: TexturesDelta::default(),
+        }
+    }
+
+    /// Passes a window event to egui. Returns whether egui used it,
+    /// like a click on a slider or typing in a text field.
+    pub fn handle_input(&mut self, event: &WindowEvent) -> bool {
+        self.state.on_window_event(&self.context, event).consumed
+    }
+
+    /// Builds the UI with the input since the last time it was built
+    pub fn run(&mut self, window: &Window, build_ui: impl FnOnce(&egui::Context)) {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, build_ui);
+
+        // Change the cursor, or open a clicked link
+        self.state
+            .handle_platform_output(window, &self.context, output.platform_output);
+
+        self.shapes = output.shapes;
+        self.pixels_per_point = output.pixels_per_point;
+        self.textures_delta.append(output.textures_delta);
+    }
+
+    /// Draws the last UI that was built on top of the view, in a render pass of its own
+    pub fn render(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        config: &SurfaceConfiguration,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+    ) {
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.pixels_per_point,
+        };
+
+        // Upload the new textures, and turn the shapes into triangles
+        let textures_delta = std::mem::take(&mut self.textures_delta);
+        for (id, image_delta) in &textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        let paint_jobs = self
+            .context
+            .tessellate(self.shapes.clone(), self.pixels_per_point);
+
+        // Only paint callbacks record command buffers of their own, and the GUI doesn't use any
+        let _ =
+            self.renderer
+                .update_buffers(device, queue, encoder, &paint_jobs, &screen_descriptor);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("GUI Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+
+                    // Keep the frame, the GUI is drawn over it
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.renderer
+                .render(&mut render_pass, &paint_jobs, &screen_descriptor);
+        }
+
+        // Textures that aren't used anymore can only be freed after they were drawn
+        for id in &textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferBindingType, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    FragmentState, LoadOp, MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState,
+    Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, SamplerBindingType, ShaderModuleDescriptor, ShaderSource,
+    ShaderStages, StoreOp, SurfaceConfiguration, TextureFormat, TextureFormatFeatureFlags,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDimension, VertexState,
+};
+
+use crate::{
+    color, entry_points,
+    hdr_bloom::{HdrBloom, HdrBloomSettings, HdrBloomTarget},
+    texture::Texture,
+};
+
+/// The format the scene is rendered in with HDR. Colors can be brighter than 1,
+/// the tonemapping pass brings them back in the range of the surface.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// The lowest and highest exposure that make sense
+pub const EXPOSURE_RANGE: (f32, f32) = (0.1, 10.0);
+
+/// Whether the scene can be rendered in HDR on the adapter. WebGL2 can't always render to
+/// float textures, the scene is drawn to the surface directly there.
+#[must_use]
+pub fn supported(adapter: &Adapter) -> bool {
+    let features = adapter.get_texture_format_features(HDR_FORMAT);
+    features
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING)
+        && features
+            .flags
+            .contains(TextureFormatFeatureFlags::FILTERABLE)
+}
+
+/// How colors brighter than 1 are mapped to the range of the surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tonemapper {
+    /// Divides every channel by itself plus 1, which keeps dark colors as they are
+    Reinhard,
+
+    /// Approximates the filmic curve of the Academy Color Encoding System,
+    /// which has more contrast and rolls off the highlights more gently
+    #[default]
+    Aces,
+}
+
+impl Tonemapper {
+    /// The other tonemapper
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Reinhard => Self::Aces,
+            Self::Aces => Self::Reinhard,
+        }
+    }
+
+    /// The number the shader branches on
+    const fn index(self) -> u32 {
+        match self {
+            Self::Reinhard => 0,
+            Self::Aces => 1,
+        }
+    }
+}
+
+/// The settings of the tonemapping pass as they're stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct HdrUniform {
+    exposure: f32,
+    tonemapper: u32,
+
+    /// Whether the output has to be gamma corrected, because the surface isn't sRGB
+    gamma_correct: u32,
+
+    /// How much of the bloom is added to the scene, 0 while it's off
+    bloom_intensity: f32,
+}
+
+/// The float texture the scene is rendered to, the mip chain of its bloom,
+/// and the bind group the tonemapping pass reads them with
+pub struct HdrTarget {
+    texture: Texture,
+    bloom: HdrBloomTarget,
+    bind_group: BindGroup,
+}
+
+impl HdrTarget {
+    /// The texture to render the scene to
+    #[must_use]
+    pub const fn view(&self) -> &TextureView {
+        &self.texture.view
+    }
+}
+
+/// Renders the scene to a float texture, and tonemaps it to the output in a second pass.
+/// Like post-processing, the pass draws a single triangle that covers the screen.
+pub struct Hdr {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    uniform_buffer: Buffer,
+    exposure: f32,
+    tonemapper: Tonemapper,
+
+    /// Makes the parts of the scene that are brighter than the surface can show glow
+    bloom: HdrBloom,
+
+    /// Whether the output has to be gamma corrected
+    gamma_correct: bool,
+
+    /// The target with the size of the surface, it has to be recreated when it's resized
+    target: HdrTarget,
+}
+
+impl Hdr {
+    /// Creates the pipeline that writes to the surface, and a target with the size of the surface
+    #[must_use]
+    pub fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
+        let exposure = 1.0;
+        let tonemapper = Tonemapper::default();
+        let gamma_correct = !config.format.is_srgb();
+        let bloom = HdrBloom::new(device);
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Tonemapping Buffer"),
+            contents: bytemuck::cast_slice(&[HdrUniform {
+                exposure,
+                tonemapper: tonemapper.index(),
+                gamma_correct: gamma_correct.into(),
+                bloom_intensity: bloom.intensity(),
+            }]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // The scene texture, its sampler, the settings, and the bloom with its own sampler
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Tonemapping Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline = Self::create_pipeline(device, config, &bind_group_layout);
+        let target = Self::new_target(&bind_group_layout, &uniform_buffer, &bloom, device, config);
+        Self {
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            exposure,
+            tonemapper,
+            bloom,
+            gamma_correct,
+            target,
+        }
+    }
+
+    /// Creates the pipeline that tonemaps the scene to a target with the format of the surface
+    fn create_pipeline(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bind_group_layout: &BindGroupLayout,
+    ) -> RenderPipeline {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Tonemapping Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("tonemap.wgsl"),
+            source: ShaderSource::Wgsl(color::shader_source(include_str!("tonemap.wgsl"))),
+        });
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Tonemapping Pipeline"),
+            layout: Some(&layout),
+
+            // The vertices are calculated from their index, so there's no vertex buffer
+            vertex: VertexState {
+                module: &shader,
+                entry_point: entry_points::VERTEX,
+                buffers: &[],
+            },
+
+            // The post-processing target has the format of the surface as well
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: entry_points::TONEMAP,
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// Creates a float texture with the size of the configuration to render the scene to,
+    /// and the mip chain of its bloom
+    fn new_target(
+        layout: &BindGroupLayout,
+        uniform_buffer: &Buffer,
+        bloom: &HdrBloom,
+        device: &Device,
+        config: &SurfaceConfiguration,
+    ) -> HdrTarget {
+        let texture = Texture::create_render_target(device, config, HDR_FORMAT, "HDR Texture");
+        let bloom_target = bloom.create_target(device, config, &texture.view);
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Tonemapping Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&texture.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&texture.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(bloom_target.view()),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::Sampler(bloom.sampler()),
+                },
+            ],
+        });
+        HdrTarget {
+            texture,
+            bloom: bloom_target,
+            bind_group,
+        }
+    }
+
+    /// Creates a target with a different size than the surface, like for screenshots
+    #[must_use]
+    pub fn create_target(&self, device: &Device, config: &SurfaceConfiguration) -> HdrTarget {
+        Self::new_target(
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            &self.bloom,
+            device,
+            config,
+        )
+    }
+
+    /// Recreates the target with the new size of the surface
+    pub fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.target = self.create_target(device, config);
+    }
+
+    /// The target with the size of the surface
+    #[must_use]
+    pub const fn target(&self) -> &HdrTarget {
+        &self.target
+    }
+
+    /// How much brighter the scene is made before it's tonemapped
+    #[must_use]
+    pub const fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Changes the exposure, clamped to [`EXPOSURE_RANGE`], and uploads it to the GPU
+    pub fn set_exposure(&mut self, queue: &Queue, exposure: f32) {
+        self.exposure = exposure.clamp(EXPOSURE_RANGE.0, EXPOSURE_RANGE.1);
+        self.write_uniform(queue);
+    }
+
+    /// How colors brighter than 1 are mapped to the range of the surface
+    #[must_use]
+    pub const fn tonemapper(&self) -> Tonemapper {
+        self.tonemapper
+    }
+
+    /// Changes the tonemapper, and uploads it to the GPU
+    pub fn set_tonemapper(&mut self, queue: &Queue, tonemapper: Tonemapper) {
+        self.tonemapper = tonemapper;
+        self.write_uniform(queue);
+    }
+
+    /// The threshold and intensity of the bloom
+    #[must_use]
+    pub const fn bloom_settings(&self) -> HdrBloomSettings {
+        self.bloom.settings()
+    }
+
+    /// Changes the threshold and intensity of the bloom, clamped to their ranges,
+    /// and uploads them to the GPU
+    pub fn set_bloom_settings(&mut self, queue: &Queue, settings: HdrBloomSettings) {
+        self.bloom.set_settings(queue, settings);
+        self.write_uniform(queue);
+    }
+
+    /// Whether the bright parts of the scene glow
+    #[must_use]
+    pub const fn bloom_enabled(&self) -> bool {
+        self.bloom.enabled()
+    }
+
+    /// Turns the bloom on or off. While it's off, its passes are skipped
+    /// and the tonemapping pass adds none of it.
+    pub fn set_bloom_enabled(&mut self, queue: &Queue, enabled: bool) {
+        self.bloom.set_enabled(enabled);
+        self.write_uniform(queue);
+    }
+
+    fn write_uniform(&self, queue: &Queue) {
+        let uniform = HdrUniform {
+            exposure: self.exposure,
+            tonemapper: self.tonemapper.index(),
+            gamma_correct: self.gamma_correct.into(),
+            bloom_intensity: self.bloom.intensity(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Draws the scene in the target to the output with its bloom,
+    /// tonemapped to the range of the surface
+    pub fn render(&self, encoder: &mut CommandEncoder, target: &HdrTarget, output: &TextureView) {
+        // The glow is added before tonemapping, so it's mapped like the rest of the scene
+        self.bloom.render(encoder, &target.bloom);
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Tonemapping Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+
+                // Every pixel is overwritten, so there's nothing to clear
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+
+        // 3 vertices make the triangle that covers the screen
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,439 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent,
+    BlendFactor, BlendOperation, BlendState, Buffer, BufferBindingType, BufferUsages,
+    ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d, FilterMode, FragmentState,
+    LoadOp, MultisampleState, Operations, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState,
+    Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule,
+    ShaderStages, StoreOp, SurfaceConfiguration, TextureDescriptor, TextureDimension,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+    VertexState,
+};
+
+use crate::{entry_points, hdr::HDR_FORMAT};
+
+/// The most mip levels the glow is blurred through, every level spreads it twice as far
+const MAX_MIP_LEVELS: u32 = 6;
+
+/// The smallest width or height a mip level can have. Smaller levels are only a few pixels,
+/// which makes the glow blocky, so small windows get fewer levels.
+const MIN_MIP_SIZE: u32 = 8;
+
+/// Adds the upsampled glow to the level above it, instead of replacing it
+const ADDITIVE_BLENDING: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent::REPLACE,
+};
+
+/// How the brightest parts of the HDR scene glow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrBloomSettings {
+    /// How bright a pixel has to be to glow. Colors can be brighter than 1 in HDR,
+    /// so only those above the range of the surface glow by default.
+    pub threshold: f32,
+
+    /// How much of the glow is added to the scene before it's tonemapped
+    pub intensity: f32,
+}
+
+impl HdrBloomSettings {
+    /// The lowest and highest threshold that make sense
+    pub const THRESHOLD_RANGE: (f32, f32) = (0.0, 10.0);
+
+    /// Beyond this, the glow drowns out the scene
+    pub const INTENSITY_RANGE: (f32, f32) = (0.0, 2.0);
+
+    /// Clamps both settings to their ranges
+    #[must_use]
+    pub const fn clamped(self) -> Self {
+        Self {
+            threshold: self
+                .threshold
+                .clamp(Self::THRESHOLD_RANGE.0, Self::THRESHOLD_RANGE.1),
+            intensity: self
+                .intensity
+                .clamp(Self::INTENSITY_RANGE.0, Self::INTENSITY_RANGE.1),
+        }
+    }
+}
+
+/// Only the colors the surface can't show glow, and only a little
+impl Default for HdrBloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.3,
+        }
+    }
+}
+
+/// The threshold as it's stored in the uniform buffer, the intensity is applied by the
+/// tonemapping pass
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct HdrBloomUniform {
+    threshold: f32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [f32; 3],
+}
+
+/// The number of mip levels for a target of the given size, at least 1.
+///
+/// The first level has half its resolution, and every next one half of that,
+/// until a level would be smaller than [`MIN_MIP_SIZE`].
+#[must_use]
+pub const fn mip_count(width: u32, height: u32) -> u32 {
+    let half_size = if width < height { width } else { height } / 2;
+    let mut count = 1;
+    while count < MAX_MIP_LEVELS && half_size >> count >= MIN_MIP_SIZE {
+        count += 1;
+    }
+    count
+}
+
+/// The mip chain the glow is blurred in for a target of a certain size.
+/// Replacing it drops the old textures, so they're freed when the target is resized.
+pub struct HdrBloomTarget {
+    /// Every level of the mip chain, the first one ends up with the whole glow.
+    /// The views keep their textures alive, so those aren't stored separately.
+    views: Vec<TextureView>,
+
+    /// Reads the scene in the first pass
+    scene_bind_group: BindGroup,
+
+    /// Read every level in the same order
+    bind_groups: Vec<BindGroup>,
+}
+
+impl HdrBloomTarget {
+    /// The first level of the mip chain, it has the glow when the bloom was rendered
+    #[must_use]
+    pub fn view(&self) -> &TextureView {
+        &self.views[0]
+    }
+}
+
+/// Makes the brightest parts of the HDR scene glow.
+///
+/// They're extracted while downsampling the scene to half its resolution, and downsampled
+/// further through a mip chain. Then every level is upsampled and added to the level above it.
+/// Every step blurs a little, and the small levels spread the glow far with few samples.
+/// The tonemapping pass adds the first level to the scene.
+pub struct HdrBloom {
+    texture_bind_group_layout: BindGroupLayout,
+
+    /// Interpolates between the texels, which smooths the downsampling and the upsampling
+    sampler: Sampler,
+    settings: HdrBloomSettings,
+    enabled: bool,
+    uniform_buffer: Buffer,
+    uniform_bind_group: BindGroup,
+    prefilter_pipeline: RenderPipeline,
+    downsample_pipeline: RenderPipeline,
+    upsample_pipeline: RenderPipeline,
+}
+
+impl HdrBloom {
+    /// Creates the pipelines of every pass, they all render to the HDR format
+    #[must_use]
+    pub fn new(device: &Device) -> Self {
+        // A texture and its sampler, for the input of every pass
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("HDR Bloom Texture Bind Group Layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let settings = HdrBloomSettings::default();
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("HDR Bloom Buffer"),
+            contents: bytemuck::cast_slice(&[HdrBloomUniform {
+                threshold: settings.threshold,
+                _padding: [0.0; 3],
+            }]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("HDR Bloom Uniform Bind Group Layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let uniform_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("HDR Bloom Uniform Bind Group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("HDR Bloom Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("hdr_bloom.wgsl"));
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("HDR Bloom Pipeline Layout"),
+            bind_group_layouts: &[&texture_bind_group_layout, &uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point, blend| {
+            Self::create_pipeline(device, &layout, &shader, entry_point, blend)
+        };
+
+        Self {
+            prefilter_pipeline: create_pipeline(
+                entry_points::HDR_BLOOM_PREFILTER,
+                BlendState::REPLACE,
+            ),
+            downsample_pipeline: create_pipeline(
+                entry_points::HDR_BLOOM_DOWNSAMPLE,
+                BlendState::REPLACE,
+            ),
+            upsample_pipeline: create_pipeline(entry_points::HDR_BLOOM_UPSAMPLE, ADDITIVE_BLENDING),
+            texture_bind_group_layout,
+            sampler,
+            settings,
+            enabled: true,
+            uniform_buffer,
+            uniform_bind_group,
+        }
+    }
+
+    /// Creates the pipeline of a pass, it draws a triangle that covers its target,
+    /// so it doesn't need a vertex buffer or a depth buffer
+    fn create_pipeline(
+        device: &Device,
+        layout: &PipelineLayout,
+        shader: &ShaderModule,
+        entry_point: &str,
+        blend: BlendState,
+    ) -> RenderPipeline {
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: entry_points::VERTEX,
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: shader,
+                entry_point,
+                targets: &[Some(ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(blend),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// Binds a texture with the linear sampler, to be read by a pass
+    fn create_bind_group(&self, device: &Device, view: &TextureView, label: &str) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some(label),
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    /// Interpolates between the texels, the tonemapping pass stretches the glow with it
+    #[must_use]
+    pub const fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// Creates the mip chain for a target with the size of the configuration,
+    /// the scene is read from the given view
+    #[must_use]
+    pub fn create_target(
+        &self,
+        device: &Device,
+        config: &SurfaceConfiguration,
+        scene: &TextureView,
+    ) -> HdrBloomTarget {
+        // Every level is a texture of its own instead of a mip level of a single texture,
+        // as the OpenGL backend can only read the first mip level of a texture
+        let views = (1..=mip_count(config.width, config.height))
+            .map(|level| {
+                device
+                    .create_texture(&TextureDescriptor {
+                        label: Some("HDR Bloom Texture"),
+
+                        // Half the resolution for the first level, but at least a pixel
+                        size: Extent3d {
+                            width: (config.width >> level).max(1),
+                            height: (config.height >> level).max(1),
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format: HDR_FORMAT,
+                        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&TextureViewDescriptor::default())
+            })
+            .collect::<Vec<_>>();
+        let bind_groups = views
+            .iter()
+            .map(|view| self.create_bind_group(device, view, "HDR Bloom Bind Group"))
+            .collect();
+        HdrBloomTarget {
+            scene_bind_group: self.create_bind_group(device, scene, "HDR Bloom Scene Bind Group"),
+            bind_groups,
+            views,
+        }
+    }
+
+    /// The current threshold and intensity
+    #[must_use]
+    pub const fn settings(&self) -> HdrBloomSettings {
+        self.settings
+    }
+
+    /// Changes the threshold and intensity, and uploads the threshold to the GPU.
+    /// Settings outside of their ranges are clamped.
+    pub fn set_settings(&mut self, queue: &Queue, settings: HdrBloomSettings) {
+        self.settings = settings.clamped();
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[HdrBloomUniform {
+                threshold: self.settings.threshold,
+                _padding: [0.0; 3],
+            }]),
+        );
+    }
+
+    /// Whether the glow is rendered and added to the scene
+    #[must_use]
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns the whole effect on or off, to compare the scene with and without it
+    pub const fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// How much of the glow the tonemapping pass adds, 0 while the bloom is off
+    #[must_use]
+    pub const fn intensity(&self) -> f32 {
+        if self.enabled {
+            self.settings.intensity
+        } else {
+            0.0
+        }
+    }
+
+    /// Renders the glow of the scene to the first level of the mip chain of the target.
+    /// Nothing is rendered while the bloom is off.
+    pub fn render(&self, encoder: &mut CommandEncoder, target: &HdrBloomTarget) {
+        if !self.enabled {
+            return;
+        }
+
+        // Scene -> 0 -> 1 -> ... -> last, every pass halves the resolution
+        self.draw_pass(
+            encoder,
+            &self.prefilter_pipeline,
+            &target.scene_bind_group,
+            &target.views[0],
+        );
+        for (input, output) in target.bind_groups.iter().zip(&target.views[1..]) {
+            self.draw_pass(encoder, &self.downsample_pipeline, input, output);
+        }
+
+        // last -> ... -> 1 -> 0, every level is blurred and added to the level above it
+        for (input, output) in target.bind_groups[1..].iter().zip(&target.views).rev() {
+            self.draw_pass(encoder, &self.upsample_pipeline, input, output);
+        }
+    }
+
+    /// Draws a pass that reads the input to the output, blending if the pipeline does
+    fn draw_pass(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        input: &BindGroup,
+        output: &TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("HDR Bloom Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+
+                // The upsampling adds to what the downsampling left there
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, input, &[]);
+        render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
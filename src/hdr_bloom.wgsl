@@ -0,0 +1,81 @@
+// Bloom in HDR: the brightest parts of the scene are extracted while it's downsampled, then
+// they're downsampled further through a mip chain, and every level is upsampled and added to the
+// level above it. The tonemapping pass adds the result to the scene.
+
+// Will store the output of the vertex shader
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+
+    // Where to read the input texture, from (0, 0) in the top left to (1, 1) in the bottom right
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// The same triangle that covers the whole target as in post_process.wgsl
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput{
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+// The texture the pass reads: the scene for the first pass, a level of the mip chain otherwise
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+// The settings of the bloom, as stored in `HdrBloomUniform`
+struct HdrBloomUniform{
+    // How bright a pixel has to be to glow, it can be above 1
+    threshold: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> bloom: HdrBloomUniform;
+
+// Averages the 4 texels around each corner of the output texel, and the 4 in its center.
+// The linear sampler averages 4 texels per sample, so it only needs 5 samples.
+fn downsample(tex_coords: vec2<f32>) -> vec3<f32>{
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_input));
+    var color = textureSample(t_input, s_input, tex_coords).rgb * 4.0;
+    color += textureSample(t_input, s_input, tex_coords + texel * vec2<f32>(-1.0, -1.0)).rgb;
+    color += textureSample(t_input, s_input, tex_coords + texel * vec2<f32>(1.0, -1.0)).rgb;
+    color += textureSample(t_input, s_input, tex_coords + texel * vec2<f32>(-1.0, 1.0)).rgb;
+    color += textureSample(t_input, s_input, tex_coords + texel * vec2<f32>(1.0, 1.0)).rgb;
+    return color / 8.0;
+}
+
+// Keeps the part of the color above the threshold, so pixels start glowing gradually.
+// Single very bright pixels would flicker as the camera moves, so the glow is limited.
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32>{
+    let color = min(downsample(in.tex_coords), vec3<f32>(64.0));
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let factor = max(luminance - bloom.threshold, 0.0) / max(luminance, 0.0001);
+    return vec4<f32>(color * factor, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32>{
+    return vec4<f32>(downsample(in.tex_coords), 1.0);
+}
+
+// Blurs the smaller level with a 3x3 tent filter while it's stretched to the larger level.
+// The pipeline adds it to what's already there.
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32>{
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_input));
+    var color = vec3<f32>(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            // The corners count once, the edges twice, and the center 4 times
+            let weight = f32((2 - abs(x)) * (2 - abs(y)));
+            let offset = texel * vec2<f32>(f32(x), f32(y));
+            color += textureSample(t_input, s_input, in.tex_coords + offset).rgb * weight;
+        }
+    }
+    return vec4<f32>(color / 16.0, 1.0);
+}
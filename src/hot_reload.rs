@@ -0,0 +1,65 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches a file for changes, so it can be reloaded while the application runs
+pub struct FileWatcher {
+    /// The watcher stops watching when it's dropped
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<notify::Result<Event>>,
+    path: PathBuf,
+}
+
+impl FileWatcher {
+    /// Starts watching the file at the given path
+    ///
+    /// # Errors
+    /// Returns an error if the directory of the file can't be watched
+    pub fn new(path: impl Into<PathBuf>) -> notify::Result<Self> {
+        let path = path.into();
+        let (sender, receiver) = mpsc::channel();
+
+        // Editors often save by replacing the file, which would stop a watch on the file itself.
+        // So the directory is watched instead, and events for other files are ignored.
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiver is only gone when the watcher is being dropped
+            let _ = sender.send(event);
+        })?;
+        watcher.watch(
+            path.parent().unwrap_or_else(|| Path::new(".")),
+            RecursiveMode::NonRecursive,
+        )?;
+
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+            path,
+        })
+    }
+
+    /// The path of the watched file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether the file was created or modified since the last call
+    #[must_use]
+    pub fn changed(&self) -> bool {
+        // Handle every pending event, a single save can cause multiple events
+        let mut changed = false;
+        for event in self.receiver.try_iter() {
+            match event {
+                Ok(event) if event.kind.is_create() || event.kind.is_modify() => {
+                    changed |= event.paths.iter().any(|path| path.ends_with(&self.path));
+                }
+                Ok(_) => {}
+                Err(error) => log::warn!("Couldn't watch {}: {error}", self.path.display()),
+            }
+        }
+        changed
+    }
+}
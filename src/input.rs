@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use winit::event::VirtualKeyCode;
+
+use crate::{camera::Direction, transform::TransformStep};
+
+/// Something the demo does when a key is pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Make the background red, green, or blue, this stops the hue animation
+    SetRed,
+    SetGreen,
+    SetBlue,
+
+    /// Reset the background to the color the demo started with
+    ResetBackground,
+
+    /// Start or stop cycling the background through all hues
+    ToggleBackgroundAnimation,
+
+    /// Switch to the next present mode to turn vertical sync on or off
+    CyclePresentMode,
+
+    /// Switch between a window and borderless fullscreen
+    ToggleFullscreen,
+
+    /// Save the current frame as a PNG file
+    Screenshot,
+
+    /// Save the albedo and normals of the G-buffer as PNG files, only natively
+    CaptureGBuffer,
+
+    /// Reload the shader from disk, only with the `hot-reload` feature
+    ReloadShader,
+
+    /// Turn multisampling on or off
+    ToggleMsaa,
+
+    /// Switch between filled triangles and a wireframe
+    ToggleWireframe,
+
+    /// Draw the models as triangles, a triangle strip, lines, a line strip, or points
+    CycleTopology,
+
+    /// Cull the back faces, the front faces, or nothing
+    CycleCullMode,
+
+    /// Apply the next post-processing effect
+    NextEffect,
+
+    /// Lower or raise the brightness the bloom starts at
+    LowerBloomThreshold,
+    RaiseBloomThreshold,
+
+    /// Spread the glow of the bloom less or further
+    LowerBloomStrength,
+    RaiseBloomStrength,
+
+    /// Make the HDR scene darker or brighter before it's tonemapped
+    LowerExposure,
+    RaiseExposure,
+
+    /// Switch between the Reinhard and the ACES tonemapper
+    CycleTonemapper,
+
+    /// Turn the glow of the bright parts of the HDR scene on or off
+    ToggleHdrBloom,
+
+    /// Switch split-screen rendering on or off
+    ToggleSplitScreen,
+
+    /// Show or hide the outline around the models
+    ToggleOutlines,
+
+    /// Show or hide the grid on the ground and the axes
+    ToggleGrid,
+
+    /// Show the sky behind the scene instead of the background color, or hide it again
+    ToggleSkybox,
+
+    /// Move, turn, or scale the model a single step
+    TransformModel(TransformStep),
+
+    /// Switch between a perspective and an orthographic camera
+    ToggleProjection,
+
+    /// Show less or more of the texture that's blended with the diffuse texture
+    LowerMixFactor,
+    RaiseMixFactor,
+
+    /// Make the fog thinner or thicker, it's off at the thinnest
+    LowerFogDensity,
+    RaiseFogDensity,
+
+    /// Double a list of numbers with a compute shader before the next frame, only natively
+    RunCompute,
+
+    /// Switch between the WGSL and the GLSL version of the texture shader,
+    /// only with the `glsl` feature
+    ToggleGlsl,
+
+    /// Grab or release the cursor to look around with the mouse
+    ToggleMouseLook,
+
+    /// Add a light where the cursor points, or remove the light that was added last
+    SpawnLight,
+    RemoveLight,
+
+    /// Switch to the next render pipeline
+    SwapPipeline,
+
+    /// Switch to the pipeline with this index, if there is one
+    SelectPipeline(usize),
+
+    /// Move the camera while the key is held down
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+}
+
+impl Action {
+    /// The direction the camera moves in while the key of this action is held down,
+    /// `None` for actions that happen once when the key is pressed
+    #[must_use]
+    pub const fn movement(self) -> Option<Direction> {
+        match self {
+            Self::MoveForward => Some(Direction::Forward),
+            Self::MoveBackward => Some(Direction::Backward),
+            Self::MoveLeft => Some(Direction::Left),
+            Self::MoveRight => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Maps keys to the actions they trigger, so the controls can be changed without editing
+/// the code that handles them. Every key triggers at most one action,
+/// but an action can have several keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMap {
+    bindings: HashMap<VirtualKeyCode, Action>,
+}
+
+impl InputMap {
+    /// Creates a map without any keys, use [`Self::default`] for the standard controls
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Makes the key trigger the action, replacing what it did before
+    #[must_use]
+    pub fn with_binding(mut self, key: VirtualKeyCode, action: Action) -> Self {
+        self.bindings.insert(key, action);
+        self
+    }
+
+    /// Makes the key do nothing
+    #[must_use]
+    pub fn without_binding(mut self, key: VirtualKeyCode) -> Self {
+        self.bindings.remove(&key);
+        self
+    }
+
+    /// The action the key triggers, if any
+    #[must_use]
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+}
+
+/// The controls the demo always had
+impl Default for InputMap {
+    fn default() -> Self {
+        let bindings = [
+            (VirtualKeyCode::R, Action::SetRed),
+            (VirtualKeyCode::G, Action::SetGreen),
+            (VirtualKeyCode::B, Action::SetBlue),
+            (VirtualKeyCode::Key0, Action::ResetBackground),
+            // A would fit better, but it's already used to move the camera
+            (VirtualKeyCode::C, Action::ToggleBackgroundAnimation),
+            (VirtualKeyCode::H, Action::ToggleBackgroundAnimation),
+            (VirtualKeyCode::V, Action::CyclePresentMode),
+            (VirtualKeyCode::F11, Action::ToggleFullscreen),
+            // Laptops often need an extra modifier key for F12
+            (VirtualKeyCode::F12, Action::Screenshot),
+            (VirtualKeyCode::P, Action::Screenshot),
+            (VirtualKeyCode::F9, Action::CaptureGBuffer),
+            (VirtualKeyCode::F5, Action::ReloadShader),
+            (VirtualKeyCode::M, Action::ToggleMsaa),
+            (VirtualKeyCode::L, Action::ToggleWireframe),
+            (VirtualKeyCode::T, Action::CycleTopology),
+            (VirtualKeyCode::F, Action::CycleCullMode),
+            (VirtualKeyCode::E, Action::NextEffect),
+            (VirtualKeyCode::LBracket, Action::LowerBloomThreshold),
+            (VirtualKeyCode::RBracket, Action::RaiseBloomThreshold),
+            (VirtualKeyCode::Minus, Action::LowerBloomStrength),
+            (VirtualKeyCode::Equals, Action::RaiseBloomStrength),
+            // The - and = next to the number keys already change the bloom
+            (VirtualKeyCode::NumpadSubtract, Action::LowerExposure),
+            (VirtualKeyCode::NumpadAdd, Action::RaiseExposure),
+            (VirtualKeyCode::Y, Action::CycleTonemapper),
+            (VirtualKeyCode::Q, Action::ToggleHdrBloom),
+            (VirtualKeyCode::K, Action::ToggleSplitScreen),
+            (VirtualKeyCode::U, Action::ToggleOutlines),
+            // G already makes the background green
+            (VirtualKeyCode::F3, Action::ToggleGrid),
+            (VirtualKeyCode::I, Action::ToggleSkybox),
+            (VirtualKeyCode::O, Action::ToggleProjection),
+            (VirtualKeyCode::J, Action::RunCompute),
+            (VirtualKeyCode::X, Action::ToggleGlsl),
+            (VirtualKeyCode::Tab, Action::ToggleMouseLook),
+            (VirtualKeyCode::N, Action::SpawnLight),
+            (VirtualKeyCode::Delete, Action::RemoveLight),
+            (VirtualKeyCode::Space, Action::SwapPipeline),
+            (VirtualKeyCode::W, Action::MoveForward),
+            (VirtualKeyCode::Up, Action::MoveForward),
+            (VirtualKeyCode::S, Action::MoveBackward),
+            (VirtualKeyCode::Down, Action::MoveBackward),
+            (VirtualKeyCode::A, Action::MoveLeft),
+            (VirtualKeyCode::D, Action::MoveRight),
+            // The camera still moves sideways with A and D
+            (VirtualKeyCode::Left, Action::LowerMixFactor),
+            (VirtualKeyCode::Right, Action::RaiseMixFactor),
+            (VirtualKeyCode::Comma, Action::LowerFogDensity),
+            (VirtualKeyCode::Period, Action::RaiseFogDensity),
+        ];
+
+        // 1 selects the first pipeline, up to 9
+        let number_keys = [
+            VirtualKeyCode::Key1,
+            VirtualKeyCode::Key2,
+            VirtualKeyCode::Key3,
+            VirtualKeyCode::Key4,
+            VirtualKeyCode::Key5,
+            VirtualKeyCode::Key6,
+            VirtualKeyCode::Key7,
+            VirtualKeyCode::Key8,
+            VirtualKeyCode::Key9,
+        ];
+        let pipeline_bindings = number_keys
+            .into_iter()
+            .enumerate()
+            .map(|(index, key)| (key, Action::SelectPipeline(index)));
+
+        // The letter keys are taken, so the model is moved with the numpad
+        let transform_bindings = [
+            (VirtualKeyCode::Numpad4, TransformStep::Left),
+            (VirtualKeyCode::Numpad6, TransformStep::Right),
+            (VirtualKeyCode::Numpad8, TransformStep::Forward),
+            (VirtualKeyCode::Numpad2, TransformStep::Backward),
+            (VirtualKeyCode::Numpad9, TransformStep::Up),
+            (VirtualKeyCode::Numpad3, TransformStep::Down),
+            (VirtualKeyCode::Numpad7, TransformStep::RotateLeft),
+            (VirtualKeyCode::Numpad1, TransformStep::RotateRight),
+            (VirtualKeyCode::NumpadMultiply, TransformStep::Grow),
+            (VirtualKeyCode::NumpadDivide, TransformStep::Shrink),
+            (VirtualKeyCode::Numpad5, TransformStep::Reset),
+        ]
+        .map(|(key, step)| (key, Action::TransformModel(step)));
+
+        Self {
+            bindings: bindings
+                .into_iter()
+                .chain(pipeline_bindings)
+                .chain(transform_bindings)
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Deg, InnerSpace, Matrix4, Quaternion, Rotation3, Vector3, Zero};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Buffer, BufferAddress, BufferUsages, Device, Queue, VertexAttribute, VertexBufferLayout,
+    VertexStepMode,
+};
+
+/// A copy of a mesh, placed somewhere in the scene
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+
+    /// 0 draws the instance with the texture of its model or material, 1 and up with that
+    /// layer of the instance texture array. The shader uses the last layer if there aren't enough.
+    pub texture_index: u32,
+}
+
+impl Instance {
+    /// Converts the position and rotation to a model matrix the shader can use
+    #[must_use]
+    pub fn to_raw(&self) -> InstanceRaw {
+        InstanceRaw {
+            model: (Matrix4::from_translation(self.position) * Matrix4::from(self.rotation)).into(),
+            texture_index: self.texture_index,
+        }
+    }
+
+    /// Creates a square grid of instances around the origin, `per_row` instances wide.
+    /// Every instance is rotated 45 degrees around the direction it's placed in.
+    /// The instances cycle through `texture_count` texture indices diagonally.
+    #[must_use]
+    pub fn grid(per_row: u16, spacing: f32, texture_count: u32) -> Vec<Self> {
+        // Move the grid, so its center is at the origin
+        let displacement = f32::from(per_row.saturating_sub(1)) * spacing / 2.0;
+
+        (0..per_row)
+            .flat_map(|z| {
+                (0..per_row).map(move |x| {
+                    let position = Vector3::new(
+                        f32::from(x).mul_add(spacing, -displacement),
+                        0.0,
+                        f32::from(z).mul_add(spacing, -displacement),
+                    );
+
+                    // Rotating around a zero vector isn't possible, so don't rotate at the origin
+                    let rotation = if position.is_zero() {
+                        Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0))
+                    } else {
+                        Quaternion::from_axis_angle(position.normalize(), Deg(45.0))
+                    };
+
+                    // A single texture is used when there's none, instead of dividing by zero
+                    let texture_index = u32::from(x + z) % texture_count.max(1);
+
+                    Self {
+                        position,
+                        rotation,
+                        texture_index,
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// Uploads the model matrices of the instances to a new instance buffer
+#[must_use]
+pub fn create_instance_buffer(device: &Device, instances: &[Instance]) -> Buffer {
+    let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Instance Buffer"),
+        contents: bytemuck::cast_slice(&instance_data),
+
+        // COPY_DST allows the instances to be updated later
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+    })
+}
+
+/// Uploads the current model matrices of the instances to an existing instance buffer
+pub fn write_instance_buffer(queue: &Queue, buffer: &Buffer, instances: &[Instance]) {
+    let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+    queue.write_buffer(buffer, 0, bytemuck::cast_slice(&instance_data));
+}
+
+/// The model matrix and texture layer of an instance, as it is stored in the instance buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    texture_index: u32,
+}
+
+impl InstanceRaw {
+    /// A vertex attribute can be at most a vec4, so the 4x4 matrix takes up 4 locations.
+    /// Locations 5 to 8 are used, to leave room for more vertex attributes.
+    /// The texture layer follows in location 9.
+    const ATTRIBUTES: [VertexAttribute; 5] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Uint32,
+    ];
+
+    /// Describes how a buffer of instances is laid out in memory
+    #[must_use]
+    pub const fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+
+            // The shader only moves on to the next element when it starts drawing a new instance
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
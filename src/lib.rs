@@ -1,24 +1,69 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 
+use std::time::Duration;
+
+use web_time::Instant;
 use wgpu::SurfaceError;
 use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    dpi::PhysicalSize,
+    event::Event,
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::Window,
 };
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use crate::state::State;
+use crate::{
+    app::{App, Context, StateBuilder, StateError},
+    state::State,
+};
 
+pub mod app;
+pub mod bloom;
+pub mod camera;
+pub mod capture;
+pub mod color;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod compute;
+pub mod draw;
+pub mod entry_points;
+pub mod fog;
+pub mod frustum;
+pub mod gbuffer;
+pub mod grid;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod hdr;
+pub mod hdr_bloom;
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+pub mod hot_reload;
+pub mod input;
+pub mod instance;
+pub mod light;
+pub mod model;
+pub mod post_process;
+pub mod resources;
+pub mod shader;
+pub mod skybox;
 pub mod state;
+#[cfg(feature = "text")]
+pub mod text;
+pub mod texture;
+pub mod time;
+pub mod transform;
+pub mod viewport;
 
-/// # Panics
-/// panics if the window couldn't be created
+/// How often the event loop logs the frame statistics
+const FRAME_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs the demo
+///
+/// # Errors
+/// Returns an error if the window couldn't be created, or the GPU couldn't be initialized
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 #[allow(clippy::future_not_send)]
-pub async fn run() {
+pub async fn run() -> Result<(), StateError> {
     // Required for wgpu error messages to be printed
     cfg_if::cfg_if! {
         if #[cfg(target_arch="wasm32")]{
@@ -29,9 +74,56 @@ pub async fn run() {
         }
     }
 
+    run_app(StateBuilder::new(), State::new).await
+}
+
+/// Resizes the surface, and lets the app resize everything that depends on it
+fn resize(ctx: &mut Context, app: &mut impl App, new_size: PhysicalSize<u32>) {
+    if ctx.resize(new_size) {
+        app.resize(ctx);
+    }
+}
+
+/// Lets the app update once with the time since the previous frame,
+/// or as many fixed steps as fit in that time
+fn update(ctx: &mut Context, app: &mut impl App, dt: Duration) {
+    match &mut ctx.fixed_timestep {
+        Some(timestep) => {
+            let step = timestep.step();
+            for _ in 0..timestep.advance(dt) {
+                app.update(ctx, step);
+            }
+        }
+        None => app.update(ctx, dt),
+    }
+}
+
+/// Creates the window and the GPU context with the builder, and runs the event loop for the app
+/// `create_app` creates. The app is created after the context, so it can create its
+/// resources on the GPU.
+///
+/// # Errors
+/// Returns an error if the window couldn't be created, or the GPU couldn't be initialized
+#[allow(clippy::future_not_send)]
+pub async fn run_app<A: App + 'static>(
+    builder: StateBuilder,
+    create_app: impl FnOnce(&Context) -> A,
+) -> Result<(), StateError> {
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-    let mut state = State::new(window).await;
+
+    // Without a GPU there is nothing to render with, so stop before the event loop starts
+    let mut ctx = builder.build(&event_loop).await?;
+    let mut app = create_app(&ctx);
+    app.load(&ctx).await;
+
+    // Only events for the window of the context are handled
+    let window_id = ctx.window().map(Window::id);
+
+    // When the previous frame was updated, to calculate how much time passed
+    let mut last_frame = Instant::now();
+
+    // Logs the frame statistics every second
+    let mut last_report = Instant::now();
 
     #[cfg(target_arch = "wasm32")]
     {
@@ -39,7 +131,8 @@ pub async fn run() {
         use winit::dpi::PhysicalSize;
         use winit::platform::web::WindowExtWebSys;
 
-        state.window().set_inner_size(PhysicalSize::new(450, 400));
+        let window = ctx.window().expect("The builder always creates a window");
+        window.set_inner_size(PhysicalSize::new(450, 400));
 
         web_sys::window()
             .and_then(|win| win.document())
@@ -53,12 +146,26 @@ pub async fn run() {
     }
 
     event_loop.run(move |event, _, control_flow| match event {
-        Event::RedrawRequested(window_id) if window_id == state.window().id() => {
-            state.update();
-            match state.render() {
+        // Nothing is rendered while the window can't be seen
+        Event::RedrawRequested(id) if Some(id) == window_id && ctx.is_visible() => {
+            // Calculate how much time passed since the previous frame
+            let now = Instant::now();
+            let dt = now - last_frame;
+            last_frame = now;
+            ctx.record_frame(dt);
+            if now - last_report >= FRAME_STATS_INTERVAL {
+                log::info!("{}", ctx.frame_stats());
+                last_report = now;
+            }
+
+            update(&mut ctx, &mut app, dt);
+            match ctx.render_frame(&mut app) {
                 Ok(()) => {}
                 // Reconfigue the surface if lost
-                Err(SurfaceError::Lost) => state.resize(state.size()),
+                Err(SurfaceError::Lost) => {
+                    let size = ctx.size;
+                    resize(&mut ctx, &mut app, size);
+                }
 
                 // The system is out of memory, we should probably quit
                 Err(SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
@@ -68,39 +175,37 @@ pub async fn run() {
             }
         }
         // RedrawRequested will onluy trigger once unless we manually request it.
-        Event::MainEventsCleared => state.window().request_redraw(),
-
-        // If the window changed
-        Event::WindowEvent { window_id, event } if window_id == state.window().id() => {
-            // And none of the applications inputs were used
-            if !state.input(&event) {
-                {
-                    // Check what event happened
-                    match event {
-                        // If the window resized, update the states size
-                        WindowEvent::Resized(physical_size) => state.resize(physical_size),
-
-                        // If the scale factor changed, update the states size
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            state.resize(*new_inner_size);
-                        }
-
-                        // If close was requested or escape was pressed, close the application
-                        winit::event::WindowEvent::CloseRequested
-                        | winit::event::WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                                    ..
-                                },
-                            ..
-                        } => *control_flow = ControlFlow::Exit,
-                        _ => {}
-                    }
+        // While the window is hidden, wait for events instead of rendering frames nobody sees.
+        Event::MainEventsCleared => {
+            if ctx.is_visible() {
+                *control_flow = ControlFlow::Poll;
+
+                // With a target frame rate, wait out the rest of the frame before the next one
+                ctx.limit_frame_rate(last_frame);
+                if let Some(window) = ctx.window() {
+                    window.request_redraw();
                 }
+            } else {
+                *control_flow = ControlFlow::Wait;
+
+                // The time spent hidden shouldn't be simulated in the first frame afterwards
+                last_frame = Instant::now();
             }
         }
+
+        // Raw mouse movement, used to look around with the camera
+        Event::DeviceEvent { event, .. } => {
+            app.device_input(&mut ctx, &event);
+        }
+
+        // If the window changed, let the app handle it.
+        // Close the application if it should exit, other events are done with.
+        Event::WindowEvent {
+            window_id: id,
+            event,
+        } if Some(id) == window_id && app.handle_window_event(&mut ctx, &event) => {
+            *control_flow = ControlFlow::Exit;
+        }
         _ => {}
     });
 }
@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Quaternion, Rad, Rotation3, Vector3, VectorSpace};
+use wgpu::{BufferBindingType, BufferUsages, Device};
+
+/// The most lights the scene can have. The light buffer always has room for this many,
+/// so it has to match `MAX_LIGHTS` in shader.wgsl, light.wgsl, and shader.frag.
+pub const MAX_LIGHTS: usize = 16;
+
+/// How the shaders declare the lights by default, as a uniform array that works everywhere
+const UNIFORM_DECLARATION: &str = "var<uniform> lights: array<Light, MAX_LIGHTS>;";
+
+/// How the shaders declare the lights when they're stored in a storage buffer
+const STORAGE_DECLARATION: &str = "var<storage, read> lights: array<Light, MAX_LIGHTS>;";
+
+/// How the GLSL shader declares the lights, as a uniform block or a storage block
+#[cfg(feature = "glsl")]
+const GLSL_UNIFORM_DECLARATION: &str = "layout(set = 3, binding = 0) uniform Lights";
+#[cfg(feature = "glsl")]
+const GLSL_STORAGE_DECLARATION: &str = "layout(set = 3, binding = 0) readonly buffer Lights";
+
+/// Whether the lights can be stored in a storage buffer. WebGL2 doesn't have storage buffers,
+/// `Limits::downlevel_webgl2_defaults` allows none, so a uniform buffer is used there instead.
+#[must_use]
+pub fn storage_supported(device: &Device) -> bool {
+    device.limits().max_storage_buffers_per_shader_stage > 0
+}
+
+/// The type of the binding the lights are stored in on the device
+#[must_use]
+pub fn binding_type(device: &Device) -> BufferBindingType {
+    if storage_supported(device) {
+        BufferBindingType::Storage { read_only: true }
+    } else {
+        BufferBindingType::Uniform
+    }
+}
+
+/// How the light buffer is used on the device. `COPY_DST` allows the lights to be updated.
+#[must_use]
+pub fn buffer_usage(device: &Device) -> BufferUsages {
+    let usage = if storage_supported(device) {
+        BufferUsages::STORAGE
+    } else {
+        BufferUsages::UNIFORM
+    };
+    usage | BufferUsages::COPY_DST
+}
+
+/// Declares the lights in the shader as a storage buffer if the device supports them.
+/// The shader declares them as a uniform array by default, as that works everywhere.
+#[must_use]
+pub fn shader_source<'a>(device: &Device, source: Cow<'a, str>) -> Cow<'a, str> {
+    declare_lights(device, source, UNIFORM_DECLARATION, STORAGE_DECLARATION)
+}
+
+/// Like [`shader_source`], for the GLSL shader
+#[cfg(feature = "glsl")]
+#[must_use]
+pub fn glsl_shader_source<'a>(device: &Device, source: Cow<'a, str>) -> Cow<'a, str> {
+    declare_lights(
+        device,
+        source,
+        GLSL_UNIFORM_DECLARATION,
+        GLSL_STORAGE_DECLARATION,
+    )
+}
+
+/// Replaces the uniform declaration with the storage one if the device supports them
+fn declare_lights<'a>(
+    device: &Device,
+    source: Cow<'a, str>,
+    uniform: &str,
+    storage: &str,
+) -> Cow<'a, str> {
+    if storage_supported(device) {
+        Cow::Owned(source.replace(uniform, storage))
+    } else {
+        source
+    }
+}
+
+/// The number of lights in the light buffer, the shaders only loop over those
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+pub struct LightCount {
+    pub count: u32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [u32; 3],
+}
+
+impl LightCount {
+    /// The count of a buffer with the given number of lights, at most [`MAX_LIGHTS`]
+    #[must_use]
+    // The count is at most 16, so it always fits
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn new(count: usize) -> Self {
+        Self {
+            count: if count < MAX_LIGHTS {
+                count
+            } else {
+                MAX_LIGHTS
+            } as u32,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// A point light as it is stored in the light buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct LightUniform {
+    /// The position of the light in world coordinates
+    pub position: [f32; 3],
+
+    /// How much of the light reaches the surfaces that face away from it, as it bounced around
+    /// the scene. Uniforms require 16 byte alignment, so it fills the space after the `vec3`.
+    pub ambient_strength: f32,
+
+    /// The color of the light, which is also its brightness
+    pub color: [f32; 3],
+
+    /// How bright the reflection of the light is on shiny surfaces
+    pub specular_strength: f32,
+}
+
+impl LightUniform {
+    /// The ambient strength of a new light, a little light reaches everywhere
+    pub const DEFAULT_AMBIENT_STRENGTH: f32 = 0.1;
+
+    /// The specular strength of a new light, the reflection is as bright as the light
+    pub const DEFAULT_SPECULAR_STRENGTH: f32 = 1.0;
+
+    #[must_use]
+    pub const fn new(position: [f32; 3], color: [f32; 3]) -> Self {
+        Self {
+            position,
+            ambient_strength: Self::DEFAULT_AMBIENT_STRENGTH,
+            color,
+            specular_strength: Self::DEFAULT_SPECULAR_STRENGTH,
+        }
+    }
+
+    /// Rotates the light around the y-axis through the origin
+    pub fn orbit(&mut self, angle: Rad<f32>) {
+        let position = Quaternion::from_angle_y(angle) * Vector3::from(self.position);
+        self.position = position.into();
+    }
+
+    /// The light part of the way from this light to the other one, 0.0 is this light and 1.0
+    /// the other one. Used to draw the light between two fixed updates.
+    #[must_use]
+    pub fn lerp(&self, other: &Self, alpha: f32) -> Self {
+        let position = Vector3::from(self.position).lerp(Vector3::from(other.position), alpha);
+        Self {
+            position: position.into(),
+            ..*other
+        }
+    }
+}
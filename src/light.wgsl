@@ -0,0 +1,69 @@
+// Draws a small copy of the model at the position of the light, to see where it is.
+// The bind groups match shader.wgsl, so they don't have to be switched.
+
+// The camera data, as stored in `CameraUniform`
+struct CameraUniform{
+    view_position: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// How colors have to be written to the surface, as stored in `ColorUniform`
+struct ColorUniform{
+    gamma_correct: u32,
+};
+
+@group(2) @binding(1)
+var<uniform> color_info: ColorUniform;
+
+// A point light, as stored in `LightUniform`
+struct Light{
+    position: vec3<f32>,
+    ambient_strength: f32,
+    color: vec3<f32>,
+    specular_strength: f32,
+};
+
+// The most lights there can be, it has to match `light::MAX_LIGHTS`
+const MAX_LIGHTS: u32 = 16u;
+
+// `light::shader_source` makes this a storage buffer if the GPU supports them, like shader.wgsl
+@group(3) @binding(0)
+var<uniform> lights: array<Light, MAX_LIGHTS>;
+
+// Only the position of the vertices is used, but the layout has to match `Vertex::desc`
+struct VertexInput{
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+// Scale used to make the model smaller than the lit copies
+const LIGHT_SCALE: f32 = 0.25;
+
+// Every instance is drawn at the light with its index
+@vertex
+fn vs_main(model: VertexInput, @builtin(instance_index) instance: u32) -> VertexOutput{
+    var out: VertexOutput;
+    let light = lights[instance];
+
+    // Move the scaled down model to the light
+    let world_position = model.position * LIGHT_SCALE + light.position;
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 1.0);
+    out.color = light.color;
+    return out;
+}
+
+// The light is drawn in its own color, without any shading
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
+    if color_info.gamma_correct != 0u {
+        return vec4<f32>(to_srgb(in.color), 1.0);
+    }
+    return vec4<f32>(in.color, 1.0);
+}
@@ -1,5 +1,8 @@
 use learn_wgpu::run;
 
 fn main() {
-    pollster::block_on(run());
+    if let Err(error) = pollster::block_on(run()) {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,554 @@
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{InnerSpace, MetricSpace, Point3, Vector2, Vector3, Zero};
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, BufferAddress, Device, IndexFormat, PrimitiveTopology,
+    RenderPass, VertexAttribute, VertexBufferLayout, VertexStepMode,
+};
+
+use crate::texture::{SharedTextures, Texture};
+
+/// A single vertex as it is stored in the vertex buffer.
+///
+/// `#[repr(C)]` makes sure the layout matches what the shader expects,
+/// `Pod` and `Zeroable` allow us to cast it to bytes for the buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+
+    /// Where on the texture this vertex is, `[0.0, 0.0]` is the top-left corner
+    /// and `[1.0, 1.0]` the bottom-right corner.
+    ///
+    /// wgpu's texture origin is the top-left corner, while the y-axis of positions
+    /// points up. So the v-coordinate has to be flipped (`v = 1.0 - y`) compared to
+    /// the position, otherwise images appear upside down.
+    pub tex_coords: [f32; 2],
+
+    /// The direction the surface faces at this vertex, used for lighting
+    pub normal: [f32; 3],
+
+    /// The directions the u- and v-coordinates of the texture point in, to rotate the normals
+    /// of the normal map from texture space to the model. They are zero where the texture
+    /// coordinates don't cover an area, then the normal map is ignored.
+    pub tangent: [f32; 3],
+    pub bitangent: [f32; 3],
+}
+
+impl Vertex {
+    /// The attributes of a vertex, in the same order as the fields.
+    /// `@location(0)` is the position, `@location(1)` are the texture coordinates,
+    /// `@location(2)` is the normal, `@location(3)` the tangent, and `@location(4)` the bitangent.
+    const ATTRIBUTES: [VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x2,
+        2 => Float32x3,
+        3 => Float32x3,
+        4 => Float32x3,
+    ];
+
+    /// Describes how a buffer of vertices is laid out in memory
+    #[must_use]
+    pub const fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            // How wide a vertex is, the shader will skip this many bytes to get to the next one
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+
+            // Every element in the buffer is a separate vertex
+            step_mode: VertexStepMode::Vertex,
+
+            // The fields of the vertex, 1:1 mapped to the fields of the struct
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// The vertices of a single triangle, in counter-clockwise order, facing the camera
+pub const TRIANGLE_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [0.0, 0.5, 0.0],
+        tex_coords: [0.5, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [-0.5, -0.5, 0.0],
+        tex_coords: [0.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, -0.5, 0.0],
+        tex_coords: [1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+];
+
+/// The vertices of the default pentagon, shared between the triangles that make it up.
+/// The texture coordinates are the positions moved to 0..1, with a flipped v-coordinate.
+///
+/// The pentagon is flat, so every normal points towards the camera. The u-coordinate follows
+/// the x-axis, and the flipped v-coordinate follows the negative y-axis.
+pub const VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.086_824_1, 0.492_403_86, 0.0],
+        tex_coords: [0.413_175_9, 0.007_596_14],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [-0.495_134_06, 0.069_586_47, 0.0],
+        tex_coords: [0.004_865_944, 0.430_413_54],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [-0.219_185_49, -0.449_397_06, 0.0],
+        tex_coords: [0.280_814_53, 0.949_397],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [0.359_669_98, -0.347_329_1, 0.0],
+        tex_coords: [0.859_67, 0.847_329_14],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [0.441_473_72, 0.234_735_9, 0.0],
+        tex_coords: [0.941_473_7, 0.265_264_1],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+];
+
+/// A triangle in front of the middle of the grid, it's drawn at half opacity over the models.
+/// Only the positions are used, in counter-clockwise order so it faces the camera.
+pub const TRANSLUCENT_TRIANGLE: &[Vertex] = &[
+    Vertex {
+        position: [-2.0, -0.5, 2.0],
+        tex_coords: [0.0, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [0.0, 0.0, 0.0],
+        bitangent: [0.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [2.0, -0.5, 2.0],
+        tex_coords: [0.0, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [0.0, 0.0, 0.0],
+        bitangent: [0.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [0.0, 2.5, 2.0],
+        tex_coords: [0.0, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [0.0, 0.0, 0.0],
+        bitangent: [0.0, 0.0, 0.0],
+    },
+];
+
+/// A square of 1 by 1 around the origin, facing the camera. The transparent quads are drawn with it.
+pub const QUAD_VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.5, 0.5, 0.0],
+        tex_coords: [0.0, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [-0.5, -0.5, 0.0],
+        tex_coords: [0.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, -0.5, 0.0],
+        tex_coords: [1.0, 1.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, 0.5, 0.0],
+        tex_coords: [1.0, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0],
+        bitangent: [0.0, -1.0, 0.0],
+    },
+];
+
+/// The 2 counter-clockwise triangles of the quad
+pub const QUAD_INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+/// The 3 triangles of the pentagon, every 3 indices point to the vertices of one triangle.
+/// Only 5 vertices are needed instead of 9, as the triangles share most of their corners.
+pub const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
+
+/// The indices of the pentagon for every topology, so each of them draws something visible.
+/// The corners of the pentagon are in counter-clockwise order.
+#[must_use]
+pub const fn shape_indices(topology: PrimitiveTopology) -> &'static [u16] {
+    match topology {
+        // Every corner once
+        PrimitiveTopology::PointList => &[0, 1, 2, 3, 4],
+
+        // Every edge of the outline separately, 2 indices per line
+        PrimitiveTopology::LineList => &[0, 1, 1, 2, 2, 3, 3, 4, 4, 0],
+
+        // The outline in one go, back to the first corner to close it
+        PrimitiveTopology::LineStrip => &[0, 1, 2, 3, 4, 0],
+
+        // Every index after the first 2 adds a triangle with the previous 2.
+        // Every other triangle is flipped by the GPU, so they all face forward.
+        PrimitiveTopology::TriangleStrip => &[0, 1, 4, 2, 3],
+        PrimitiveTopology::TriangleList => INDICES,
+    }
+}
+
+/// Converts the indices to the smallest index format that can address every vertex.
+///
+/// Returns the bytes to store in the index buffer and the format to read them with.
+///
+/// # Panics
+/// Panics if an index points past the vertices while 16-bit indices are used
+#[must_use]
+pub fn index_data(indices: &[u32], num_vertices: usize) -> (Vec<u8>, IndexFormat) {
+    // 16-bit indices take half the memory, but can only address 65536 vertices
+    if num_vertices <= usize::from(u16::MAX) + 1 {
+        let indices = indices
+            .iter()
+            .map(|&index| u16::try_from(index).unwrap())
+            .collect::<Vec<_>>();
+        (bytemuck::cast_slice(&indices).to_vec(), IndexFormat::Uint16)
+    } else {
+        (bytemuck::cast_slice(indices).to_vec(), IndexFormat::Uint32)
+    }
+}
+
+/// Gives every triangle its own copy of its corners, so no vertex is shared between triangles.
+/// Returns the copied vertices, and indices that draw them in order.
+#[must_use]
+pub fn unweld(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let vertices = indices
+        .iter()
+        .map(|&index| vertices[index as usize])
+        .collect::<Vec<_>>();
+    let indices = (0..u32::try_from(vertices.len()).unwrap_or(u32::MAX)).collect();
+    (vertices, indices)
+}
+
+/// Calculates the normal of every vertex from the triangles it's part of, for meshes that don't
+/// have normals.
+///
+/// The normals of the triangles are added up before they're normalized, so larger triangles
+/// count more. Vertices that aren't part of a triangle with an area face along z.
+pub fn compute_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![Vector3::zero(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let corners = [
+            triangle[0] as usize,
+            triangle[1] as usize,
+            triangle[2] as usize,
+        ];
+        let [p0, p1, p2] = corners.map(|index| Vector3::from(vertices[index].position));
+
+        // The cross product of two edges points out of the counter-clockwise side,
+        // its length is twice the area of the triangle
+        let normal = (p1 - p0).cross(p2 - p0);
+        for index in corners {
+            normals[index] += normal;
+        }
+    }
+
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normal = if normal.is_zero() {
+            [0.0, 0.0, 1.0]
+        } else {
+            normal.normalize().into()
+        };
+    }
+}
+
+/// Calculates the tangent and bitangent of every vertex from the positions and texture
+/// coordinates of the triangles it's part of, the results of the triangles are averaged.
+///
+/// Triangles without an area in texture space don't have a direction for u and v, so they're
+/// skipped. Vertices that are only part of those triangles get a zero tangent and bitangent.
+pub fn compute_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vector3::zero(); vertices.len()];
+    let mut bitangents = vec![Vector3::zero(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let corners = [
+            triangle[0] as usize,
+            triangle[1] as usize,
+            triangle[2] as usize,
+        ];
+        let [v0, v1, v2] = corners.map(|index| vertices[index]);
+
+        // The edges of the triangle, in model and in texture space
+        let delta_pos1 = Vector3::from(v1.position) - Vector3::from(v0.position);
+        let delta_pos2 = Vector3::from(v2.position) - Vector3::from(v0.position);
+        let delta_uv1 = Vector2::from(v1.tex_coords) - Vector2::from(v0.tex_coords);
+        let delta_uv2 = Vector2::from(v2.tex_coords) - Vector2::from(v0.tex_coords);
+
+        // Solve `delta_pos = delta_uv.x * tangent + delta_uv.y * bitangent` for both edges.
+        // The determinant is twice the area of the triangle in texture space.
+        let determinant = delta_uv1
+            .x
+            .mul_add(delta_uv2.y, -(delta_uv1.y * delta_uv2.x));
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+        let r = determinant.recip();
+        let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
+        let bitangent = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * r;
+
+        for index in corners {
+            tangents[index] += tangent;
+            bitangents[index] += bitangent;
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        // Zero vectors can't be normalized, they tell the shader to ignore the normal map
+        let normalize = |vector: Vector3<f32>| {
+            if vector.is_zero() {
+                vector
+            } else {
+                vector.normalize()
+            }
+        };
+        vertex.tangent = normalize(tangent).into();
+        vertex.bitangent = normalize(bitangent).into();
+    }
+}
+
+/// The distance from the origin to the vertex that's furthest away from it.
+/// A sphere with this radius around the origin contains the whole mesh, however it's rotated.
+#[must_use]
+pub fn bounding_radius(vertices: &[Vertex]) -> f32 {
+    vertices
+        .iter()
+        .map(|vertex| Vector3::from(vertex.position).magnitude())
+        .fold(0.0, f32::max)
+}
+
+/// The middle of the box around the vertices
+#[must_use]
+pub fn bounding_center(vertices: &[Vertex]) -> [f32; 3] {
+    let Some(first) = vertices.first() else {
+        return [0.0; 3];
+    };
+    let (min, max) = vertices.iter().fold(
+        (first.position, first.position),
+        |(mut min, mut max), vertex| {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex.position[axis]);
+                max[axis] = max[axis].max(vertex.position[axis]);
+            }
+            (min, max)
+        },
+    );
+    [0, 1, 2].map(|axis| f32::midpoint(min[axis], max[axis]))
+}
+
+/// A model loaded from a file, made of meshes that each use one of the materials
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+}
+
+impl Model {
+    /// The radius of the sphere around the origin that contains every mesh
+    #[must_use]
+    pub fn bounding_radius(&self) -> f32 {
+        self.meshes
+            .iter()
+            .map(|mesh| mesh.bounding_radius)
+            .fold(0.0, f32::max)
+    }
+}
+
+/// The textures to draw meshes with, and the bind group to make them available in group 0
+pub struct Material {
+    pub name: String,
+    pub diffuse_texture: Texture,
+
+    /// The directions the surface faces, relative to the tangent and bitangent
+    pub normal_texture: Texture,
+    pub bind_group: BindGroup,
+
+    /// Whether what's behind the material shows through it. Meshes with a transparent material
+    /// are drawn after the opaque ones, and blended with what's behind them.
+    pub is_transparent: bool,
+}
+
+impl Material {
+    /// Binds the textures with a layout created with [`Texture::create_bind_group_layout`].
+    /// The shared textures are used by every material, so they're kept alive by the caller.
+    #[must_use]
+    pub fn new(
+        device: &Device,
+        name: &str,
+        diffuse_texture: Texture,
+        normal_texture: Texture,
+        shared: &SharedTextures,
+        layout: &BindGroupLayout,
+    ) -> Self {
+        let bind_group = Texture::create_bind_group(
+            device,
+            layout,
+            &diffuse_texture,
+            &normal_texture,
+            shared,
+            name,
+        );
+        Self {
+            name: name.to_owned(),
+            diffuse_texture,
+            normal_texture,
+            bind_group,
+            is_transparent: false,
+        }
+    }
+
+    /// Marks the material as transparent or opaque, materials are opaque by default
+    #[must_use]
+    pub const fn with_transparency(mut self, is_transparent: bool) -> Self {
+        self.is_transparent = is_transparent;
+        self
+    }
+}
+
+/// A part of a model that is drawn with a single material
+pub struct Mesh {
+    pub name: String,
+    pub vertex_buffer: Buffer,
+
+    /// The indices are always stored as `u32`
+    pub index_buffer: Buffer,
+    pub num_elements: u32,
+
+    /// The index of the material in the model
+    pub material: usize,
+
+    /// The radius of the sphere around the origin that contains every vertex
+    pub bounding_radius: f32,
+
+    /// The middle of the box around the vertices, transparent meshes are sorted by its distance
+    /// to the camera
+    pub center: [f32; 3],
+}
+
+/// Adds methods to draw models to a render pass
+pub trait DrawModel<'a> {
+    /// Draws the given instances of a mesh, with the texture of the given material in group 0
+    fn draw_mesh(&mut self, mesh: &'a Mesh, material: &'a Material, instances: Range<u32>);
+
+    /// Draws the given instances of every mesh in the model, each with its own material
+    fn draw_model(&mut self, model: &'a Model, instances: Range<u32>);
+
+    /// Draws the given instances of the meshes with an opaque material
+    fn draw_model_opaque(&mut self, model: &'a Model, instances: Range<u32>);
+
+    /// Draws the given instances of the meshes with a transparent material, the mesh that's
+    /// furthest from the camera first. So every mesh is blended with the ones behind it.
+    ///
+    /// Whole meshes are sorted by their center, as if the model is at the origin. The triangles
+    /// of a mesh aren't sorted, and neither are meshes that cross each other.
+    fn draw_model_transparent(
+        &mut self,
+        model: &'a Model,
+        instances: Range<u32>,
+        camera_position: Point3<f32>,
+    );
+}
+
+impl<'a, 'b> DrawModel<'b> for RenderPass<'a>
+where
+    'b: 'a,
+{
+    fn draw_mesh(&mut self, mesh: &'b Mesh, material: &'b Material, instances: Range<u32>) {
+        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.set_index_buffer(mesh.index_buffer.slice(..), IndexFormat::Uint32);
+        self.set_bind_group(0, &material.bind_group, &[]);
+        self.draw_indexed(0..mesh.num_elements, 0, instances);
+    }
+
+    fn draw_model(&mut self, model: &'b Model, instances: Range<u32>) {
+        for mesh in &model.meshes {
+            let material = &model.materials[mesh.material];
+            self.draw_mesh(mesh, material, instances.clone());
+        }
+    }
+
+    fn draw_model_opaque(&mut self, model: &'b Model, instances: Range<u32>) {
+        for mesh in &model.meshes {
+            let material = &model.materials[mesh.material];
+            if !material.is_transparent {
+                self.draw_mesh(mesh, material, instances.clone());
+            }
+        }
+    }
+
+    fn draw_model_transparent(
+        &mut self,
+        model: &'b Model,
+        instances: Range<u32>,
+        camera_position: Point3<f32>,
+    ) {
+        let mut meshes = model
+            .meshes
+            .iter()
+            .filter(|mesh| model.materials[mesh.material].is_transparent)
+            .map(|mesh| (camera_position.distance2(Point3::from(mesh.center)), mesh))
+            .collect::<Vec<_>>();
+        meshes.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        for (_, mesh) in meshes {
+            let material = &model.materials[mesh.material];
+            self.draw_mesh(mesh, material, instances.clone());
+        }
+    }
+}
+
+/// Adds methods to draw models without their materials, for the light debug pipeline
+pub trait DrawLight<'a> {
+    /// Draws the given instances of a mesh, one for every light
+    fn draw_light_mesh(&mut self, mesh: &'a Mesh, instances: Range<u32>);
+
+    /// Draws the given instances of every mesh in the model
+    fn draw_light_model(&mut self, model: &'a Model, instances: Range<u32>);
+}
+
+impl<'a, 'b> DrawLight<'b> for RenderPass<'a>
+where
+    'b: 'a,
+{
+    fn draw_light_mesh(&mut self, mesh: &'b Mesh, instances: Range<u32>) {
+        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.set_index_buffer(mesh.index_buffer.slice(..), IndexFormat::Uint32);
+        self.draw_indexed(0..mesh.num_elements, 0, instances);
+    }
+
+    fn draw_light_model(&mut self, model: &'b Model, instances: Range<u32>) {
+        for mesh in &model.meshes {
+            self.draw_light_mesh(mesh, instances.clone());
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// Draws a slightly larger copy of every instance in a single color. The stencil test only
+// lets it through around the instances, where they didn't write to the stencil buffer.
+// The bind groups match shader.wgsl, so they don't have to be switched.
+
+// The camera data, as stored in `CameraUniform`
+struct CameraUniform{
+    view_position: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// The model matrix, as stored in `TransformUniform`
+struct TransformUniform{
+    model: mat4x4<f32>,
+};
+
+@group(1) @binding(1)
+var<uniform> model_transform: TransformUniform;
+
+// How colors have to be written to the surface, as stored in `ColorUniform`
+struct ColorUniform{
+    gamma_correct: u32,
+};
+
+@group(2) @binding(1)
+var<uniform> color_info: ColorUniform;
+
+// Small per-draw parameters, as stored in `DrawConstants`
+struct DrawConstants{
+    tint: vec3<f32>,
+    scale: f32,
+};
+
+// `draw::shader_source` replaces this with a `var<push_constant>` declaration if it can,
+// like in shader.wgsl, so the outline is scaled like the models
+@group(2) @binding(3)
+var<uniform> draw: DrawConstants;
+
+// Only the position of the vertices is used, but the layout has to match `Vertex::desc`
+struct VertexInput{
+    @location(0) position: vec3<f32>,
+};
+
+// The model matrix of an instance, as in shader.wgsl
+struct InstanceInput{
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+};
+
+// How much larger the outline is than the instance, and its linear color
+const OUTLINE_SCALE: f32 = 1.1;
+const COLOR: vec3<f32> = vec3<f32>(1.0, 0.6, 0.0);
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32>{
+    // The outline follows the model matrix, like the models in shader.wgsl
+    let model_matrix = model_transform.model * mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+
+    // Scale the model around its origin, before it's placed in the world
+    let position = model.position * draw.scale * OUTLINE_SCALE;
+    return camera.view_proj * model_matrix * vec4<f32>(position, 1.0);
+}
+
+// The outline isn't lit, so it stands out from the models
+@fragment
+fn fs_main() -> @location(0) vec4<f32>{
+    if color_info.gamma_correct != 0u {
+        return vec4<f32>(to_srgb(COLOR), 1.0);
+    }
+    return vec4<f32>(COLOR, 1.0);
+}
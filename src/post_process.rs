@@ -0,0 +1,318 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffer, BufferBindingType,
+    BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, Device, FragmentState, LoadOp,
+    MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    SamplerBindingType, ShaderStages, StoreOp, SurfaceConfiguration, TextureSampleType,
+    TextureView, TextureViewDimension, VertexState,
+};
+
+use crate::{
+    bloom::{Bloom, BloomSettings, BloomTarget},
+    entry_points,
+    texture::Texture,
+};
+
+/// The effects the post-processing pass can apply to the rendered scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Effect {
+    /// The scene is drawn as is, without a post-processing pass
+    #[default]
+    None,
+    Grayscale,
+    Invert,
+    Vignette,
+
+    /// The bright parts glow, it's rendered in its own passes by [`Bloom`]
+    Bloom,
+}
+
+impl Effect {
+    /// The effect after this one, wrapping around to no effect at the end
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::None => Self::Grayscale,
+            Self::Grayscale => Self::Invert,
+            Self::Invert => Self::Vignette,
+            Self::Vignette => Self::Bloom,
+            Self::Bloom => Self::None,
+        }
+    }
+
+    /// The number the shader branches on
+    const fn index(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Grayscale => 1,
+            Self::Invert => 2,
+            Self::Vignette => 3,
+            Self::Bloom => 4,
+        }
+    }
+}
+
+/// The effect as it is stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+struct PostUniform {
+    effect: u32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [u32; 3],
+}
+
+impl PostUniform {
+    const fn new(effect: Effect) -> Self {
+        Self {
+            effect: effect.index(),
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// The texture the scene is rendered to before the post-processing pass,
+/// the bind group the pass reads it with, and the textures of the bloom
+pub struct PostTarget {
+    texture: Texture,
+    bind_group: BindGroup,
+    bloom: BloomTarget,
+}
+
+impl PostTarget {
+    /// The texture to render the scene to
+    #[must_use]
+    pub const fn view(&self) -> &TextureView {
+        &self.texture.view
+    }
+}
+
+/// Renders the scene to an intermediate texture, and draws it to the surface with an effect
+/// in a second pass. The pass draws a single triangle that covers the screen.
+pub struct PostProcess {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    uniform_buffer: Buffer,
+    effect: Effect,
+    bloom: Bloom,
+
+    /// The target with the size of the surface, it has to be recreated when it's resized
+    target: PostTarget,
+}
+
+impl PostProcess {
+    /// Creates the pipeline, and a target with the size and format of the surface
+    #[must_use]
+    pub fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Post-processing Buffer"),
+            contents: bytemuck::cast_slice(&[PostUniform::new(Effect::None)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // The scene texture, its sampler, and the effect
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Post-processing Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Post-processing Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("post_process.wgsl"));
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Post-processing Pipeline"),
+            layout: Some(&layout),
+
+            // The vertices are calculated from their index, so there's no vertex buffer
+            vertex: VertexState {
+                module: &shader,
+                entry_point: entry_points::VERTEX,
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: entry_points::POST_PROCESS,
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+
+            // A single triangle covers the screen, it's never culled and doesn't need depth
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+
+            // The scene is already resolved when it's sampled
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let bloom = Bloom::new(device, config.format);
+        let target = Self::new_target(&bind_group_layout, &uniform_buffer, &bloom, device, config);
+        Self {
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            effect: Effect::None,
+            bloom,
+            target,
+        }
+    }
+
+    /// Creates a texture with the size and format of the configuration to render the scene to
+    fn new_target(
+        layout: &BindGroupLayout,
+        uniform_buffer: &Buffer,
+        bloom: &Bloom,
+        device: &Device,
+        config: &SurfaceConfiguration,
+    ) -> PostTarget {
+        let texture =
+            Texture::create_render_target(device, config, config.format, "Post-processing Input");
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Post-processing Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&texture.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&texture.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        PostTarget {
+            bloom: bloom.create_target(device, config, &texture.view),
+            texture,
+            bind_group,
+        }
+    }
+
+    /// Creates a target with a different size than the surface, like for screenshots
+    #[must_use]
+    pub fn create_target(&self, device: &Device, config: &SurfaceConfiguration) -> PostTarget {
+        Self::new_target(
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            &self.bloom,
+            device,
+            config,
+        )
+    }
+
+    /// Recreates the target with the new size of the surface.
+    /// The old textures are dropped with the old target, which frees them.
+    pub fn resize(&mut self, device: &Device, config: &SurfaceConfiguration) {
+        self.target = self.create_target(device, config);
+    }
+
+    /// The target with the size of the surface
+    #[must_use]
+    pub const fn target(&self) -> &PostTarget {
+        &self.target
+    }
+
+    /// The effect that is applied to the scene
+    #[must_use]
+    pub const fn effect(&self) -> Effect {
+        self.effect
+    }
+
+    /// Whether the scene has to be rendered to a target first.
+    /// Without an effect, it's faster to render the scene to the surface directly.
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.effect != Effect::None
+    }
+
+    /// Changes the effect, and uploads it to the GPU
+    pub fn set_effect(&mut self, queue: &Queue, effect: Effect) {
+        self.effect = effect;
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[PostUniform::new(effect)]),
+        );
+    }
+
+    /// The threshold and strength of the bloom
+    #[must_use]
+    pub const fn bloom_settings(&self) -> BloomSettings {
+        self.bloom.settings()
+    }
+
+    /// Changes the threshold and strength of the bloom, they're clamped to their ranges
+    pub fn set_bloom_settings(&mut self, queue: &Queue, settings: BloomSettings) {
+        self.bloom.set_settings(queue, settings);
+    }
+
+    /// Draws the scene in the target to the output with the effect applied
+    pub fn render(&self, encoder: &mut CommandEncoder, target: &PostTarget, output: &TextureView) {
+        if self.effect == Effect::Bloom {
+            self.bloom.render(encoder, &target.bloom, output);
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Post-processing Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+
+                // Every pixel is overwritten, so there's nothing to clear
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+
+        // 3 vertices make the triangle that covers the screen
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,70 @@
+// Post-processing: the scene is rendered to a texture first, and this shader draws it to the
+// surface with an effect applied to every pixel
+
+// Will store the output of the vertex shader
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+
+    // Where to read the scene texture, from (0, 0) in the top left to (1, 1) in the bottom right
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// Draws a single triangle that covers the whole screen, without a vertex buffer.
+// The vertices are at (-1, 1), (3, 1), and (-1, -3) in clip coordinates, everything outside of
+// the screen is clipped, so every pixel is drawn once.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput{
+    // 0 -> (0, 0), 1 -> (2, 0), 2 -> (0, 2)
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+// The rendered scene and the sampler to read it with
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+
+// Which effect to apply, as stored in `PostUniform`
+struct PostUniform{
+    // 0 is no effect, 1 grayscale, 2 inverted colors, and 3 a vignette.
+    // Bloom is drawn by bloom.wgsl instead.
+    effect: u32,
+};
+
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
+    let color = textureSample(t_scene, s_scene, in.tex_coords);
+
+    switch post.effect {
+        // Grayscale: the brightness of the color, green looks brightest to the human eye
+        case 1u: {
+            let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+            return vec4<f32>(vec3<f32>(luminance), color.a);
+        }
+
+        // Invert: every channel is flipped
+        case 2u: {
+            return vec4<f32>(1.0 - color.rgb, color.a);
+        }
+
+        // Vignette: the edges get darker the further they are from the center
+        case 3u: {
+            let distance = length(in.tex_coords - 0.5);
+            let brightness = smoothstep(0.8, 0.3, distance);
+            return vec4<f32>(color.rgb * brightness, color.a);
+        }
+
+        // No effect, the scene is copied as is
+        default: {
+            return color;
+        }
+    }
+}
@@ -0,0 +1,702 @@
+use std::{
+    collections::HashMap,
+    io::{BufReader, Cursor},
+    path::{Path, PathBuf},
+    sync::{PoisonError, RwLock},
+};
+
+#[cfg(feature = "gltf")]
+use std::borrow::Cow;
+
+#[cfg(feature = "gltf")]
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+use image::ImageError;
+use thiserror::Error;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupLayout, BufferUsages, Device, Queue,
+};
+
+#[cfg(feature = "gltf")]
+use crate::color;
+use crate::{
+    model::{self, Material, Mesh, Model, Vertex},
+    texture::{self, SharedTextures, Texture},
+};
+
+/// The reasons loading a resource can fail
+#[derive(Debug, Error)]
+pub enum LoadError {
+    /// The file doesn't exist, or the server responded with 404 on the web
+    #[error("{0} doesn't exist")]
+    NotFound(String),
+    #[error("couldn't read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// The server responded with another error than 404, only on the web
+    #[error("couldn't fetch {url}: the server responded with {status}")]
+    Http { url: String, status: u16 },
+
+    /// The request didn't reach the server, or the response couldn't be read, only on the web
+    #[error("couldn't fetch {url}: {message}")]
+    Network { url: String, message: String },
+    #[error("couldn't parse the model: {0}")]
+    Obj(#[from] tobj::LoadError),
+    #[error("couldn't decode the texture: {0}")]
+    Image(#[from] ImageError),
+    #[cfg(feature = "gltf")]
+    #[error("couldn't parse the glTF model: {0}")]
+    Gltf(#[from] gltf::Error),
+    #[cfg(feature = "gltf")]
+    #[error("data URIs aren't supported, the data has to be in a separate file or the GLB")]
+    DataUri,
+    #[cfg(feature = "gltf")]
+    #[error("buffer {0} of the model is shorter than its length")]
+    BufferTooShort(usize),
+}
+
+/// The directory or URL the resources are loaded from, if it was changed
+static BASE_PATH: RwLock<Option<String>> = RwLock::new(None);
+
+/// Loads the resources from another directory, or on the web from another URL.
+/// Relative URLs are relative to the page.
+pub fn set_base_path(path: impl Into<String>) {
+    *BASE_PATH.write().unwrap_or_else(PoisonError::into_inner) = Some(path.into());
+}
+
+/// Resources are stored in the `res` directory of the crate. On the web, the page is served
+/// from the crate, so they're in `res` next to it.
+fn base_path() -> String {
+    let base_path = BASE_PATH
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    base_path.unwrap_or_else(|| {
+        if cfg!(target_arch = "wasm32") {
+            "res".to_owned()
+        } else {
+            concat!(env!("CARGO_MANIFEST_DIR"), "/res").to_owned()
+        }
+    })
+}
+
+/// Where a file in the resource directory is
+#[cfg(not(target_arch = "wasm32"))]
+fn resource_path(file_name: &str) -> PathBuf {
+    Path::new(&base_path()).join(file_name)
+}
+
+/// A file that couldn't be read is only missing if it doesn't exist
+#[cfg(not(target_arch = "wasm32"))]
+fn io_error(path: PathBuf, source: std::io::Error) -> LoadError {
+    if source.kind() == std::io::ErrorKind::NotFound {
+        LoadError::NotFound(path.display().to_string())
+    } else {
+        LoadError::Io { path, source }
+    }
+}
+
+/// Requests a file in the resource directory from the server, and waits for the response
+#[cfg(target_arch = "wasm32")]
+async fn fetch(file_name: &str) -> Result<web_sys::Response, LoadError> {
+    use wasm_bindgen::JsCast;
+
+    let url = format!("{}/{file_name}", base_path());
+    let network_error = |error: wasm_bindgen::JsValue| LoadError::Network {
+        url: url.clone(),
+        message: format!("{error:?}"),
+    };
+
+    let window = web_sys::window().ok_or_else(|| LoadError::Network {
+        url: url.clone(),
+        message: "there's no window to fetch from".to_owned(),
+    })?;
+    let response: web_sys::Response =
+        wasm_bindgen_futures::JsFuture::from(window.fetch_with_str(&url))
+            .await
+            .map_err(network_error)?
+            .dyn_into()
+            .map_err(network_error)?;
+    match response.status() {
+        404 => Err(LoadError::NotFound(url)),
+        status if !response.ok() => Err(LoadError::Http { url, status }),
+        _ => Ok(response),
+    }
+}
+
+/// Reads the body of a response, `read` starts reading it as text or bytes
+#[cfg(target_arch = "wasm32")]
+async fn read_response(
+    file_name: &str,
+    read: Result<js_sys::Promise, wasm_bindgen::JsValue>,
+) -> Result<wasm_bindgen::JsValue, LoadError> {
+    let network_error = |error: wasm_bindgen::JsValue| LoadError::Network {
+        url: format!("{}/{file_name}", base_path()),
+        message: format!("{error:?}"),
+    };
+    wasm_bindgen_futures::JsFuture::from(read.map_err(network_error)?)
+        .await
+        .map_err(network_error)
+}
+
+/// Reads a text file from the resource directory natively, or fetches it from the server on
+/// the web
+///
+/// # Errors
+/// Returns an error if the file doesn't exist, or couldn't be read
+// Files are read without waiting natively, only fetching them has to be waited for
+#[cfg_attr(not(target_arch = "wasm32"), allow(clippy::unused_async))]
+pub async fn load_string(file_name: &str) -> Result<String, LoadError> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let response = fetch(file_name).await?;
+            let text = read_response(file_name, response.text()).await?;
+            Ok(text.as_string().unwrap_or_default())
+        } else {
+            let path = resource_path(file_name);
+            std::fs::read_to_string(&path).map_err(|source| io_error(path, source))
+        }
+    }
+}
+
+/// Reads a binary file from the resource directory natively, or fetches it from the server on
+/// the web
+///
+/// # Errors
+/// Returns an error if the file doesn't exist, or couldn't be read
+#[cfg_attr(not(target_arch = "wasm32"), allow(clippy::unused_async))]
+pub async fn load_binary(file_name: &str) -> Result<Vec<u8>, LoadError> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let response = fetch(file_name).await?;
+            let buffer = read_response(file_name, response.array_buffer()).await?;
+            Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+        } else {
+            let path = resource_path(file_name);
+            std::fs::read(&path).map_err(|source| io_error(path, source))
+        }
+    }
+}
+
+/// Reads an image file from the resource directory, and uploads it to the GPU
+///
+/// # Errors
+/// Returns an error if the file couldn't be read or decoded
+pub async fn load_texture(
+    file_name: &str,
+    is_normal_map: bool,
+    device: &Device,
+    queue: &Queue,
+) -> Result<Texture, LoadError> {
+    let data = load_binary(file_name).await?;
+    Ok(Texture::from_bytes(
+        device,
+        queue,
+        &data,
+        file_name,
+        is_normal_map,
+    )?)
+}
+
+/// Loads the textures of a material, `relative_path` turns the texture paths of the material
+/// into paths in the resource directory
+async fn load_material(
+    material: &tobj::Material,
+    relative_path: impl Fn(&Path) -> String,
+    device: &Device,
+    queue: &Queue,
+    shared: &SharedTextures,
+    layout: &BindGroupLayout,
+) -> Result<Material, LoadError> {
+    // The image is decoded here, to check whether any of it is transparent
+    let (diffuse_texture, is_opaque) = match &material.diffuse_texture {
+        Some(texture) => {
+            let file_name = relative_path(Path::new(texture));
+            let image = image::load_from_memory(&load_binary(&file_name).await?)?;
+            (
+                Texture::from_image(device, queue, &image, Some(&file_name), false),
+                texture::is_opaque(&image),
+            )
+        }
+        None => (
+            Texture::from_color(device, queue, [255; 4], "White Texture", false),
+            true,
+        ),
+    };
+    let normal_texture = match &material.normal_texture {
+        Some(texture) => {
+            load_texture(&relative_path(Path::new(texture)), true, device, queue).await?
+        }
+        None => Texture::flat_normal_map(device, queue),
+    };
+    Ok(Material::new(
+        device,
+        &material.name,
+        diffuse_texture,
+        normal_texture,
+        shared,
+        layout,
+    )
+    .with_transparency(!is_opaque))
+}
+
+/// The white material meshes without a material are drawn with
+fn white_material(
+    device: &Device,
+    queue: &Queue,
+    shared: &SharedTextures,
+    layout: &BindGroupLayout,
+) -> Material {
+    Material::new(
+        device,
+        "Default Material",
+        Texture::from_color(device, queue, [255; 4], "White Texture", false),
+        Texture::flat_normal_map(device, queue),
+        shared,
+        layout,
+    )
+}
+
+/// Uploads the vertices and indices of a mesh to the GPU, the buffers are labeled after the file
+fn create_mesh(
+    device: &Device,
+    file_name: &str,
+    name: String,
+    vertices: &[Vertex],
+    indices: &[u32],
+    material: usize,
+) -> Mesh {
+    let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some(&format!("{file_name} Vertex Buffer")),
+        contents: bytemuck::cast_slice(vertices),
+        usage: BufferUsages::VERTEX,
+    });
+    let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some(&format!("{file_name} Index Buffer")),
+        contents: bytemuck::cast_slice(indices),
+        usage: BufferUsages::INDEX,
+    });
+
+    Mesh {
+        name,
+        vertex_buffer,
+        index_buffer,
+        num_elements: u32::try_from(indices.len()).unwrap_or(u32::MAX),
+        material,
+        bounding_radius: model::bounding_radius(vertices),
+        center: model::bounding_center(vertices),
+    }
+}
+
+/// Loads an OBJ model and its materials from the resource directory.
+/// Meshes without a material are drawn with a white texture,
+/// materials without a normal map use a flat one.
+///
+/// Normals missing from the file are calculated from the triangles.
+/// Every material uses the same `shared` textures.
+///
+/// # Errors
+/// Returns an error if the model, its materials, or its textures couldn't be read or parsed
+pub async fn load_model(
+    file_name: &str,
+    device: &Device,
+    queue: &Queue,
+    shared: &SharedTextures,
+    layout: &BindGroupLayout,
+) -> Result<Model, LoadError> {
+    // Material and texture files are relative to the model
+    let directory = Path::new(file_name)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let relative_path = |path: &Path| directory.join(path).to_string_lossy().into_owned();
+
+    let obj_text = load_string(file_name).await?;
+
+    // The materials are parsed while the model is, which can't wait for them to be fetched.
+    // So the material files the model uses are loaded first.
+    let mut mtl_texts = HashMap::new();
+    for line in obj_text.lines() {
+        if let Some(("mtllib", mtl_file)) = line.trim().split_once(' ') {
+            let mtl_path = PathBuf::from(mtl_file.trim());
+            let mtl_text = load_string(&relative_path(&mtl_path)).await.ok();
+            mtl_texts.insert(mtl_path, mtl_text);
+        }
+    }
+
+    let (models, obj_materials) = tobj::load_obj_buf(
+        &mut BufReader::new(Cursor::new(obj_text)),
+        &tobj::LoadOptions {
+            // The pipelines can only draw triangles
+            triangulate: true,
+
+            // Use the same index for the position, texture coordinates, and normal of a vertex
+            single_index: true,
+            ..Default::default()
+        },
+        |path| {
+            let mtl_text = mtl_texts
+                .get(path)
+                .and_then(Option::as_deref)
+                .ok_or(tobj::LoadError::OpenFileFailed)?;
+            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mtl_text)))
+        },
+    )?;
+
+    let mut materials = Vec::new();
+    for material in &obj_materials? {
+        materials
+            .push(load_material(material, relative_path, device, queue, shared, layout).await?);
+    }
+
+    // Meshes without a material use an extra white material at the end
+    let default_material = materials.len();
+    if models.iter().any(|model| model.mesh.material_id.is_none()) {
+        materials.push(white_material(device, queue, shared, layout));
+    }
+
+    let meshes = models
+        .into_iter()
+        .map(|model| {
+            let mut vertices = (0..model.mesh.positions.len() / 3)
+                .map(|i| Vertex {
+                    position: [
+                        model.mesh.positions[i * 3],
+                        model.mesh.positions[i * 3 + 1],
+                        model.mesh.positions[i * 3 + 2],
+                    ],
+
+                    // OBJ files have their v-coordinate pointing up, so it has to be flipped.
+                    // Meshes without texture coordinates use the top-left of the texture.
+                    tex_coords: if model.mesh.texcoords.is_empty() {
+                        [0.0, 0.0]
+                    } else {
+                        [
+                            model.mesh.texcoords[i * 2],
+                            1.0 - model.mesh.texcoords[i * 2 + 1],
+                        ]
+                    },
+
+                    // Meshes without normals get them from their triangles below
+                    normal: if model.mesh.normals.is_empty() {
+                        [0.0; 3]
+                    } else {
+                        [
+                            model.mesh.normals[i * 3],
+                            model.mesh.normals[i * 3 + 1],
+                            model.mesh.normals[i * 3 + 2],
+                        ]
+                    },
+
+                    // Calculated from the triangles below
+                    tangent: [0.0; 3],
+                    bitangent: [0.0; 3],
+                })
+                .collect::<Vec<_>>();
+            if model.mesh.normals.is_empty() {
+                model::compute_normals(&mut vertices, &model.mesh.indices);
+            }
+            model::compute_tangents(&mut vertices, &model.mesh.indices);
+
+            create_mesh(
+                device,
+                file_name,
+                model.name,
+                &vertices,
+                &model.mesh.indices,
+                model.mesh.material_id.unwrap_or(default_material),
+            )
+        })
+        .collect();
+
+    Ok(Model { meshes, materials })
+}
+
+/// The path of a file a glTF model refers to, relative to the model.
+/// Data URIs embed the file in the model, but decoding them isn't supported.
+#[cfg(feature = "gltf")]
+fn gltf_file_path(directory: &Path, uri: &str) -> Result<String, LoadError> {
+    if uri.starts_with("data:") {
+        return Err(LoadError::DataUri);
+    }
+    Ok(directory.join(uri).to_string_lossy().into_owned())
+}
+
+/// Loads an image of a glTF model, from a buffer view or a separate file
+#[cfg(feature = "gltf")]
+async fn load_gltf_texture(
+    texture: &gltf::Texture<'_>,
+    directory: &Path,
+    buffers: &[Cow<'_, [u8]>],
+    is_normal_map: bool,
+    device: &Device,
+    queue: &Queue,
+) -> Result<Texture, LoadError> {
+    let image = texture.source();
+    let label = image
+        .name()
+        .map_or_else(|| format!("Image {}", image.index()), str::to_owned);
+    let bytes = match image.source() {
+        gltf::image::Source::View { view, .. } => {
+            Cow::Borrowed(&buffers[view.buffer().index()][view.offset()..][..view.length()])
+        }
+        gltf::image::Source::Uri { uri, .. } => {
+            Cow::Owned(load_binary(&gltf_file_path(directory, uri)?).await?)
+        }
+    };
+    Ok(Texture::from_bytes(
+        device,
+        queue,
+        &bytes,
+        &label,
+        is_normal_map,
+    )?)
+}
+
+/// Loads the base color texture and normal map of a glTF material.
+/// Without a base color texture, the material is drawn in its base color factor.
+#[cfg(feature = "gltf")]
+async fn load_gltf_material(
+    material: &gltf::Material<'_>,
+    directory: &Path,
+    buffers: &[Cow<'_, [u8]>],
+    device: &Device,
+    queue: &Queue,
+    shared: &SharedTextures,
+    layout: &BindGroupLayout,
+) -> Result<Material, LoadError> {
+    let name = material.name().unwrap_or("Material");
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor().map(f64::from);
+    let diffuse_texture = match pbr.base_color_texture() {
+        Some(info) => {
+            load_gltf_texture(&info.texture(), directory, buffers, false, device, queue).await?
+        }
+        // The factor is linear, the texture stores sRGB
+        None => Texture::from_color(
+            device,
+            queue,
+            color::to_rgba8(wgpu::Color { r, g, b, a }),
+            "Base Color",
+            false,
+        ),
+    };
+    let normal_texture = match material.normal_texture() {
+        Some(info) => {
+            load_gltf_texture(&info.texture(), directory, buffers, true, device, queue).await?
+        }
+        None => Texture::flat_normal_map(device, queue),
+    };
+    Ok(Material::new(
+        device,
+        name,
+        diffuse_texture,
+        normal_texture,
+        shared,
+        layout,
+    )
+    .with_transparency(material.alpha_mode() == gltf::material::AlphaMode::Blend))
+}
+
+/// Collects the meshes of the node and its children, with the transforms that place them in the
+/// model. The transform of a node is relative to its parent.
+#[cfg(feature = "gltf")]
+fn flatten_gltf_nodes<'a>(
+    node: &gltf::Node<'a>,
+    parent: Matrix4<f32>,
+    meshes: &mut Vec<(gltf::Mesh<'a>, Matrix4<f32>)>,
+) {
+    let transform = parent * Matrix4::from(node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        meshes.push((mesh, transform));
+    }
+    for child in node.children() {
+        flatten_gltf_nodes(&child, transform, meshes);
+    }
+}
+
+/// Reads the vertices and indices of a glTF primitive, and moves them into the model with the
+/// transform of its node. Returns `None` if the primitive doesn't have positions.
+#[cfg(feature = "gltf")]
+fn read_gltf_primitive(
+    primitive: &gltf::Primitive<'_>,
+    transform: Matrix4<f32>,
+    buffers: &[Cow<'_, [u8]>],
+) -> Option<(Vec<Vertex>, Vec<u32>)> {
+    // The reader follows the accessors into the buffers, whether the attributes of a vertex are
+    // next to each other or in separate buffer views
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(AsRef::as_ref));
+    let positions = reader.read_positions()?.collect::<Vec<_>>();
+    let normals = reader.read_normals().map(Iterator::collect::<Vec<_>>);
+    let tex_coords = reader
+        .read_tex_coords(0)
+        .map(|tex_coords| tex_coords.into_f32().collect::<Vec<_>>());
+
+    // Primitives without indices use every vertex once, in order
+    let mut indices = reader.read_indices().map_or_else(
+        || (0..u32::try_from(positions.len()).unwrap_or(u32::MAX)).collect(),
+        |indices| indices.into_u32().collect::<Vec<_>>(),
+    );
+
+    // Normals are turned with the inverse transpose, so they stay perpendicular to the surface
+    // when the node is scaled unevenly
+    let linear = Matrix3::from_cols(
+        transform.x.truncate(),
+        transform.y.truncate(),
+        transform.z.truncate(),
+    );
+    let normal_matrix = linear
+        .invert()
+        .map_or(linear, |inverse| inverse.transpose());
+
+    let mut vertices = positions
+        .iter()
+        .enumerate()
+        .map(|(i, &position)| Vertex {
+            position: transform.transform_point(Point3::from(position)).into(),
+
+            // glTF's texture origin is the top-left corner too, so nothing is flipped
+            tex_coords: tex_coords
+                .as_ref()
+                .and_then(|tex_coords| tex_coords.get(i).copied())
+                .unwrap_or_default(),
+
+            // Meshes without normals get them from their triangles below
+            normal: normals
+                .as_ref()
+                .and_then(|normals| normals.get(i))
+                .map_or([0.0; 3], |&normal| {
+                    (normal_matrix * Vector3::from(normal)).normalize().into()
+                }),
+
+            // Calculated from the triangles below
+            tangent: [0.0; 3],
+            bitangent: [0.0; 3],
+        })
+        .collect::<Vec<_>>();
+
+    // A mirroring transform turns the triangles inside out, so their winding is reversed to keep
+    // facing the same way
+    if linear.determinant() < 0.0 {
+        for triangle in indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    // glTF asks for flat normals when they're missing, so every triangle gets its own corners.
+    // Then the normal of a corner is only calculated from its own triangle.
+    if normals.is_none() {
+        (vertices, indices) = model::unweld(&vertices, &indices);
+        model::compute_normals(&mut vertices, &indices);
+    }
+    model::compute_tangents(&mut vertices, &indices);
+    Some((vertices, indices))
+}
+
+/// Loads a glTF or GLB model and its materials from the resource directory.
+///
+/// Buffers and images can be in the GLB or in separate files, but not in data URIs.
+/// Meshes without a material are drawn with a white texture, and meshes without normals are
+/// shaded flat.
+///
+/// The nodes of the default scene are flattened: every mesh is copied with the transform of
+/// its node applied to the vertices. So the model is drawn with the instances like an OBJ model.
+/// Only triangles are loaded, other primitives are skipped with a warning.
+///
+/// # Errors
+/// Returns an error if the model, its buffers, or its textures couldn't be read or parsed
+#[cfg(feature = "gltf")]
+pub async fn load_gltf(
+    file_name: &str,
+    device: &Device,
+    queue: &Queue,
+    shared: &SharedTextures,
+    layout: &BindGroupLayout,
+) -> Result<Model, LoadError> {
+    // Buffers and images are relative to the model
+    let directory = Path::new(file_name)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+
+    let gltf::Gltf { document, blob } = gltf::Gltf::from_slice(&load_binary(file_name).await?)?;
+    let mut buffers = Vec::new();
+    for buffer in document.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => Cow::Borrowed(blob.as_deref().unwrap_or_default()),
+            gltf::buffer::Source::Uri(uri) => {
+                Cow::Owned(load_binary(&gltf_file_path(directory, uri)?).await?)
+            }
+        };
+        if data.len() < buffer.length() {
+            return Err(LoadError::BufferTooShort(buffer.index()));
+        }
+        buffers.push(data);
+    }
+
+    let mut materials = Vec::new();
+    for material in document.materials() {
+        materials.push(
+            load_gltf_material(
+                &material, directory, &buffers, device, queue, shared, layout,
+            )
+            .await?,
+        );
+    }
+
+    // Primitives without a material use an extra white material at the end
+    let default_material = materials.len();
+    if document
+        .meshes()
+        .flat_map(|mesh| mesh.primitives())
+        .any(|primitive| primitive.material().index().is_none())
+    {
+        materials.push(white_material(device, queue, shared, layout));
+    }
+
+    // Files without scenes are a library of meshes, they're all drawn where they are
+    let mut nodes = Vec::new();
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            for node in scene.nodes() {
+                flatten_gltf_nodes(&node, Matrix4::identity(), &mut nodes);
+            }
+        }
+        None => nodes.extend(document.meshes().map(|mesh| (mesh, Matrix4::identity()))),
+    }
+
+    let mut meshes = Vec::new();
+    for (mesh, transform) in nodes {
+        let name = mesh
+            .name()
+            .map_or_else(|| format!("Mesh {}", mesh.index()), str::to_owned);
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                log::warn!(
+                    "Skipped {:?} in {name}, only triangles are supported",
+                    primitive.mode()
+                );
+                continue;
+            }
+            let Some((vertices, indices)) = read_gltf_primitive(&primitive, transform, &buffers)
+            else {
+                log::warn!("Skipped a primitive without positions in {name}");
+                continue;
+            };
+            meshes.push(create_mesh(
+                device,
+                file_name,
+                name.clone(),
+                &vertices,
+                &indices,
+                primitive.material().index().unwrap_or(default_material),
+            ));
+        }
+    }
+
+    Ok(Model { meshes, materials })
+}
@@ -0,0 +1,156 @@
+// Fragment shader, a GLSL port of `fs_main` in shader.wgsl.
+// It should look exactly like the WGSL version, X switches between them.
+#version 450
+
+// The camera data, as stored in `CameraUniform`
+layout(set = 1, binding = 0) uniform CameraUniform {
+    vec4 view_position;
+    mat4 view_proj;
+} camera;
+
+// The number of seconds since the application started, as stored in `TimeUniform`
+layout(set = 2, binding = 0) uniform TimeUniform {
+    float u_time;
+};
+
+// How colors have to be written to the surface, as stored in `ColorUniform`
+layout(set = 2, binding = 1) uniform ColorUniform {
+    // 1 if the surface isn't sRGB, so the output has to be gamma corrected here
+    uint gamma_correct;
+} color_info;
+
+// How much of the blend texture is mixed in, as stored in `MixUniform`
+layout(set = 2, binding = 2) uniform MixUniform {
+    float factor;
+} mix_info;
+
+// Small per-draw parameters, as stored in `DrawConstants`
+layout(set = 2, binding = 3) uniform DrawConstants {
+    vec3 tint;
+    float scale;
+} draw;
+
+// A point light, as stored in `LightUniform`
+struct Light {
+    vec3 position;
+    float ambient_strength;
+    vec3 color;
+    float specular_strength;
+};
+
+// The most lights there can be, it has to match `light::MAX_LIGHTS`
+#define MAX_LIGHTS 16
+
+// The lights, `light::glsl_shader_source` makes this a storage block if the GPU supports them
+layout(set = 3, binding = 0) uniform Lights {
+    Light lights[MAX_LIGHTS];
+};
+
+// The number of lights in use, as stored in `LightCount`
+layout(set = 3, binding = 1) uniform LightCount {
+    uint light_count;
+};
+
+// The fog, as stored in `FogUniform`
+layout(set = 3, binding = 2) uniform FogUniform {
+    vec3 color;
+    float density;
+    float start;
+    float end;
+
+    // 0 for exponential fog, 1 for linear fog
+    uint mode;
+} fog;
+
+// The texture and the sampler to read it with, the normal map, and the texture to blend with.
+// GLSL for Vulkan keeps textures and samplers apart like WGSL, they're combined where they're
+// sampled.
+layout(set = 0, binding = 0) uniform texture2D t_diffuse;
+layout(set = 0, binding = 1) uniform sampler s_diffuse;
+layout(set = 0, binding = 2) uniform texture2D t_normal;
+layout(set = 0, binding = 3) uniform sampler s_normal;
+layout(set = 0, binding = 4) uniform texture2D t_blend;
+layout(set = 0, binding = 5) uniform sampler s_blend;
+layout(set = 0, binding = 6) uniform texture2DArray t_instances;
+layout(set = 0, binding = 7) uniform sampler s_instances;
+
+// The interpolated outputs of the vertex shader. wgpu requires every output to be read,
+// so the position is declared even though only the gradient shader uses it.
+layout(location = 0) in vec2 v_vert_pos;
+layout(location = 1) in vec2 v_tex_coords;
+layout(location = 2) in vec3 v_world_normal;
+layout(location = 3) in vec3 v_world_position;
+layout(location = 4) in vec3 v_world_tangent;
+layout(location = 5) in vec3 v_world_bitangent;
+layout(location = 6) flat in uint v_texture_index;
+
+// The first color target
+layout(location = 0) out vec4 f_color;
+
+// Reads the normal map, and rotates the normal from texture space to world space
+vec3 surface_normal() {
+    vec3 normal = normalize(v_world_normal);
+
+    // Triangles without an area in texture space don't have a tangent
+    if (length(v_world_tangent) < 0.001 || length(v_world_bitangent) < 0.001) {
+        return normal;
+    }
+
+    vec3 tangent_normal = texture(sampler2D(t_normal, s_normal), v_tex_coords).xyz * 2.0 - 1.0;
+    mat3 tbn = mat3(normalize(v_world_tangent), normalize(v_world_bitangent), normal);
+    return normalize(tbn * tangent_normal);
+}
+
+// Converts a linear color to sRGB, like an sRGB surface would do when it's written to
+vec3 to_srgb(vec3 linear) {
+    bvec3 cutoff = lessThanEqual(linear, vec3(0.0031308));
+    vec3 lower = linear * 12.92;
+    vec3 higher = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+    // Instances with a texture index above 0 use a layer of the instance textures instead.
+    // The layer is clamped to the layers of the array, like in WGSL.
+    vec4 diffuse = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords);
+    float layer = float(max(v_texture_index, 1u) - 1u);
+    vec4 instance = texture(sampler2DArray(t_instances, s_instances), vec3(v_tex_coords, layer));
+    vec4 object_color = mix(
+        v_texture_index > 0u ? instance : diffuse,
+        texture(sampler2D(t_blend, s_blend), v_tex_coords),
+        mix_info.factor
+    );
+
+    // Ambient, diffuse and Blinn-Phong specular lighting of every light, like in shader.wgsl
+    vec3 normal = surface_normal();
+    vec3 view_dir = normalize(camera.view_position.xyz - v_world_position);
+    float ambient_pulse = 1.0 + 0.5 * sin(u_time);
+    vec3 lighting = vec3(0.0);
+    for (uint i = 0u; i < min(light_count, uint(MAX_LIGHTS)); i++) {
+        Light light = lights[i];
+        vec3 ambient_color = light.color * light.ambient_strength * ambient_pulse;
+
+        vec3 light_dir = normalize(light.position - v_world_position);
+        vec3 diffuse_color = light.color * max(dot(normal, light_dir), 0.0);
+
+        vec3 half_dir = normalize(view_dir + light_dir);
+        vec3 specular_color =
+            light.color * pow(max(dot(normal, half_dir), 0.0), 32.0) * light.specular_strength;
+
+        lighting += ambient_color + diffuse_color + specular_color;
+    }
+
+    // Fade the color into the fog by its distance to the camera, like in shader.wgsl
+    float distance = length(camera.view_position.xyz - v_world_position);
+    float visibility = fog.mode == 1u
+        ? clamp((fog.end - distance) / (fog.end - fog.start), 0.0, 1.0)
+        : exp(-fog.density * distance);
+    vec3 fogged = mix(lighting * object_color.rgb, fog.color, 1.0 - visibility);
+
+    // Tint the color, and gamma correct it if the surface doesn't
+    vec3 result = fogged * draw.tint;
+    if (color_info.gamma_correct != 0u) {
+        result = to_srgb(result);
+    }
+    f_color = vec4(result, object_color.a);
+}
@@ -0,0 +1,291 @@
+#[cfg(any(feature = "glsl", feature = "spirv"))]
+use std::borrow::Cow;
+#[cfg(feature = "glsl")]
+use std::path::Path;
+
+use naga::{front::wgsl, Binding};
+use thiserror::Error;
+#[cfg(feature = "glsl")]
+use wgpu::naga::{
+    front::glsl::{Frontend, Options},
+    ShaderStage,
+};
+#[cfg(any(feature = "glsl", feature = "spirv"))]
+use wgpu::{
+    naga::{
+        valid::{Capabilities, ValidationFlags, Validator},
+        Module,
+    },
+    Features, ShaderModuleDescriptor, ShaderSource,
+};
+use wgpu::{Device, ErrorFilter, ShaderModule};
+
+/// The entry point of GLSL shaders. Every stage is a separate shader with a `main` function.
+pub const GLSL_ENTRY_POINT: &str = "main";
+
+/// The shader modules and entry points a render pipeline runs.
+/// WGSL shaders usually have both stages in one module, GLSL shaders need one module per stage.
+#[derive(Debug, Clone, Copy)]
+pub struct ShaderDesc<'a> {
+    pub vertex: &'a ShaderModule,
+    pub vs_entry: &'a str,
+    pub fragment: &'a ShaderModule,
+    pub fs_entry: &'a str,
+}
+
+impl<'a> ShaderDesc<'a> {
+    /// Runs both stages from the same module, like a WGSL file with both entry points
+    #[must_use]
+    pub const fn new(module: &'a ShaderModule, vs_entry: &'a str, fs_entry: &'a str) -> Self {
+        Self {
+            vertex: module,
+            vs_entry,
+            fragment: module,
+            fs_entry,
+        }
+    }
+
+    /// Runs separately compiled vertex and fragment shaders, like GLSL shaders
+    #[must_use]
+    pub const fn glsl(vertex: &'a ShaderModule, fragment: &'a ShaderModule) -> Self {
+        Self {
+            vertex,
+            vs_entry: GLSL_ENTRY_POINT,
+            fragment,
+            fs_entry: GLSL_ENTRY_POINT,
+        }
+    }
+}
+
+/// The reasons a GLSL or SPIR-V shader couldn't be loaded
+#[cfg(any(feature = "glsl", feature = "spirv"))]
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("couldn't read {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("can't tell the stage of {0}, GLSL shaders need a .vert, .frag, or .comp extension")]
+    UnknownStage(String),
+    #[error("couldn't parse {path}:\n{message}")]
+    Parse { path: String, message: String },
+    #[error("{path} isn't a valid shader: {message}")]
+    Validation { path: String, message: String },
+}
+
+/// A shader or pipeline wgpu rejected, like a shader that doesn't compile
+#[derive(Debug, Error)]
+#[error("{shader} failed to compile: {message}")]
+pub struct PipelineError {
+    /// The shader the pipeline was created with, like its path
+    pub shader: String,
+
+    /// What wgpu reported, with the lines of the shader that are wrong
+    pub message: String,
+}
+
+/// Creates shader modules or pipelines for a shader, and returns the first validation error
+/// wgpu reported instead of letting it panic.
+///
+/// Those errors would otherwise only show up deep in wgpu's logs.
+/// `shader` names the shader in the error, like its path.
+///
+/// # Errors
+/// Returns an error if wgpu reported a validation error while `create` ran
+pub async fn validate_creation<T>(
+    device: &Device,
+    shader: &str,
+    create: impl FnOnce() -> T,
+) -> Result<T, PipelineError> {
+    device.push_error_scope(ErrorFilter::Validation);
+    let created = create();
+    if let Some(error) = device.pop_error_scope().await {
+        return Err(PipelineError {
+            shader: shader.to_owned(),
+            message: error.to_string(),
+        });
+    }
+    Ok(created)
+}
+
+/// Finds the fragment shaders in a WGSL shader that can be drawn with, in declaration order.
+///
+/// Entry points that write to more than one color target, like the G-buffer shader,
+/// need a different render pass, so they're skipped.
+///
+/// # Errors
+/// Returns an error if the shader couldn't be parsed
+pub fn fragment_entry_points(source: &str) -> Result<Vec<String>, wgsl::ParseError> {
+    let module = wgsl::parse_str(source)?;
+    Ok(module
+        .entry_points
+        .into_iter()
+        .filter(|entry_point| entry_point.stage == naga::ShaderStage::Fragment)
+        .filter(|entry_point| {
+            entry_point.function.result.as_ref().is_some_and(|result| {
+                matches!(result.binding, Some(Binding::Location { location: 0, .. }))
+            })
+        })
+        .map(|entry_point| entry_point.name)
+        .collect())
+}
+
+/// Infers the stage of a GLSL shader from its file extension, the way glslang does
+#[cfg(feature = "glsl")]
+#[must_use]
+pub fn glsl_stage(path: &str) -> Option<ShaderStage> {
+    match Path::new(path).extension()?.to_str()? {
+        "vert" => Some(ShaderStage::Vertex),
+        "frag" => Some(ShaderStage::Fragment),
+        "comp" => Some(ShaderStage::Compute),
+        _ => None,
+    }
+}
+
+/// Compiles a GLSL shader, the stage is inferred from the extension of the path.
+///
+/// wgpu would panic on a shader that doesn't compile, so it's translated by naga first,
+/// which reports every error with its line and column instead.
+///
+/// # Errors
+/// Returns an error if the stage is unknown, or the shader couldn't be parsed or validated
+#[cfg(feature = "glsl")]
+pub fn create_glsl_module(
+    device: &Device,
+    path: &str,
+    source: &str,
+) -> Result<ShaderModule, ShaderError> {
+    let stage = glsl_stage(path).ok_or_else(|| ShaderError::UnknownStage(path.to_owned()))?;
+
+    let module = Frontend::default()
+        .parse(&Options::from(stage), source)
+        .map_err(|errors| ShaderError::Parse {
+            path: path.to_owned(),
+            message: errors
+                .iter()
+                .map(|error| {
+                    let location = error.meta.location(source);
+                    format!(
+                        "{path}:{}:{}: {error}",
+                        location.line_number, location.line_position
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })?;
+
+    create_validated_module(device, path, module)
+}
+
+/// Loads a precompiled SPIR-V shader, like one compiled from shader.wgsl with naga-cli:
+/// `naga src/shader.wgsl src/shader.spv`. The entry points keep their names.
+///
+/// wgpu can load SPIR-V directly with [`wgpu::util::make_spirv`], but a shader that needs
+/// features the device doesn't have would be a fatal error. It's translated by naga first,
+/// so those shaders are reported as errors instead.
+///
+/// # Errors
+/// Returns an error if the SPIR-V couldn't be parsed, or the device can't run it
+#[cfg(feature = "spirv")]
+pub fn create_spirv_module(
+    device: &Device,
+    label: &str,
+    spirv: &[u8],
+) -> Result<ShaderModule, ShaderError> {
+    // SPIR-V for Vulkan has its y-axis pointing down, like naga-cli and glslang write it,
+    // so it's flipped back to wgpu's coordinates
+    let options = wgpu::naga::front::spv::Options {
+        adjust_coordinate_space: true,
+        strict_capabilities: true,
+        block_ctx_dump_prefix: None,
+    };
+    let module = wgpu::naga::front::spv::parse_u8_slice(spirv, &options).map_err(|error| {
+        ShaderError::Parse {
+            path: label.to_owned(),
+            message: error.to_string(),
+        }
+    })?;
+
+    create_validated_module(device, label, module)
+}
+
+/// Reads a SPIR-V file from disk and loads it like [`create_spirv_module`]
+///
+/// # Errors
+/// Returns an error if the file couldn't be read, or the shader couldn't be loaded
+#[cfg(all(feature = "spirv", not(target_arch = "wasm32")))]
+pub fn load_spirv_module(device: &Device, path: &str) -> Result<ShaderModule, ShaderError> {
+    let spirv = std::fs::read(path).map_err(|source| ShaderError::Read {
+        path: path.to_owned(),
+        source,
+    })?;
+    create_spirv_module(device, path, &spirv)
+}
+
+/// The shader capabilities the features of the device allow, like wgpu checks them
+#[cfg(any(feature = "glsl", feature = "spirv"))]
+fn device_capabilities(device: &Device) -> Capabilities {
+    let features = device.features();
+    let mut capabilities = Capabilities::empty();
+    capabilities.set(
+        Capabilities::PUSH_CONSTANT,
+        features.contains(Features::PUSH_CONSTANTS),
+    );
+    capabilities.set(
+        Capabilities::FLOAT64,
+        features.contains(Features::SHADER_F64),
+    );
+    capabilities.set(
+        Capabilities::PRIMITIVE_INDEX,
+        features.contains(Features::SHADER_PRIMITIVE_INDEX),
+    );
+    capabilities.set(
+        Capabilities::MULTIVIEW,
+        features.contains(Features::MULTIVIEW),
+    );
+    capabilities.set(
+        Capabilities::EARLY_DEPTH_TEST,
+        features.contains(Features::SHADER_EARLY_DEPTH_TEST),
+    );
+    capabilities.set(
+        Capabilities::DUAL_SOURCE_BLENDING,
+        features.contains(Features::DUAL_SOURCE_BLENDING),
+    );
+    capabilities
+}
+
+/// Validates a module translated by naga against the device, and creates the shader module.
+/// wgpu validates it as well, but it would panic on errors.
+#[cfg(any(feature = "glsl", feature = "spirv"))]
+fn create_validated_module(
+    device: &Device,
+    label: &str,
+    module: Module,
+) -> Result<ShaderModule, ShaderError> {
+    Validator::new(ValidationFlags::all(), device_capabilities(device))
+        .validate(&module)
+        .map_err(|error| ShaderError::Validation {
+            path: label.to_owned(),
+            message: error_chain(&error),
+        })?;
+
+    Ok(device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(label),
+        source: ShaderSource::Naga(Cow::Owned(module)),
+    }))
+}
+
+/// Joins an error with the errors that caused it. Validation errors are nested,
+/// the innermost one tells what's actually wrong, like a missing capability.
+#[cfg(any(feature = "glsl", feature = "spirv"))]
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+    message
+}
@@ -0,0 +1,72 @@
+// Vertex shader, a GLSL port of `vs_main` in shader.wgsl.
+// GLSL shaders are compiled with naga when the `glsl` feature is enabled.
+#version 450
+
+// The camera data, as stored in `CameraUniform`
+layout(set = 1, binding = 0) uniform CameraUniform {
+    // The position of the camera, to calculate the direction light is reflected in
+    vec4 view_position;
+
+    // Transforms world coordinates to clip coordinates
+    mat4 view_proj;
+} camera;
+
+// The model matrix, as stored in `TransformUniform`
+layout(set = 1, binding = 1) uniform TransformUniform {
+    // Moves, turns, and scales all instances together, after they're placed
+    mat4 model;
+} model_transform;
+
+// Small per-draw parameters, as stored in `DrawConstants`.
+// `draw::glsl_shader_source` turns this into a push constant block if the GPU supports them.
+layout(set = 2, binding = 3) uniform DrawConstants {
+    // Multiplied with the color of every fragment
+    vec3 tint;
+
+    // Scales the models around their origin
+    float scale;
+} draw;
+
+// The data of a single vertex, the locations match the `shader_location` in `Vertex::desc`
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 tex_coords;
+layout(location = 2) in vec3 normal;
+layout(location = 3) in vec3 tangent;
+layout(location = 4) in vec3 bitangent;
+
+// The model matrix of an instance, split in 4 columns as an attribute can be at most a vec4
+layout(location = 5) in vec4 model_matrix_0;
+layout(location = 6) in vec4 model_matrix_1;
+layout(location = 7) in vec4 model_matrix_2;
+layout(location = 8) in vec4 model_matrix_3;
+
+// 0 for the diffuse texture, or 1 more than the layer of the instance textures
+layout(location = 9) in uint texture_index;
+
+// The outputs have the same locations as the fields of `VertexOutput` in shader.wgsl
+layout(location = 0) out vec2 v_vert_pos;
+layout(location = 1) out vec2 v_tex_coords;
+layout(location = 2) out vec3 v_world_normal;
+layout(location = 3) out vec3 v_world_position;
+layout(location = 4) out vec3 v_world_tangent;
+layout(location = 5) out vec3 v_world_bitangent;
+layout(location = 6) flat out uint v_texture_index;
+
+void main() {
+    // Reassemble the model matrix of the instance, and place it with the model matrix of the model
+    mat4 model_matrix = model_transform.model
+        * mat4(model_matrix_0, model_matrix_1, model_matrix_2, model_matrix_3);
+
+    // Place the vertex in the world with the model matrix, then transform it to clip coordinates
+    vec4 world_position = model_matrix * vec4(position * draw.scale, 1.0);
+    gl_Position = camera.view_proj * world_position;
+    v_world_position = world_position.xyz;
+
+    // `0.0` as w means it's a direction, so it's rotated but not moved
+    v_world_normal = (model_matrix * vec4(normal, 0.0)).xyz;
+    v_world_tangent = (model_matrix * vec4(tangent, 0.0)).xyz;
+    v_world_bitangent = (model_matrix * vec4(bitangent, 0.0)).xyz;
+    v_vert_pos = position.xy;
+    v_tex_coords = tex_coords;
+    v_texture_index = texture_index;
+}
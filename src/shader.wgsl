@@ -1,5 +1,112 @@
 // Vertex shader
 
+// The camera data, as stored in `CameraUniform`
+struct CameraUniform{
+    // The position of the camera, to calculate the direction light is reflected in
+    view_position: vec4<f32>,
+
+    // Transforms world coordinates to clip coordinates
+    view_proj: mat4x4<f32>,
+};
+
+// The camera is bound in group 1, the texture is bound in group 0
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// The model matrix, as stored in `TransformUniform`
+struct TransformUniform{
+    // Moves, turns, and scales all instances together, after they're placed
+    model: mat4x4<f32>,
+};
+
+// The model matrix is bound next to the camera, the view-projection matrix is applied after it
+@group(1) @binding(1)
+var<uniform> model_transform: TransformUniform;
+
+// The number of seconds since the application started, as stored in `TimeUniform`.
+// It's bound in group 2, so it's available to both the vertex and fragment shader.
+@group(2) @binding(0)
+var<uniform> u_time: f32;
+
+// Small per-draw parameters, as stored in `DrawConstants`
+struct DrawConstants{
+    // Multiplied with the color of every fragment
+    tint: vec3<f32>,
+
+    // Scales the models around their origin
+    scale: f32,
+};
+
+// The draw constants are in group 2 as well if the GPU doesn't support push constants.
+// Otherwise `draw::shader_source` replaces this with a `var<push_constant>` declaration.
+@group(2) @binding(3)
+var<uniform> draw: DrawConstants;
+
+// A point light, as stored in `LightUniform`
+struct Light{
+    position: vec3<f32>,
+    ambient_strength: f32,
+    color: vec3<f32>,
+    specular_strength: f32,
+};
+
+// The number of lights in use, as stored in `LightCount`
+struct LightCount{
+    count: u32,
+};
+
+// The most lights there can be, it has to match `light::MAX_LIGHTS`
+const MAX_LIGHTS: u32 = 16u;
+
+// The lights are bound in group 3. WebGL2 has no storage buffers, so they're a uniform array.
+// Otherwise `light::shader_source` replaces this with a `var<storage, read>` declaration.
+@group(3) @binding(0)
+var<uniform> lights: array<Light, MAX_LIGHTS>;
+
+@group(3) @binding(1)
+var<uniform> light_count: LightCount;
+
+// The fog, as stored in `FogUniform`
+struct FogUniform{
+    // The linear color distant geometry fades into, it follows the background color
+    color: vec3<f32>,
+
+    // How fast exponential fog thickens, 0 turns the fog off
+    density: f32,
+
+    // Where linear fog starts and ends
+    start: f32,
+    end: f32,
+
+    // 0 for exponential fog, 1 for linear fog
+    mode: u32,
+};
+
+// The fog is bound next to the lights, it's part of the scene as well
+@group(3) @binding(2)
+var<uniform> fog: FogUniform;
+
+// The data of a single vertex, as stored in the vertex buffer
+struct VertexInput{
+    // `@location(n)` matches the `shader_location` of the attribute in `Vertex::desc`
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec3<f32>,
+    @location(4) bitangent: vec3<f32>,
+};
+
+// The model matrix of an instance, split in 4 columns as an attribute can be at most a vec4
+struct InstanceInput{
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+
+    // 0 for the diffuse texture, or 1 more than the layer of the instance textures
+    @location(9) texture_index: u32,
+};
+
 // Will store the output of the vertex shader
 struct VertexOutput{
     // `@builtin(position)` tells WGPU this contains the vertex's clip coordinates (gl_Position)
@@ -7,39 +114,255 @@ struct VertexOutput{
     // and receive by fragment shader.
     // You need an extra variable, if you want to keep the original values.
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) vert_pos: vec2<f32>
+    @location(0) vert_pos: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+
+    // The normal and position in world coordinates, to calculate the lighting with
+    @location(2) world_normal: vec3<f32>,
+    @location(3) world_position: vec3<f32>,
+
+    // The directions of the texture coordinates in world coordinates, for the normal map
+    @location(4) world_tangent: vec3<f32>,
+    @location(5) world_bitangent: vec3<f32>,
+
+    // Integers can't be interpolated, every fragment gets the value of the first vertex
+    @location(6) @interpolate(flat) texture_index: u32,
 };
 
 // `@vertex` marks the function as a valid entry point for a vertex shader
 @vertex
 fn vs_main(
-    // @builtin(vertex_index) marks a parameter as vertex_index parameter
-    @builtin(vertex_index) in_vertex_index: u32
+    // The vertex read from the vertex buffer
+    model: VertexInput,
+
+    // The instance read from the instance buffer
+    instance: InstanceInput,
 ) -> VertexOutput{
+    // Reassemble the model matrix of the instance, and place the instance with the model matrix
+    // of the whole model. The model matrix is on the left, so it's applied last.
+    let model_matrix = model_transform.model * mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+
     // Create the Vertex Output, var means that a variable can be modified
     var out: VertexOutput;
 
-    // f32(..) and i32(..) are casts
-    // Variables defined with let can't be modified
-    let x = f32(1 - i32(in_vertex_index)) * 0.5;
-    let y = f32(i32(in_vertex_index & 1u) * 2 - 1) * 0.5;
-    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
-    out.vert_pos = vec2<f32>(x, y);
+    // Place the vertex in the world with the model matrix, then transform it to clip coordinates.
+    // `1.0` as w means it's a point.
+    let world_position = model_matrix * vec4<f32>(model.position * draw.scale, 1.0);
+    out.clip_position = camera.view_proj * world_position;
+    out.world_position = world_position.xyz;
+
+    // `0.0` as w means it's a direction, so it's rotated but not moved.
+    // The model is only scaled the same in every direction, otherwise the normal would need
+    // its own matrix. The fragment shader normalizes it again.
+    out.world_normal = (model_matrix * vec4<f32>(model.normal, 0.0)).xyz;
+    out.world_tangent = (model_matrix * vec4<f32>(model.tangent, 0.0)).xyz;
+    out.world_bitangent = (model_matrix * vec4<f32>(model.bitangent, 0.0)).xyz;
+    out.vert_pos = model.position.xy;
+    out.tex_coords = model.tex_coords;
+    out.texture_index = instance.texture_index;
     return out;
 }
 
 // Fragment shader
 
+// The texture and the sampler to read it with, bound in group 0
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+// The normal map of the material, also in group 0
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(3)
+var s_normal: sampler;
+
+// The texture that's blended with the diffuse texture, also in group 0
+@group(0) @binding(4)
+var t_blend: texture_2d<f32>;
+@group(0) @binding(5)
+var s_blend: sampler;
+
+// The textures instances can be drawn with instead of the diffuse texture, also in group 0
+@group(0) @binding(6)
+var t_instances: texture_2d_array<f32>;
+@group(0) @binding(7)
+var s_instances: sampler;
+
+// How much of the blend texture is mixed in, as stored in `MixUniform`
+struct MixUniform{
+    // 0.0 only shows the diffuse texture, 1.0 only the blend texture
+    factor: f32,
+};
+
+// The mix factor is bound in group 2, next to the color handling
+@group(2) @binding(2)
+var<uniform> mix_info: MixUniform;
+
+// Reads the texture of the instance and the blend texture, and mixes them by the mix factor.
+// The textures are sRGB, so they're mixed in linear space.
+fn sample_textures(tex_coords: vec2<f32>, texture_index: u32) -> vec4<f32>{
+    // Instances can ask for more layers than the array has, they use the last one then.
+    // WGSL clamps the index to the layers of the array, `textureNumLayers` isn't needed for it.
+    // It wouldn't work on OpenGL anyway, naga translates it to an invalid `textureSize` call.
+    // Both textures are read, as `textureSample` can't be called in a branch that depends
+    // on the instance.
+    let diffuse = textureSample(t_diffuse, s_diffuse, tex_coords);
+    let layer = max(texture_index, 1u) - 1u;
+    let instance = textureSample(t_instances, s_instances, tex_coords, layer);
+    return mix(
+        select(diffuse, instance, texture_index > 0u),
+        textureSample(t_blend, s_blend, tex_coords),
+        mix_info.factor,
+    );
+}
+
+// Reads the normal map, and rotates the normal from texture space to world space
+fn surface_normal(in: VertexOutput) -> vec3<f32>{
+    // Interpolated normals aren't unit length anymore, so normalize them again
+    let normal = normalize(in.world_normal);
+
+    // Triangles without an area in texture space don't have a tangent,
+    // the normal map can't be rotated then, so the normal of the geometry is used
+    if length(in.world_tangent) < 0.001 || length(in.world_bitangent) < 0.001 {
+        return normal;
+    }
+
+    // The normal map stores directions from -1.0 to 1.0 as colors from 0.0 to 1.0
+    let tangent_normal = textureSample(t_normal, s_normal, in.tex_coords).xyz * 2.0 - 1.0;
+
+    // The columns of the TBN matrix are where the x-, y-, and z-axes of texture space point to
+    let tbn = mat3x3<f32>(
+        normalize(in.world_tangent),
+        normalize(in.world_bitangent),
+        normal,
+    );
+    return normalize(tbn * tangent_normal);
+}
+
+// How colors have to be written to the surface, as stored in `ColorUniform`
+struct ColorUniform{
+    // 1 if the surface isn't sRGB, so the output has to be gamma corrected here
+    gamma_correct: u32,
+};
+
+// The color handling is bound in group 2, next to the time
+@group(2) @binding(1)
+var<uniform> color_info: ColorUniform;
+
+// Converts a linear color to sRGB, like an sRGB surface would do when it's written to
+fn to_srgb(linear: vec3<f32>) -> vec3<f32>{
+    // sRGB is linear close to black, and follows a power curve everywhere else
+    let cutoff = linear <= vec3<f32>(0.0031308);
+    let lower = linear * 12.92;
+    let higher = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(higher, lower, cutoff);
+}
+
+// Every fragment shader returns its color through this, so it looks the same on every surface.
+// The color is tinted with the draw constants first.
+fn output_color(color: vec4<f32>) -> vec4<f32>{
+    let tinted = vec4<f32>(color.rgb * draw.tint, color.a);
+    if color_info.gamma_correct != 0u {
+        return vec4<f32>(to_srgb(tinted.rgb), tinted.a);
+    }
+    return tinted;
+}
+
+// Fades the color into the fog by its distance to the camera.
+// The fog has the color of the background, so distant geometry disappears into it.
+fn apply_fog(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32>{
+    let distance = length(camera.view_position.xyz - world_position);
+
+    // How much of the color is still visible through the fog
+    var visibility: f32;
+    if fog.mode == 1u {
+        visibility = clamp((fog.end - distance) / (fog.end - fog.start), 0.0, 1.0);
+    } else {
+        visibility = exp(-fog.density * distance);
+    }
+    // Mixing in this order keeps the color exactly as it was without fog
+    return mix(color, fog.color, 1.0 - visibility);
+}
+
 // `@location(0)`: store the returned value as first color target
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
-    // Make the 
-    return vec4<f32>(0.3, 0.2, 0.1, 1.0);
+    // Read the color of the textures at the interpolated texture coordinates.
+    // The coordinates are already flipped on the CPU, as the y-axis points down in textures.
+    let object_color = sample_textures(in.tex_coords, in.texture_index);
+
+    // The normal map adds detail to the direction the surface faces
+    let normal = surface_normal(in);
+    let view_dir = normalize(camera.view_position.xyz - in.world_position);
+
+    // The ambient light slowly pulses between 50% and 150% over time,
+    // so the sides facing away from the lights breathe
+    let ambient_pulse = 1.0 + 0.5 * sin(u_time);
+
+    // Every light adds to the color, the ones past the count aren't in use
+    var lighting = vec3<f32>(0.0);
+    for (var i = 0u; i < min(light_count.count, MAX_LIGHTS); i += 1u) {
+        let light = lights[i];
+
+        // Light that bounced around the scene, so even the dark side isn't completely black
+        let ambient_color = light.color * light.ambient_strength * ambient_pulse;
+
+        // Surfaces are brighter the more directly they face the light
+        let light_dir = normalize(light.position - in.world_position);
+        let diffuse_strength = max(dot(normal, light_dir), 0.0);
+        let diffuse_color = light.color * diffuse_strength;
+
+        // Blinn-Phong: the reflection is brightest where the normal is halfway between the
+        // direction of the light and the direction of the camera
+        let half_dir = normalize(view_dir + light_dir);
+        let specular_strength =
+            pow(max(dot(normal, half_dir), 0.0), 32.0) * light.specular_strength;
+        let specular_color = light.color * specular_strength;
+
+        lighting += ambient_color + diffuse_color + specular_color;
+    }
+
+    let result = apply_fog(lighting * object_color.rgb, in.world_position);
+    return output_color(vec4<f32>(result, object_color.a));
+}
+
+// The outputs of the G-buffer pipeline, every location is written to its own color target
+struct GBufferOutput{
+    // The color of the surface without lighting, in linear space
+    @location(0) albedo: vec4<f32>,
+
+    // The normal in world space, mapped from -1.0..1.0 to 0.0..1.0 to fit in the texture
+    @location(1) normal: vec4<f32>,
+};
+
+// Writes what's needed to light the surface later, instead of lighting it right away
+@fragment
+fn fs_gbuffer(in: VertexOutput) -> GBufferOutput{
+    var out: GBufferOutput;
+    let object_color = sample_textures(in.tex_coords, in.texture_index);
+    out.albedo = vec4<f32>(object_color.rgb * draw.tint, object_color.a);
+    out.normal = vec4<f32>(surface_normal(in) * 0.5 + 0.5, 1.0);
+    return out;
 }
 
 // `@location(0)`: store the returned value as first color target
 @fragment
 fn fs_main2(in: VertexOutput) -> @location(0) vec4<f32>{
     // Make the 
-    return vec4<f32>(in.vert_pos[0], in.vert_pos[1], 1.0 - (in.vert_pos[0] + in.vert_pos[1]) / 2.0, 1.0);
+    return output_color(vec4<f32>(in.vert_pos[0], in.vert_pos[1], 1.0 - (in.vert_pos[0] + in.vert_pos[1]) / 2.0, 1.0));
+}
+
+// `@location(0)`: store the returned value as first color target
+@fragment
+fn fs_pulse(in: VertexOutput) -> @location(0) vec4<f32>{
+    // Make the brightness of the texture pulse between 50% and 100% every ~2 seconds
+    let brightness = 0.75 + 0.25 * sin(u_time * 3.0);
+    let color = sample_textures(in.tex_coords, in.texture_index);
+    return output_color(vec4<f32>(color.rgb * brightness, color.a));
 }
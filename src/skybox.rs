@@ -0,0 +1,196 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use image::{DynamicImage, Rgba, RgbaImage};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType, BufferUsages,
+    Device, Queue, RenderPass, SamplerBindingType, ShaderStages, TextureSampleType,
+    TextureViewDimension,
+};
+
+use crate::{camera::Camera, texture::Texture};
+
+/// The sRGB colors of the generated sky straight up, at the horizon, and straight down
+const ZENITH_COLOR: [f32; 3] = [40.0, 90.0, 200.0];
+const HORIZON_COLOR: [f32; 3] = [190.0, 215.0, 240.0];
+const GROUND_COLOR: [f32; 3] = [70.0, 60.0, 50.0];
+
+/// The direction through a point of a cube map face, `u` and `v` go from -1 at the top left
+/// to 1 at the bottom right. The faces are in the order +x, -x, +y, -y, +z, -z.
+fn face_direction(face: usize, u: f32, v: f32) -> Vector3<f32> {
+    match face {
+        0 => Vector3::new(1.0, -v, -u),
+        1 => Vector3::new(-1.0, -v, u),
+        2 => Vector3::new(u, 1.0, v),
+        3 => Vector3::new(u, -1.0, -v),
+        4 => Vector3::new(u, -v, 1.0),
+        _ => Vector3::new(-u, -v, -1.0),
+    }
+}
+
+/// Generates the faces of a sky that fades from blue above to light at the horizon,
+/// with brown ground below it, `size` by `size` pixels each
+#[must_use]
+pub fn gradient_faces(size: u32) -> [DynamicImage; 6] {
+    // The center of a pixel, from -1 to 1
+    #[allow(clippy::cast_precision_loss)]
+    let coordinate = |pixel: u32| ((pixel as f32 + 0.5) / size as f32).mul_add(2.0, -1.0);
+    std::array::from_fn(|face| {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(size, size, |x, y| {
+            let height = face_direction(face, coordinate(x), coordinate(y))
+                .normalize()
+                .y;
+
+            // The sky changes quickly near the horizon, the ground slowly
+            let (color, amount) = if height >= 0.0 {
+                (ZENITH_COLOR, height.sqrt())
+            } else {
+                (GROUND_COLOR, (-height).powf(0.25))
+            };
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let channel = |index: usize| {
+                (color[index] - HORIZON_COLOR[index]).mul_add(amount, HORIZON_COLOR[index]) as u8
+            };
+            Rgba([channel(0), channel(1), channel(2), u8::MAX])
+        }))
+    })
+}
+
+/// What the skybox shader needs, as it's stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct SkyboxUniform {
+    /// Turns a point on the screen back into a direction from the camera
+    inverse_view_proj: [[f32; 4]; 4],
+
+    /// Whether the output has to be gamma corrected, like the `ColorUniform`
+    gamma_correct: u32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [u32; 3],
+}
+
+/// A cube map around the scene that's infinitely far away, drawn behind everything else.
+/// It has its own bind group with the cube map and the uniform, so it doesn't need the ones
+/// of the models.
+#[derive(Debug)]
+pub struct Skybox {
+    uniform: SkyboxUniform,
+    uniform_buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+
+    /// The bind group keeps the cube map alive, so it doesn't have to be stored separately
+    bind_group: BindGroup,
+}
+
+impl Skybox {
+    /// Uploads the faces as a cube map, in the order +x, -x, +y, -y, +z, -z.
+    /// `gamma_correct` has to be set if the target of the skybox doesn't store linear colors.
+    #[must_use]
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        faces: &[DynamicImage; 6],
+        gamma_correct: bool,
+    ) -> Self {
+        let cube_map = Texture::from_cubemap(device, queue, faces, "Skybox Texture");
+        let uniform = SkyboxUniform {
+            inverse_view_proj: Matrix4::identity().into(),
+            gamma_correct: gamma_correct.into(),
+            _padding: [0; 3],
+        };
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Skybox Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // The cube map, its sampler, and the uniform
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Skybox Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Skybox Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&cube_map.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&cube_map.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            uniform,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// Describes the bind group of the skybox, for its pipeline layout
+    #[must_use]
+    pub const fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Uploads the rotation and projection of the camera. The position is left out,
+    /// as the sky is so far away that moving doesn't change how it looks.
+    pub fn write_camera(&mut self, queue: &Queue, camera: &Camera) {
+        self.uniform.inverse_view_proj = camera
+            .build_rotation_projection_matrix()
+            .invert()
+            .unwrap_or_else(Matrix4::identity)
+            .into();
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniform]),
+        );
+    }
+
+    /// Draws the sky with the pipeline that's set, it replaces the bind group in group 0
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+
+        // 3 vertices make the triangle that covers the screen
+        render_pass.draw(0..3, 0..1);
+    }
+}
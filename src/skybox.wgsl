@@ -0,0 +1,53 @@
+// Draws the sky behind everything. A single triangle covers the screen at the far plane,
+// and every pixel looks up the cube map in the direction the camera sees it in.
+
+// What the shader needs, as stored in `SkyboxUniform`
+struct SkyboxUniform{
+    // The inverse of the view-projection matrix without the position of the camera
+    inverse_view_proj: mat4x4<f32>,
+    gamma_correct: u32,
+};
+
+@group(0) @binding(0)
+var t_sky: texture_cube<f32>;
+@group(0) @binding(1)
+var s_sky: sampler;
+@group(0) @binding(2)
+var<uniform> sky: SkyboxUniform;
+
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+
+    // The position on the screen, from (-1, -1) in the bottom left to (1, 1) in the top right
+    @location(0) ndc: vec2<f32>,
+};
+
+// The vertices are calculated from their index like in post_process.wgsl. The depth is 1,
+// the furthest it can be, so the sky only shows where nothing else was drawn.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput{
+    // 0 -> (0, 0), 1 -> (2, 0), 2 -> (0, 2)
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
+    // Undo the projection of the pixel on the near and the far plane, the direction between
+    // them is where the camera looks. With an orthographic projection, that's the same
+    // direction for every pixel.
+    let near = sky.inverse_view_proj * vec4<f32>(in.ndc, 0.0, 1.0);
+    let far = sky.inverse_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = far.xyz / far.w - near.xyz / near.w;
+
+    let color = textureSample(t_sky, s_sky, direction).rgb;
+    if sky.gamma_correct != 0u {
+        return vec4<f32>(to_srgb(color), 1.0);
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,10 @@
+// Converts a linear color to sRGB, like an sRGB surface would do when it's written to.
+// WGSL can't include other files, so this is appended to the shaders that call it.
+// shader.wgsl has its own, so naga can compile it to SPIR-V on its own.
+fn to_srgb(linear: vec3<f32>) -> vec3<f32>{
+    // sRGB is linear close to black, and follows a power curve everywhere else
+    let cutoff = linear <= vec3<f32>(0.0031308);
+    let lower = linear * 12.92;
+    let higher = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(higher, lower, cutoff);
+}
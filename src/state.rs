@@ -1,429 +1,3453 @@
+#[cfg(target_arch = "wasm32")]
+use std::future::Future;
+use std::{borrow::Cow, ffi::OsStr, path::Path, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{path::PathBuf, time::SystemTime};
+
+use bytemuck::Zeroable;
+use cgmath::{EuclideanSpace, MetricSpace, Point3, Quaternion, Rad, Rotation3, Transform as _};
+use web_time::Instant;
 use wgpu::{
-    Adapter, Backends, BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder,
-    CommandEncoderDescriptor, Device, DeviceDescriptor, Face, Features, FragmentState, FrontFace,
-    Instance, InstanceDescriptor, Limits, LoadOp, MultisampleState, Operations,
-    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology, Queue,
-    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    RequestAdapterOptions, ShaderModuleDescriptor, ShaderSource, StoreOp, Surface,
-    SurfaceConfiguration, SurfaceError, TextureFormat, TextureUsages, TextureView,
-    TextureViewDescriptor, VertexState,
+    util::{BufferInitDescriptor, DeviceExt},
+    AdapterInfo, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BlendState, Buffer,
+    BufferBindingType, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder,
+    CommandEncoderDescriptor, CompareFunction, DepthBiasState, DepthStencilState, Device, Extent3d,
+    Face, Features, FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState, Operations,
+    PipelineLayout, PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
+    PushConstantRange, Queue, RenderPass, RenderPassColorAttachment,
+    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    StencilFaceState, StencilOperation, StencilState, StoreOp, SurfaceConfiguration,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor, VertexBufferLayout, VertexState,
 };
 use winit::{
-    dpi::PhysicalSize,
-    event::{ElementState, WindowEvent},
-    window::Window,
+    dpi::PhysicalPosition,
+    event::{DeviceEvent, ElementState, MouseButton, WindowEvent},
+    window::{CursorGrabMode, Window},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::compute::{self, ComputeError, DoubleKernel};
+#[cfg(feature = "gui")]
+use crate::gui::EguiRenderer;
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+use crate::hot_reload::FileWatcher;
+#[cfg(feature = "glsl")]
+use crate::shader::ShaderError;
+#[cfg(feature = "text")]
+use crate::text::TextRenderer;
+use crate::{
+    app::{App, Context},
+    bloom::BloomSettings,
+    camera::{self, Camera, CameraController, CameraUniform},
+    capture::{self, CaptureError},
+    color::{self, ColorUniform},
+    draw::{self, DrawConstants},
+    entry_points,
+    fog::{FogSettings, FogUniform},
+    frustum::Frustum,
+    gbuffer::GBuffer,
+    grid::{Grid, LineVertex},
+    hdr::{self, Hdr, HdrTarget},
+    hdr_bloom::HdrBloomSettings,
+    input::{Action, InputMap},
+    instance::{self, InstanceRaw},
+    light::{self, LightCount, LightUniform, MAX_LIGHTS},
+    model::{
+        self, DrawLight, DrawModel, Model, Vertex, INDICES, QUAD_INDICES, QUAD_VERTICES,
+        TRANSLUCENT_TRIANGLE, VERTICES,
+    },
+    post_process::{Effect, PostProcess, PostTarget},
+    resources::{self, LoadError},
+    shader::{self, PipelineError, ShaderDesc},
+    skybox::{self, Skybox},
+    texture::{self, MixUniform, SharedTextures, Texture},
+    time::TimeUniform,
+    transform::{Transform, TransformUniform},
+    viewport::Viewport,
+};
+
+/// Where the shader is read from when hot reloading is enabled
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader.wgsl");
+
+/// Where the precompiled SPIR-V version of shader.wgsl is loaded from, if it exists
+#[cfg(all(feature = "spirv", not(target_arch = "wasm32")))]
+const SPIRV_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader.spv");
+
+/// The model the instances are drawn with, the shape is drawn if it can't be loaded
+const MODEL_FILE: &str = "cube.obj";
+
+/// How fast the instances spin around, in radians per second
+const INSTANCE_ROTATION_SPEED: f32 = 0.5;
+
+/// How fast the lights orbit around the origin, in radians per second
+const LIGHT_ORBIT_SPEED: f32 = 1.0;
+
+/// Lights added at the cursor are placed at this height, the height of the first light.
+/// If the cursor points above the horizon, they're placed this far from the camera instead.
+const LIGHT_SPAWN_HEIGHT: f32 = 2.0;
+const LIGHT_SPAWN_DISTANCE: f32 = 5.0;
+
+/// The number of samples per pixel for anti-aliasing, if the GPU supports it
+const MSAA_SAMPLE_COUNT: u32 = 4;
+
+/// The number of cells along each side of the grid, and their width
+const GRID_SIZE: u16 = 20;
+const GRID_SPACING: f32 = 1.0;
+
+/// The width and height of the faces of the generated sky, in pixels.
+/// It's a smooth gradient, so it doesn't need many.
+const SKYBOX_FACE_SIZE: u32 = 64;
+
+/// The value the models write to the stencil buffer, the outline is drawn everywhere else
+const STENCIL_REFERENCE: u32 = 1;
+
+/// How long moving the cursor pauses the background animation
+const BACKGROUND_CURSOR_PAUSE: Duration = Duration::from_secs(1);
+
+/// How often the frame statistics in the window title are updated
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The layers of the texture array the instances can pick instead of the texture of the model
+const INSTANCE_TEXTURES: [&[u8]; 2] = [
+    include_bytes!("happy-tree.png"),
+    include_bytes!("../res/cube-diffuse.png"),
+];
+
+/// How much a key press multiplies or divides the exposure by
+const EXPOSURE_STEP: f32 = 1.25;
+
+/// How much the arrow keys change the mix factor of the textures per press
+const MIX_FACTOR_STEP: f32 = 0.1;
+
+/// How much , and . change the density of the exponential fog per press
+const FOG_DENSITY_STEP: f32 = 0.02;
+
+/// The number of values J doubles with the compute shader, not a multiple of the workgroup size
+/// so the last workgroup is only partly used
+#[cfg(not(target_arch = "wasm32"))]
+const COMPUTE_VALUE_COUNT: u32 = 1000;
+
+/// Where the frame rate is drawn, a little away from the top-left corner, and its size in pixels
+#[cfg(feature = "text")]
+const TEXT_POSITION: (f32, f32) = (8.0, 8.0);
+#[cfg(feature = "text")]
+const TEXT_SCALE: f32 = 24.0;
+
+/// The frame rate is white, so it can be read on most backgrounds
+#[cfg(feature = "text")]
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// The textures a frame is rendered to besides the color target, they have the size of the target
+struct RenderTargets {
+    /// Stores the depth of every pixel, so only the closest geometry gets drawn
+    depth_texture: Texture,
+
+    /// The multisampled texture frames are rendered to before they're resolved to the target,
+    /// `None` if multisampling isn't used
+    msaa_view: Option<TextureView>,
+}
+
+/// The shader a named pipeline runs, it's kept to recreate the pipeline
+enum PipelineShader {
+    /// The fragment entry point in shader.wgsl, the vertex entry point is always `vs_main`.
+    /// The module is replaced when the shader is reloaded.
+    Wgsl(String),
+
+    /// Separately compiled GLSL vertex and fragment shaders
+    #[cfg(feature = "glsl")]
+    Glsl {
+        vertex: ShaderModule,
+        fragment: ShaderModule,
+    },
+
+    /// shader.wgsl precompiled to SPIR-V, it runs `vs_main` and `fs_main`
+    #[cfg(feature = "spirv")]
+    SpirV(ShaderModule),
+}
+
+/// A render pipeline that was registered under a name
+struct NamedPipeline {
+    name: String,
+    shader: PipelineShader,
+    pipeline: RenderPipeline,
+}
+
+/// The demo app: instanced shapes with a camera, switchable pipelines, and background colors
+#[allow(clippy::struct_excessive_bools)]
 pub struct State {
-    surface: Surface,
-    device: Device,
-    queue: Queue,
-    config: SurfaceConfiguration,
-    size: PhysicalSize<u32>,
+    /// The name, backend, and driver of the GPU, to include in bug reports
+    adapter_info: AdapterInfo,
+
+    /// The background color in linear space, it's gamma corrected when the surface isn't sRGB
+    background_color: Color,
+
+    /// The background color the demo started with, 0 resets the background to it
+    initial_background_color: Color,
+
+    /// Whether the background color cycles through the hues over time,
+    /// and the hue of the background in degrees
+    animate_background: bool,
+    background_hue: f64,
+
+    /// The cursor's color is shown instead of the animation until this time
+    background_paused_until: Option<Instant>,
+
+    /// The shader every pipeline is created from
+    shader: ShaderModule,
+
+    /// Reports changes to the shader file, so it can be reloaded
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    shader_watcher: Option<FileWatcher>,
+
+    /// The pipelines that can be rendered with, and the index of the one in use
+    pipelines: Vec<NamedPipeline>,
+    active_pipeline: usize,
+
+    /// Whether the first pipeline is drawn in the left half of the screen,
+    /// and the second in the right half. K switches it on or off.
+    split_screen: bool,
+
+    /// The textures are kept alive as long as the bind group that uses them.
+    /// The shape doesn't have a normal map, so its normals aren't changed.
+    #[allow(dead_code)]
+    diffuse_texture: Texture,
+    #[allow(dead_code)]
+    normal_texture: Texture,
+    #[allow(dead_code)]
+    shared_textures: SharedTextures,
+
+    /// The number of samples per pixel the GPU supports, and the number in use.
+    /// M switches between multisampling and 1 sample per pixel.
+    supported_sample_count: u32,
+    sample_count: u32,
+
+    /// Fill to draw the triangles, Line to only draw their edges as a wireframe.
+    /// L switches between them if the GPU supports it.
+    polygon_mode: PolygonMode,
+
+    /// How the vertices are turned into points, lines, or triangles. T cycles through them.
+    topology: PrimitiveTopology,
+
+    /// Which side of the triangles isn't drawn, and the winding order of the front side.
+    /// F cycles between culling the back, the front, or nothing.
+    cull_mode: Option<Face>,
+    front_face: FrontFace,
+
+    /// The depth and multisampled textures with the size of the surface
+    render_targets: RenderTargets,
+
+    /// The format the scene pipelines render to, the HDR format or the format of the surface
+    scene_format: TextureFormat,
+
+    /// Tonemaps the HDR scene to the surface, `None` if the adapter can't render it in HDR
+    hdr: Option<Hdr>,
+
+    /// Draws the rendered scene to the surface with an effect, E cycles through them
+    post_process: PostProcess,
+
+    /// The window with the parameters that can be tweaked, there's none without a window
+    #[cfg(feature = "gui")]
+    gui: Option<EguiRenderer>,
+
+    /// Draws the frame rate in the top-left corner, there's none if the font couldn't be loaded
+    #[cfg(feature = "text")]
+    text: Option<TextRenderer>,
+
+    /// The compute pipeline, there's none if the adapter doesn't support compute shaders.
+    /// J requests to run it once, between the next update and render.
+    #[cfg(not(target_arch = "wasm32"))]
+    compute: Option<DoubleKernel>,
+    #[cfg(not(target_arch = "wasm32"))]
+    compute_requested: bool,
+    texture_bind_group_layout: BindGroupLayout,
+    diffuse_bind_group: BindGroup,
+
+    /// The camera, and the uniform buffer its matrix is stored in on the GPU
+    camera: Camera,
+    camera_controller: CameraController,
+
+    /// The actions the keys trigger, like moving the camera or changing the background
+    input_map: InputMap,
+
+    /// Whether the cursor is grabbed, so mouse movement rotates the camera
+    mouse_look: bool,
+
+    /// Whether the left mouse button is held, so dragging orbits the camera around its target
+    mouse_pressed: bool,
+
+    /// Where the cursor is in the window, N adds a light where it points
+    cursor_position: Option<PhysicalPosition<f64>>,
+    camera_uniform: CameraUniform,
+    camera_buffer: Buffer,
+
+    /// Places the model in the world before the camera looks at it, bound next to the camera.
+    /// It's only uploaded when it changed.
+    model_transform: Transform,
+    model_transform_buffer: Buffer,
+    model_transform_changed: bool,
+    camera_bind_group_layout: BindGroupLayout,
+    camera_bind_group: BindGroup,
+
+    /// When the application started, and the uniform buffer the timing is stored in
+    start_time: Instant,
+    time_uniform: TimeUniform,
+    time_buffer: Buffer,
+
+    /// How much of the blend texture is mixed into the diffuse texture, from 0.0 to 1.0.
+    /// The left and right arrow keys change it.
+    mix_factor: f32,
+    mix_buffer: Buffer,
+
+    /// Tints and scales the models. The uniform buffer is only used without push constants.
+    draw_constants: DrawConstants,
+    draw_buffer: Option<Buffer>,
+
+    /// The window title the resolution and frame statistics of the context are shown after
+    window_title: String,
+    last_title_update: Instant,
+
+    /// Whether the scene is rendered to a target that stores linear colors, an sRGB surface or
+    /// the HDR texture. Otherwise colors have to be gamma corrected.
+    /// The shaders read it from the color uniform buffer.
+    is_srgb: bool,
+
+    /// Binds the time and color uniform buffers to group 2
+    globals_bind_group_layout: BindGroupLayout,
+    globals_bind_group: BindGroup,
+
+    /// The point lights in group 3, and the pipeline that draws a small model where they are.
+    /// The lights before the last update are kept to draw them in between two fixed updates.
+    /// The light buffer has room for `MAX_LIGHTS`, the count buffer tells how many are used.
+    lights: Vec<LightUniform>,
+    previous_lights: Vec<LightUniform>,
+    light_buffer: Buffer,
+    light_count_buffer: Buffer,
+    light_bind_group_layout: BindGroupLayout,
+    light_bind_group: BindGroup,
+    light_shader: ShaderModule,
+    light_pipeline: RenderPipeline,
+
+    /// How distant geometry fades into the background, bound next to the lights.
+    /// `None` turns it off, the shader fogs nothing with a density of 0 then.
+    fog: Option<FogSettings>,
+    fog_buffer: Buffer,
+
+    /// The outline is drawn around the models where they didn't write to the stencil buffer
+    outline_shader: ShaderModule,
+    outline_pipeline: RenderPipeline,
+    show_outlines: bool,
+
+    /// The grid on the ground and the axes, to see where things are
+    grid: Grid,
+    grid_shader: ShaderModule,
+    grid_pipeline: RenderPipeline,
+    show_grid: bool,
+
+    /// The sky around the scene, it replaces the background color while it's shown
+    skybox: Skybox,
+    skybox_shader: ShaderModule,
+    skybox_pipeline: RenderPipeline,
+    show_skybox: bool,
+
+    /// The triangle that's drawn at half opacity after the models, to show alpha blending
+    translucent_shader: ShaderModule,
+    translucent_pipeline: RenderPipeline,
+    translucent_vertex_buffer: Buffer,
+
+    /// Draws the meshes with a transparent material after the opaque ones, blended with
+    /// what's behind them. It always uses the texture shader, whichever pipeline is active.
+    transparent_pipeline: RenderPipeline,
+
+    /// Textured quads behind each other, to show transparent instances being sorted.
+    /// The instance buffer holds them from the furthest to the closest to the camera.
+    quad_vertex_buffer: Buffer,
+    quad_index_buffer: Buffer,
+    quad_instances: Vec<instance::Instance>,
+    quad_instance_buffer: Buffer,
+
+    vertex_buffer: Buffer,
+    num_vertices: u32,
+
+    /// The indices into the vertex buffer, `None` if the vertices should be drawn in order
+    index_buffer: Option<Buffer>,
+    index_format: IndexFormat,
+    num_indices: u32,
+
+    /// The model loaded from the resource directory, the shape is drawn if it couldn't be loaded
+    obj_model: Option<Model>,
+
+    /// The copies of the shape to draw, and their model matrices on the GPU
+    instances: Vec<instance::Instance>,
+    instance_buffer: Buffer,
+
+    /// How many instances are at the start of the instance buffer, the ones the camera can't see
+    /// are left out. They're tested with a sphere around the model or shape of this radius.
+    visible_instances: u32,
+    bounding_radius: f32,
+}
+
+impl State {
+    /// Reads the shader from disk, so it can be changed without recompiling the application.
+    /// The embedded shader is used if the file can't be read.
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    fn shader_source() -> Cow<'static, str> {
+        std::fs::read_to_string(SHADER_PATH).map_or_else(
+            |error| {
+                log::warn!("Couldn't read {SHADER_PATH}, using the embedded shader: {error}");
+                include_str!("shader.wgsl").into()
+            },
+            Cow::Owned,
+        )
+    }
+
+    /// Runs `create` like [`shader::validate_creation`], and returns the first validation error
+    /// wgpu reported instead of letting it panic. `shader` names the shader in the error.
+    ///
+    /// Waiting for the errors blocks, which isn't possible on the web.
+    /// The browser reports them in the console instead.
+    ///
+    /// # Errors
+    /// Returns an error if wgpu reported a validation error while `create` ran
+    #[cfg_attr(
+        target_arch = "wasm32",
+        allow(unused_variables, clippy::unnecessary_wraps)
+    )]
+    fn validate_creation<T>(
+        device: &Device,
+        shader: &str,
+        create: impl FnOnce() -> T,
+    ) -> Result<T, PipelineError> {
+        #[cfg(target_arch = "wasm32")]
+        return Ok(create());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pollster::block_on(shader::validate_creation(device, shader, create))
+    }
+
+    /// Compiles the shader, and finds the fragment shaders in it
+    fn create_shader(device: &Device, source: Cow<'_, str>) -> (ShaderModule, Vec<String>) {
+        let source = light::shader_source(device, draw::shader_source(device, source));
+
+        // Every fragment shader gets its own pipeline, so new ones don't have to be registered.
+        // A shader that doesn't parse is reported by wgpu as well.
+        let fragment_entry_points =
+            shader::fragment_entry_points(&source).unwrap_or_else(|error| {
+                log::error!(
+                    "Couldn't parse the shader:\n{}",
+                    error.emit_to_string(&source)
+                );
+                Vec::new()
+            });
+        log::info!("Fragment shaders: {}", fragment_entry_points.join(", "));
+
+        // Can also be done with:
+        //let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: ShaderSource::Wgsl(source),
+        });
+        (module, fragment_entry_points)
+    }
+
+    /// Creates a layout for a pipeline, with the bind groups and push constants its shader uses
+    fn create_pipeline_layout(
+        device: &Device,
+        bind_group_layouts: &[&BindGroupLayout],
+        push_constant_ranges: &[PushConstantRange],
+    ) -> PipelineLayout {
+        device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+
+            // The bind groups the shader can use, the index in this slice is the group number
+            bind_group_layouts,
+            push_constant_ranges,
+        })
+    }
+
+    /// Compiles the GLSL port of the texture shader in shader.vert and shader.frag
+    ///
+    /// # Errors
+    /// Returns an error if either shader couldn't be compiled
+    #[cfg(feature = "glsl")]
+    fn create_glsl_shaders(device: &Device) -> Result<PipelineShader, ShaderError> {
+        let vertex = draw::glsl_shader_source(device, include_str!("shader.vert").into());
+        let fragment = light::glsl_shader_source(
+            device,
+            draw::glsl_shader_source(device, include_str!("shader.frag").into()),
+        );
+        Ok(PipelineShader::Glsl {
+            vertex: shader::create_glsl_module(device, "shader.vert", &vertex)?,
+            fragment: shader::create_glsl_module(device, "shader.frag", &fragment)?,
+        })
+    }
+
+    /// The color target of a pipeline that renders to a texture with the given format.
+    /// `BlendState::REPLACE` overwrites the old colors, `BlendState::ALPHA_BLENDING` mixes the
+    /// new colors with them by their alpha, to draw translucent geometry.
+    const fn color_target(
+        format: TextureFormat,
+        blend: BlendState,
+    ) -> [Option<ColorTargetState>; 1] {
+        [Some(ColorTargetState {
+            // Using the format of the surface or texture that is rendered to
+            format,
+
+            // How the new data is combined with the old data
+            blend: Some(blend),
+
+            // Write to all colors
+            write_mask: ColorWrites::ALL,
+        })]
+    }
+
+    /// How a pipeline turns vertices into primitives. Strips have to know the format of the index
+    /// buffer they're drawn with, the highest index restarts the strip.
+    const fn primitive_state(
+        topology: PrimitiveTopology,
+        index_format: IndexFormat,
+        polygon_mode: PolygonMode,
+        cull_mode: Option<Face>,
+        front_face: FrontFace,
+    ) -> PrimitiveState {
+        PrimitiveState {
+            // `PrimitiveTopology::TriangleList` means that every 3 vertices will correspond
+            // to 1 triangle.
+            topology,
+            strip_index_format: match topology {
+                PrimitiveTopology::LineStrip | PrimitiveTopology::TriangleStrip => {
+                    Some(index_format)
+                }
+                _ => None,
+            },
+
+            // Determine whether a triangle is facing forward.
+            // With `FrontFace::Ccw`, a triangle is facing forward if the vertices are in
+            // counter-clockwise direction.
+            // Other triangles are culled as specified by `Face::Back`, `None` draws both sides.
+            front_face,
+            cull_mode,
+
+            // Fill draws the triangles, Line only their edges.
+            // Line requires Features::POLYGON_MODE_LINE.
+            polygon_mode,
+
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        }
+    }
+
+    fn create_pipeline(
+        device: &Device,
+        layout: &PipelineLayout,
+        shader: ShaderDesc<'_>,
+        targets: &[Option<ColorTargetState>],
+        vertex_layouts: &[VertexBufferLayout<'_>],
+        sample_count: u32,
+        primitive: PrimitiveState,
+    ) -> Result<RenderPipeline, PipelineError> {
+        // Translucent geometry shouldn't hide what's behind it, so it doesn't write its depth.
+        // It still doesn't show through opaque geometry, as that's drawn first.
+        let is_opaque = targets.iter().flatten().all(|target| {
+            target
+                .blend
+                .is_none_or(|blend| blend == BlendState::REPLACE)
+        });
+
+        // Only draw pixels that are closer than the pixel that was already drawn.
+        // The stencil buffer isn't used.
+        Self::create_pipeline_with_depth_stencil(
+            device,
+            layout,
+            shader,
+            targets,
+            vertex_layouts,
+            sample_count,
+            primitive,
+            Self::depth_stencil_state(is_opaque, CompareFunction::Less, StencilState::default()),
+        )
+    }
+
+    /// How a pipeline tests and writes the depth and stencil of the pixels it draws
+    const fn depth_stencil_state(
+        depth_write_enabled: bool,
+        depth_compare: CompareFunction,
+        stencil: StencilState,
+    ) -> DepthStencilState {
+        DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled,
+            depth_compare,
+            stencil,
+            bias: DepthBiasState {
+                constant: 0,
+                slope_scale: 0.0,
+                clamp: 0.0,
+            },
+        }
+    }
+
+    /// Sets the stencil of every pixel that's drawn to the reference value of the render pass,
+    /// so later pipelines can test where the models are
+    const fn stencil_write_state() -> StencilState {
+        let face = StencilFaceState {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Replace,
+        };
+        StencilState {
+            front: face,
+            back: face,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        }
+    }
+
+    /// Only draws pixels where the stencil isn't the reference value of the render pass,
+    /// without changing the stencil
+    const fn stencil_outline_state() -> StencilState {
+        let face = StencilFaceState {
+            compare: CompareFunction::NotEqual,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        };
+        StencilState {
+            front: face,
+            back: face,
+            read_mask: 0xff,
+            write_mask: 0,
+        }
+    }
+
+    /// Like [`Self::create_pipeline`], but with the given depth and stencil state
+    ///
+    /// # Errors
+    /// Returns an error if wgpu rejected the pipeline, like when the shader doesn't have
+    /// the entry points, or they don't match the layout
+    #[allow(clippy::too_many_arguments)]
+    fn create_pipeline_with_depth_stencil(
+        device: &Device,
+        layout: &PipelineLayout,
+        shader: ShaderDesc<'_>,
+        targets: &[Option<ColorTargetState>],
+        vertex_layouts: &[VertexBufferLayout<'_>],
+        sample_count: u32,
+        primitive: PrimitiveState,
+        depth_stencil: DepthStencilState,
+    ) -> Result<RenderPipeline, PipelineError> {
+        Self::validate_creation(device, shader.fs_entry, || {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: shader.vertex,
+                    // The function in the shader that should be the entry point.
+                    // In this case for the vertex shader.
+                    entry_point: shader.vs_entry,
+
+                    // The types of vertices to pass to the vertex shader, one layout per slot
+                    buffers: vertex_layouts,
+                },
+
+                // The fragment state is optional, but here it's needed to store color data
+                // to the surface
+                fragment: Some(FragmentState {
+                    module: shader.fragment,
+                    entry_point: shader.fs_entry,
+
+                    // The color outputs to set up, one per `@location` the fragment shader writes
+                    targets,
+                }),
+
+                // How to interpret vertices when converting them into triangles
+                primitive,
+
+                // The depth buffer is used to only draw the pixels that are closest to the camera,
+                // the stencil buffer to only draw where an earlier pipeline did or didn't draw
+                depth_stencil: Some(depth_stencil),
+
+                multisample: MultisampleState {
+                    // The number of samples the pipeline uses, has to match the render targets
+                    count: sample_count,
+
+                    // Which samples should be active (all of them)
+                    mask: !0,
+
+                    // No anti aliasing is used
+                    alpha_to_coverage_enabled: false,
+                },
+
+                // Number of array layers the render attachments can have
+                multiview: None,
+            })
+        })
+    }
+
+    /// Unwraps a pipeline that runs one of the embedded shaders. build.rs checks that they
+    /// compile and have the entry points the pipelines use, so wgpu doesn't reject them.
+    ///
+    /// # Panics
+    /// Panics with the error if wgpu rejected the pipeline anyway
+    fn expect_embedded(pipeline: Result<RenderPipeline, PipelineError>) -> RenderPipeline {
+        pipeline.unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Creates a bind group that makes every uniform buffer available to the given shader
+    /// stages, the index of the buffer in the slice is its binding.
+    fn create_uniform_bind_group(
+        device: &Device,
+        label: &str,
+        buffers: &[&Buffer],
+        visibility: ShaderStages,
+    ) -> (BindGroupLayout, BindGroup) {
+        let layout_entries = (0..)
+            .zip(buffers)
+            .map(|(binding, _)| BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+
+                    // The size of the buffer won't change
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            })
+            .collect::<Vec<_>>();
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&format!("{label} Bind Group Layout")),
+            entries: &layout_entries,
+        });
+
+        let entries = (0..)
+            .zip(buffers)
+            .map(|(binding, buffer)| BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect::<Vec<_>>();
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{label} Bind Group")),
+            layout: &layout,
+            entries: &entries,
+        });
+
+        (layout, bind_group)
+    }
+
+    /// Creates the uniform buffers that are bound to group 2, and returns the time buffer to
+    /// update it every frame. Both shaders can use the time to animate. The color uniform tells
+    /// the fragment shaders whether they have to gamma correct their output, the surface format
+    /// doesn't change, so it's never written to again. The mix factor is bound after it.
+    /// The draw constants buffer is bound last, if the GPU doesn't support push constants.
+    fn create_globals(
+        device: &Device,
+        time_uniform: TimeUniform,
+        is_srgb: bool,
+        mix_buffer: &Buffer,
+        draw_buffer: Option<&Buffer>,
+    ) -> (Buffer, BindGroupLayout, BindGroup) {
+        let time_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Time Buffer"),
+            contents: bytemuck::cast_slice(&[time_uniform]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let color_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Color Buffer"),
+            contents: bytemuck::cast_slice(&[ColorUniform::new(is_srgb)]),
+            usage: BufferUsages::UNIFORM,
+        });
+        let buffers: Vec<_> = [&time_buffer, &color_buffer, mix_buffer]
+            .into_iter()
+            .chain(draw_buffer)
+            .collect();
+        let (layout, bind_group) = Self::create_uniform_bind_group(
+            device,
+            "Globals",
+            &buffers,
+            ShaderStages::VERTEX_FRAGMENT,
+        );
+        (time_buffer, layout, bind_group)
+    }
+
+    /// Creates the buffer with room for every light, and the uniform buffer with their count,
+    /// bound to group 3 with the fog. The lights are in a storage buffer if the GPU supports them,
+    /// otherwise in a uniform buffer, as WebGL2 has no storage buffers.
+    fn create_lights(
+        device: &Device,
+        lights: &[LightUniform],
+        fog_buffer: &Buffer,
+    ) -> (Buffer, Buffer, BindGroupLayout, BindGroup) {
+        let mut contents = [LightUniform::zeroed(); MAX_LIGHTS];
+        contents[..lights.len()].copy_from_slice(lights);
+        let light_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::cast_slice(&contents),
+            usage: light::buffer_usage(device),
+        });
+        let count_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Light Count Buffer"),
+            contents: bytemuck::cast_slice(&[LightCount::new(lights.len())]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let layout_entry = |binding, ty| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::VERTEX_FRAGMENT,
+            ty: BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Light Bind Group Layout"),
+            entries: &[
+                layout_entry(0, light::binding_type(device)),
+                layout_entry(1, BufferBindingType::Uniform),
+                layout_entry(2, BufferBindingType::Uniform),
+            ],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Light Bind Group"),
+            layout: &layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: light_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: count_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: fog_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        (light_buffer, count_buffer, layout, bind_group)
+    }
+
+    /// Creates the pipeline that draws the lights. It uses the same bind groups as the other
+    /// pipelines, but draws a copy for every light without the instance buffer.
+    fn create_light_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        bind_group_layouts: &[&BindGroupLayout],
+        shader: &ShaderModule,
+        sample_count: u32,
+        primitive: PrimitiveState,
+    ) -> RenderPipeline {
+        // The light isn't tinted or scaled, so it doesn't use the push constants.
+        // OpenGL can't set push constants a shader doesn't declare.
+        Self::expect_embedded(Self::create_pipeline(
+            device,
+            &Self::create_pipeline_layout(device, bind_group_layouts, &[]),
+            ShaderDesc::new(shader, entry_points::VERTEX, entry_points::LIGHT),
+            &Self::color_target(format, BlendState::REPLACE),
+            &[Vertex::desc()],
+            sample_count,
+            primitive,
+        ))
+    }
+
+    /// Creates the pipeline that draws the outline around the models. It's instanced, and uses
+    /// the bind groups and draw constants of the other pipelines. It ignores the depth,
+    /// so the outline shows through other geometry, but only where the models didn't write
+    /// to the stencil buffer.
+    fn create_outline_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        bind_group_layouts: &[&BindGroupLayout],
+        shader: &ShaderModule,
+        sample_count: u32,
+        primitive: PrimitiveState,
+    ) -> RenderPipeline {
+        Self::expect_embedded(Self::create_pipeline_with_depth_stencil(
+            device,
+            &Self::create_pipeline_layout(
+                device,
+                bind_group_layouts,
+                &draw::push_constant_ranges(device),
+            ),
+            ShaderDesc::new(shader, entry_points::VERTEX, entry_points::OUTLINE),
+            &Self::color_target(format, BlendState::REPLACE),
+            &[Vertex::desc(), InstanceRaw::desc()],
+            sample_count,
+            primitive,
+            Self::depth_stencil_state(
+                false,
+                CompareFunction::Always,
+                Self::stencil_outline_state(),
+            ),
+        ))
+    }
+
+    /// Creates the pipeline that draws the lines of the grid. It uses the same bind groups as
+    /// the other pipelines, and the depth buffer, so the models hide the lines behind them.
+    fn create_grid_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        bind_group_layouts: &[&BindGroupLayout],
+        shader: &ShaderModule,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        Self::expect_embedded(Self::create_pipeline(
+            device,
+            &Self::create_pipeline_layout(device, bind_group_layouts, &[]),
+            ShaderDesc::new(shader, entry_points::VERTEX, entry_points::GRID),
+            &Self::color_target(format, BlendState::REPLACE),
+            &[LineVertex::desc()],
+            sample_count,
+            Self::primitive_state(
+                PrimitiveTopology::LineList,
+                IndexFormat::Uint16,
+                PolygonMode::Fill,
+                None,
+                FrontFace::Ccw,
+            ),
+        ))
+    }
+
+    /// Creates the pipeline that draws the sky. It only uses the bind group of the skybox,
+    /// and has no vertex buffer. The sky is drawn at the far plane, where the depth buffer was
+    /// cleared to, so it needs `LessEqual` to pass there. It doesn't write its depth, as nothing
+    /// can be behind it.
+    fn create_skybox_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        bind_group_layout: &BindGroupLayout,
+        shader: &ShaderModule,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        Self::expect_embedded(Self::create_pipeline_with_depth_stencil(
+            device,
+            &Self::create_pipeline_layout(device, &[bind_group_layout], &[]),
+            ShaderDesc::new(shader, entry_points::VERTEX, entry_points::SKYBOX),
+            &Self::color_target(format, BlendState::REPLACE),
+            &[],
+            sample_count,
+            PrimitiveState::default(),
+            Self::depth_stencil_state(false, CompareFunction::LessEqual, StencilState::default()),
+        ))
+    }
+
+    /// Creates the pipeline that draws the translucent triangle with alpha blending.
+    /// It uses the same bind groups as the other pipelines, and isn't instanced.
+    fn create_translucent_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        bind_group_layouts: &[&BindGroupLayout],
+        shader: &ShaderModule,
+        sample_count: u32,
+    ) -> RenderPipeline {
+        Self::expect_embedded(Self::create_pipeline(
+            device,
+            &Self::create_pipeline_layout(device, bind_group_layouts, &[]),
+            ShaderDesc::new(shader, entry_points::VERTEX, entry_points::TRANSLUCENT),
+            &Self::color_target(format, BlendState::ALPHA_BLENDING),
+            &[Vertex::desc()],
+            sample_count,
+            Self::primitive_state(
+                PrimitiveTopology::TriangleList,
+                IndexFormat::Uint16,
+                PolygonMode::Fill,
+                Some(Face::Back),
+                FrontFace::Ccw,
+            ),
+        ))
+    }
+
+    /// Creates the pipeline that draws transparent meshes with alpha blending. It's instanced,
+    /// and uses the bind groups and draw constants of the other pipelines.
+    /// The triangles are always filled, and both sides are drawn, so surfaces behind
+    /// the front of a transparent model show through.
+    ///
+    /// # Errors
+    /// Returns an error if wgpu rejected the pipeline, like when the shader has no texture shader
+    fn create_transparent_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        bind_group_layouts: &[&BindGroupLayout],
+        shader: &ShaderModule,
+        sample_count: u32,
+    ) -> Result<RenderPipeline, PipelineError> {
+        Self::create_pipeline(
+            device,
+            &Self::create_pipeline_layout(
+                device,
+                bind_group_layouts,
+                &draw::push_constant_ranges(device),
+            ),
+            ShaderDesc::new(shader, entry_points::VERTEX, entry_points::TEXTURE),
+            &Self::color_target(format, BlendState::ALPHA_BLENDING),
+            &[Vertex::desc(), InstanceRaw::desc()],
+            sample_count,
+            Self::primitive_state(
+                PrimitiveTopology::TriangleList,
+                IndexFormat::Uint16,
+                PolygonMode::Fill,
+                None,
+                FrontFace::Ccw,
+            ),
+        )
+    }
+
+    /// Creates the depth texture, and the multisampled framebuffer if multisampling is used.
+    /// Both have the size of the surface, so they have to be recreated when it's resized.
+    fn create_render_targets(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> RenderTargets {
+        RenderTargets {
+            depth_texture: Texture::create_depth_texture(
+                device,
+                config,
+                sample_count,
+                "Depth Texture",
+            ),
+            msaa_view: (sample_count > 1).then(|| {
+                Texture::create_multisampled_framebuffer(device, config, format, sample_count)
+            }),
+        }
+    }
+
+    /// Uploads the vertices and indices of the shape to the GPU
+    fn create_shape_buffers(device: &Device) -> (Buffer, Buffer) {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: BufferUsages::VERTEX,
+        });
+        (vertex_buffer, Self::create_index_buffer(device, INDICES))
+    }
+
+    /// Uploads 16-bit indices of the shape to the GPU
+    fn create_index_buffer(device: &Device, indices: &[u16]) -> Buffer {
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: BufferUsages::INDEX,
+        })
+    }
+
+    /// Loads the model with its materials, the textures are bound to group 0.
+    /// A missing or broken model isn't fatal, the shape is drawn instead.
+    ///
+    /// On the web, the model can't be waited for here. It's fetched in [`App::load`] instead.
+    fn load_obj_model(
+        device: &Device,
+        queue: &Queue,
+        shared: &SharedTextures,
+        layout: &BindGroupLayout,
+    ) -> Option<Model> {
+        if cfg!(target_arch = "wasm32") {
+            return None;
+        }
+        pollster::block_on(resources::load_model(
+            MODEL_FILE, device, queue, shared, layout,
+        ))
+        .map_err(|error| log::warn!("Couldn't load {MODEL_FILE}: {error}"))
+        .ok()
+    }
+
+    /// The background color when no other color is given
+    pub const DEFAULT_BACKGROUND_COLOR: Color = Color {
+        r: 0.1,
+        g: 0.2,
+        b: 0.3,
+        a: 1.0,
+    };
+
+    /// Creates the resources of the demo on the GPU of the context, with the background color
+    /// from the `LEARN_WGPU_CLEAR_COLOR` environment variable or the default one
+    ///
+    /// # Panics
+    /// Panics if the embedded texture couldn't be decoded
+    #[must_use]
+    pub fn new(ctx: &Context) -> Self {
+        Self::with_background_color(
+            ctx,
+            color::background_color_from_env().unwrap_or(Self::DEFAULT_BACKGROUND_COLOR),
+        )
+    }
+
+    /// Creates the resources of the demo on the GPU of the context.
+    /// The background starts with the given linear color, and 0 resets it to that color.
+    ///
+    /// # Panics
+    /// Panics if the embedded texture couldn't be decoded
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn with_background_color(ctx: &Context, background_color: Color) -> Self {
+        let Context {
+            device,
+            queue,
+            config,
+            ..
+        } = ctx;
+
+        // Render the scene in HDR and tonemap it to the surface if the adapter can,
+        // otherwise the scene is drawn to the surface directly
+        let hdr = hdr::supported(&ctx.adapter).then(|| Hdr::new(device, config));
+        let scene_format = if hdr.is_some() {
+            hdr::HDR_FORMAT
+        } else {
+            config.format
+        };
+        log::info!("Scene format: {scene_format:?}");
+
+        // Use multisampling if the scene and depth formats support it, and create the
+        // depth buffer and multisampled framebuffer with the same size as the surface
+        let sample_count = texture::supported_sample_count(
+            &ctx.adapter,
+            &[scene_format, Texture::DEPTH_FORMAT],
+            MSAA_SAMPLE_COUNT,
+        );
+        let render_targets =
+            Self::create_render_targets(device, config, scene_format, sample_count);
+
+        // Load the texture to draw on the shape
+        let diffuse_texture = Texture::from_bytes(
+            device,
+            queue,
+            include_bytes!("happy-tree.png"),
+            "happy-tree.png",
+            false,
+        )
+        .unwrap();
+        let normal_texture = Texture::flat_normal_map(device, queue);
+
+        // And a second texture, that's blended with the first one by the mix factor,
+        // and the textures instances can be drawn with instead
+        let shared_textures = SharedTextures {
+            blend: Texture::checkerboard(device, queue, 8, 8, "Checkerboard"),
+            instances: Texture::from_bytes_array(
+                device,
+                queue,
+                &INSTANCE_TEXTURES,
+                "Instance Textures",
+            )
+            .unwrap(),
+        };
+
+        // Describe the resources the fragment shader can access in group 0,
+        // and bind the textures, the normal map, and their samplers to it
+        let texture_bind_group_layout = Texture::create_bind_group_layout(device);
+        let diffuse_bind_group = Texture::create_bind_group(
+            device,
+            &texture_bind_group_layout,
+            &diffuse_texture,
+            &normal_texture,
+            &shared_textures,
+            "Diffuse",
+        );
+
+        // Create a camera looking at the shape, and upload its matrix to group 1.
+        // The fragment shader uses its position for the specular reflections.
+        let camera = Camera::new(camera::aspect_ratio(config.width, config.height));
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(&camera);
+        let camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[camera_uniform]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // The model matrix is bound after the camera, only the vertex shaders need it
+        let model_transform = Transform::default();
+        let model_transform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Model Transform Buffer"),
+            contents: bytemuck::cast_slice(&[TransformUniform::from(model_transform)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let (camera_bind_group_layout, camera_bind_group) = Self::create_uniform_bind_group(
+            device,
+            "Camera",
+            &[&camera_buffer, &model_transform_buffer],
+            ShaderStages::VERTEX_FRAGMENT,
+        );
+
+        // The draw constants need a uniform buffer if the GPU doesn't support push constants
+        let draw_constants = DrawConstants::default();
+        let draw_buffer = if draw::push_constants_supported(device) {
+            log::info!("Passing the draw constants as push constants");
+            None
+        } else {
+            log::info!("Passing the draw constants in a uniform buffer");
+            Some(device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Draw Constants Buffer"),
+                contents: bytemuck::cast_slice(&[draw_constants]),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            }))
+        };
+
+        // Only show the first texture at the start
+        let mix_factor = 0.0;
+        let mix_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Mix Buffer"),
+            contents: bytemuck::cast_slice(&[MixUniform::new(mix_factor)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // Create the uniform buffers for the time, the color handling, the mix factor,
+        // and maybe the draw constants in group 2
+        let time_uniform = TimeUniform::new();
+        let is_srgb = scene_format.is_srgb() || hdr.is_some();
+        let (time_buffer, globals_bind_group_layout, globals_bind_group) = Self::create_globals(
+            device,
+            time_uniform,
+            is_srgb,
+            &mix_buffer,
+            draw_buffer.as_ref(),
+        );
+
+        // Create a white light above the shapes in group 3, more can be added later
+        let lights = vec![LightUniform::new(
+            [2.0, LIGHT_SPAWN_HEIGHT, 2.0],
+            [1.0, 1.0, 1.0],
+        )];
+        // The fog starts off, it's written every update to follow the background color
+        let fog_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Fog Buffer"),
+            contents: bytemuck::cast_slice(&[FogUniform::new(None, background_color)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let (light_buffer, light_count_buffer, light_bind_group_layout, light_bind_group) =
+            Self::create_lights(device, &lights, &fog_buffer);
+        let bind_group_layouts = [
+            &texture_bind_group_layout,
+            &camera_bind_group_layout,
+            &globals_bind_group_layout,
+            &light_bind_group_layout,
+        ];
+        let light_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("light.wgsl"),
+            source: ShaderSource::Wgsl(light::shader_source(
+                device,
+                color::shader_source(include_str!("light.wgsl")),
+            )),
+        });
+        let light_pipeline = Self::create_light_pipeline(
+            device,
+            scene_format,
+            &bind_group_layouts,
+            &light_shader,
+            sample_count,
+            Self::primitive_state(
+                PrimitiveTopology::TriangleList,
+                IndexFormat::Uint16,
+                PolygonMode::Fill,
+                Some(Face::Back),
+                FrontFace::Ccw,
+            ),
+        );
+
+        let outline_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("outline.wgsl"),
+            source: ShaderSource::Wgsl(draw::shader_source(
+                device,
+                color::shader_source(include_str!("outline.wgsl")),
+            )),
+        });
+        let outline_pipeline = Self::create_outline_pipeline(
+            device,
+            scene_format,
+            &bind_group_layouts,
+            &outline_shader,
+            sample_count,
+            Self::primitive_state(
+                PrimitiveTopology::TriangleList,
+                IndexFormat::Uint16,
+                PolygonMode::Fill,
+                Some(Face::Back),
+                FrontFace::Ccw,
+            ),
+        );
+
+        let mut skybox = Skybox::new(
+            device,
+            queue,
+            &skybox::gradient_faces(SKYBOX_FACE_SIZE),
+            !is_srgb,
+        );
+        skybox.write_camera(queue, &camera);
+        let skybox_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("skybox.wgsl"),
+            source: ShaderSource::Wgsl(color::shader_source(include_str!("skybox.wgsl"))),
+        });
+        let skybox_pipeline = Self::create_skybox_pipeline(
+            device,
+            scene_format,
+            skybox.bind_group_layout(),
+            &skybox_shader,
+            sample_count,
+        );
+
+        let grid = Grid::new(device, GRID_SIZE, GRID_SPACING);
+        let grid_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("grid.wgsl"),
+            source: ShaderSource::Wgsl(color::shader_source(include_str!("grid.wgsl"))),
+        });
+        let grid_pipeline = Self::create_grid_pipeline(
+            device,
+            scene_format,
+            &bind_group_layouts,
+            &grid_shader,
+            sample_count,
+        );
+
+        // The translucent triangle is drawn after the models with alpha blending
+        let translucent_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("translucent.wgsl"),
+            source: ShaderSource::Wgsl(color::shader_source(include_str!("translucent.wgsl"))),
+        });
+        let translucent_pipeline = Self::create_translucent_pipeline(
+            device,
+            scene_format,
+            &bind_group_layouts,
+            &translucent_shader,
+            sample_count,
+        );
+        let translucent_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Translucent Vertex Buffer"),
+            contents: bytemuck::cast_slice(TRANSLUCENT_TRIANGLE),
+            usage: BufferUsages::VERTEX,
+        });
+
+        // The quads are the transparent meshes of the shape, placed off to the side
+        let quad_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Quad Vertex Buffer"),
+            contents: bytemuck::cast_slice(QUAD_VERTICES),
+            usage: BufferUsages::VERTEX,
+        });
+        let quad_index_buffer = Self::create_index_buffer(device, QUAD_INDICES);
+        let quad_instances = [[-2.0, 1.6, 3.4], [-1.8, 1.6, 4.0], [-1.6, 1.6, 4.6]]
+            .map(|position| instance::Instance {
+                position: position.into(),
+                rotation: Quaternion::from_angle_y(Rad(0.0)),
+                texture_index: 0,
+            })
+            .to_vec();
+        let quad_instance_buffer = instance::create_instance_buffer(device, &quad_instances);
+
+        let (vertex_buffer, index_buffer) = Self::create_shape_buffers(device);
+        let obj_model =
+            Self::load_obj_model(device, queue, &shared_textures, &texture_bind_group_layout);
+
+        // Place copies of the shape in a 10x10 grid, and upload their model matrices and textures
+        // to the GPU. They alternate between their own texture and the instance textures.
+        #[allow(clippy::cast_possible_truncation)]
+        let instances = instance::Instance::grid(10, 1.0, INSTANCE_TEXTURES.len() as u32 + 1);
+        let instance_buffer = instance::create_instance_buffer(device, &instances);
+
+        // The instances are rotated around their origin, so a sphere around it always contains them
+        let bounding_radius = obj_model
+            .as_ref()
+            .map_or_else(|| model::bounding_radius(VERTICES), Model::bounding_radius);
+
+        // Start with the embedded shader, with hot reloading the one on disk replaces it later
+        let (shader, fragment_entry_points) =
+            Self::create_shader(device, include_str!("shader.wgsl").into());
+        let transparent_pipeline = Self::expect_embedded(Self::create_transparent_pipeline(
+            device,
+            scene_format,
+            &bind_group_layouts,
+            &shader,
+            sample_count,
+        ));
+        let mut state = Self {
+            adapter_info: ctx.adapter.get_info(),
+            background_color,
+            initial_background_color: background_color,
+            animate_background: false,
+            background_hue: 0.0,
+            background_paused_until: None,
+            supported_sample_count: sample_count,
+            sample_count,
+            polygon_mode: PolygonMode::Fill,
+            topology: PrimitiveTopology::TriangleList,
+            cull_mode: Some(Face::Back),
+            front_face: FrontFace::Ccw,
+            render_targets,
+            scene_format,
+            hdr,
+            post_process: PostProcess::new(device, config),
+            #[cfg(feature = "gui")]
+            gui: ctx
+                .window()
+                .map(|window| EguiRenderer::new(device, config.format, window)),
+            #[cfg(feature = "text")]
+            text: TextRenderer::new(device, config)
+                .map_err(|error| log::error!("Couldn't load the font: {error}"))
+                .ok(),
+            #[cfg(not(target_arch = "wasm32"))]
+            compute: compute::is_supported(&ctx.adapter).then(|| DoubleKernel::new(device)),
+            #[cfg(not(target_arch = "wasm32"))]
+            compute_requested: false,
+            shader,
+            #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+            shader_watcher: FileWatcher::new(SHADER_PATH)
+                .map_err(|error| log::warn!("Couldn't watch {SHADER_PATH}: {error}"))
+                .ok(),
+            pipelines: Vec::new(),
+            active_pipeline: 0,
+            split_screen: false,
+            diffuse_texture,
+            normal_texture,
+            shared_textures,
+            texture_bind_group_layout,
+            diffuse_bind_group,
+            camera,
+            camera_controller: CameraController::new(1.5, 0.003),
+            input_map: InputMap::default(),
+            mouse_look: false,
+            mouse_pressed: false,
+            cursor_position: None,
+            camera_uniform,
+            camera_buffer,
+            model_transform,
+            model_transform_buffer,
+            model_transform_changed: false,
+            camera_bind_group_layout,
+            camera_bind_group,
+            start_time: Instant::now(),
+            window_title: ctx.window().map_or_else(String::new, Window::title),
+            last_title_update: Instant::now(),
+            time_uniform,
+            time_buffer,
+            mix_factor,
+            mix_buffer,
+            draw_constants,
+            draw_buffer,
+            is_srgb,
+            light_pipeline,
+            globals_bind_group_layout,
+            globals_bind_group,
+            previous_lights: lights.clone(),
+            lights,
+            light_buffer,
+            light_count_buffer,
+            light_bind_group_layout,
+            light_bind_group,
+            light_shader,
+            fog: None,
+            fog_buffer,
+            outline_shader,
+            outline_pipeline,
+            show_outlines: false,
+            grid,
+            grid_shader,
+            grid_pipeline,
+            show_grid: false,
+            skybox,
+            skybox_shader,
+            skybox_pipeline,
+            show_skybox: false,
+            translucent_shader,
+            translucent_pipeline,
+            translucent_vertex_buffer,
+            transparent_pipeline,
+            quad_vertex_buffer,
+            quad_index_buffer,
+            quad_instances,
+            quad_instance_buffer,
+            vertex_buffer,
+            num_vertices: u32::try_from(VERTICES.len()).unwrap(),
+            index_buffer: Some(index_buffer),
+            index_format: IndexFormat::Uint16,
+            num_indices: u32::try_from(INDICES.len()).unwrap(),
+            obj_model,
+            visible_instances: u32::try_from(instances.len()).unwrap(),
+            instances,
+            instance_buffer,
+            bounding_radius,
+        };
+
+        state.log_adapter_info(ctx);
+
+        // Register a pipeline for every fragment shader in shader.wgsl
+        state.pipelines = state
+            .create_wgsl_pipelines(ctx, &state.shader, fragment_entry_points)
+            .unwrap_or_else(|error| panic!("{error}"));
+
+        // The shader on disk might be broken, then the embedded one is kept
+        #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+        state.reload_shader(ctx);
+
+        // And the GLSL port of the texture pipeline, to compare them
+        #[cfg(feature = "glsl")]
+        match Self::create_glsl_shaders(device) {
+            Ok(shader) => {
+                if let Err(error) = state.insert_pipeline(ctx, "glsl", shader) {
+                    log::error!("The GLSL shaders don't match the pipeline: {error}");
+                }
+            }
+            Err(error) => log::error!("Couldn't compile the GLSL shaders: {error}"),
+        }
+
+        // And the precompiled SPIR-V version, if it was compiled
+        #[cfg(all(feature = "spirv", not(target_arch = "wasm32")))]
+        state.add_spirv_pipeline(ctx);
+        state
+    }
+
+    /// The layouts of the bind groups every pipeline uses, in the order of their group index
+    const fn bind_group_layouts(&self) -> [&BindGroupLayout; 4] {
+        [
+            &self.texture_bind_group_layout,
+            &self.camera_bind_group_layout,
+            &self.globals_bind_group_layout,
+            &self.light_bind_group_layout,
+        ]
+    }
+
+    /// The layout of the pipelines that draw the models: every bind group,
+    /// and the draw constants if they're passed as push constants
+    fn pipeline_layout(&self, device: &Device) -> PipelineLayout {
+        Self::create_pipeline_layout(
+            device,
+            &self.bind_group_layouts(),
+            &draw::push_constant_ranges(device),
+        )
+    }
+
+    /// Creates a pipeline using the given fragment shader entry point,
+    /// and registers it under the given name so it can be selected later.
+    /// A pipeline that was already registered under that name is replaced.
+    ///
+    /// # Errors
+    /// Returns an error if wgpu rejected the pipeline, like when the shader has no fragment shader
+    /// with that name. The registered pipelines don't change then.
+    pub fn add_pipeline(
+        &mut self,
+        ctx: &Context,
+        name: &str,
+        fragment_entry_point: &str,
+    ) -> Result<(), PipelineError> {
+        self.insert_pipeline(
+            ctx,
+            name,
+            PipelineShader::Wgsl(fragment_entry_point.to_owned()),
+        )
+    }
+
+    /// Creates a pipeline that runs the given shader, and registers it under the given name.
+    /// A pipeline that was already registered under that name is replaced.
+    ///
+    /// # Errors
+    /// Returns an error if wgpu rejected the pipeline, the registered pipelines don't change then
+    fn insert_pipeline(
+        &mut self,
+        ctx: &Context,
+        name: &str,
+        shader: PipelineShader,
+    ) -> Result<(), PipelineError> {
+        let layout = self.pipeline_layout(&ctx.device);
+        let pipeline = self.create_named_pipeline(ctx, &layout, &self.shader, &shader)?;
+
+        // Replace the existing pipeline with the same name, otherwise add a new one
+        if let Some(existing) = self.pipelines.iter_mut().find(|named| named.name == name) {
+            existing.shader = shader;
+            existing.pipeline = pipeline;
+        } else {
+            self.pipelines.push(NamedPipeline {
+                name: name.to_owned(),
+                shader,
+                pipeline,
+            });
+        }
+        Ok(())
+    }
+
+    /// Loads shader.spv, and registers a pipeline for it as "spirv". It's compiled separately,
+    /// so it's checked whether the shader still matches the bind groups of the pipelines.
+    #[cfg(all(feature = "spirv", not(target_arch = "wasm32")))]
+    fn add_spirv_pipeline(&mut self, ctx: &Context) {
+        if !std::path::Path::new(SPIRV_PATH).exists() {
+            log::info!("No SPIR-V shader, compile one with `naga src/shader.wgsl src/shader.spv`");
+            return;
+        }
+        let module = match shader::load_spirv_module(&ctx.device, SPIRV_PATH) {
+            Ok(module) => module,
+            Err(error) => {
+                log::error!("Couldn't load the SPIR-V shader: {error}");
+                return;
+            }
+        };
+
+        // The SPIR-V declares the draw constants and the lights as uniforms, so it can't be used
+        // with push constants or a storage buffer for the lights, and an outdated file might not
+        // match the bind groups anymore
+        if let Err(error) = self.insert_pipeline(ctx, "spirv", PipelineShader::SpirV(module)) {
+            log::error!("The SPIR-V shader doesn't match the pipeline: {error}");
+        }
+    }
+
+    /// Creates a pipeline with the current settings that runs the shader of a named pipeline.
+    /// WGSL pipelines use the given module, so they can be created with a reloaded shader.
+    ///
+    /// # Errors
+    /// Returns an error if wgpu rejected the pipeline
+    fn create_named_pipeline(
+        &self,
+        ctx: &Context,
+        layout: &PipelineLayout,
+        wgsl: &ShaderModule,
+        shader: &PipelineShader,
+    ) -> Result<RenderPipeline, PipelineError> {
+        let shader = match shader {
+            PipelineShader::Wgsl(fragment_entry_point) => {
+                ShaderDesc::new(wgsl, entry_points::VERTEX, fragment_entry_point)
+            }
+            #[cfg(feature = "glsl")]
+            PipelineShader::Glsl { vertex, fragment } => ShaderDesc::glsl(vertex, fragment),
+            #[cfg(feature = "spirv")]
+            PipelineShader::SpirV(module) => {
+                ShaderDesc::new(module, entry_points::VERTEX, entry_points::TEXTURE)
+            }
+        };
+        // The models mark where they are in the stencil buffer, so the outline isn't drawn there
+        Self::create_pipeline_with_depth_stencil(
+            &ctx.device,
+            layout,
+            shader,
+            &Self::color_target(self.scene_format, BlendState::REPLACE),
+            &[Vertex::desc(), InstanceRaw::desc()],
+            self.sample_count,
+            self.primitive(self.polygon_mode),
+            Self::depth_stencil_state(true, CompareFunction::Less, Self::stencil_write_state()),
+        )
+    }
+
+    /// How the models are turned into primitives with the current topology and culling,
+    /// strips use the format of the index buffer of the model or shape
+    const fn primitive(&self, polygon_mode: PolygonMode) -> PrimitiveState {
+        let index_format = if self.obj_model.is_some() {
+            IndexFormat::Uint32
+        } else {
+            self.index_format
+        };
+        Self::primitive_state(
+            self.topology,
+            index_format,
+            polygon_mode,
+            self.cull_mode,
+            self.front_face,
+        )
+    }
+
+    /// Creates a pipeline for every fragment shader of the WGSL shader,
+    /// named after their entry points
+    ///
+    /// # Errors
+    /// Returns the first error wgpu reported for the pipelines
+    fn create_wgsl_pipelines(
+        &self,
+        ctx: &Context,
+        wgsl: &ShaderModule,
+        fragment_entry_points: Vec<String>,
+    ) -> Result<Vec<NamedPipeline>, PipelineError> {
+        let layout = self.pipeline_layout(&ctx.device);
+        fragment_entry_points
+            .into_iter()
+            .map(|entry_point| {
+                let shader = PipelineShader::Wgsl(entry_point.clone());
+                Ok(NamedPipeline {
+                    pipeline: self.create_named_pipeline(ctx, &layout, wgsl, &shader)?,
+                    name: entry_point,
+                    shader,
+                })
+            })
+            .collect()
+    }
+
+    /// Creates a new version of every registered pipeline with the given shader,
+    /// in the same order as they were registered
+    ///
+    /// # Errors
+    /// Returns the first error wgpu reported for the pipelines
+    fn recreate_pipelines(
+        &self,
+        ctx: &Context,
+        shader: &ShaderModule,
+    ) -> Result<Vec<RenderPipeline>, PipelineError> {
+        let layout = self.pipeline_layout(&ctx.device);
+        self.pipelines
+            .iter()
+            .map(|named| self.create_named_pipeline(ctx, &layout, shader, &named.shader))
+            .collect()
+    }
+
+    /// Switches between the WGSL and the GLSL version of the texture pipeline,
+    /// they should look exactly the same
+    #[cfg(feature = "glsl")]
+    fn toggle_glsl(&mut self) {
+        let is_glsl = self
+            .pipelines
+            .get(self.active_pipeline)
+            .is_some_and(|named| named.name == "glsl");
+        let name = if is_glsl {
+            entry_points::TEXTURE
+        } else {
+            "glsl"
+        };
+        if self.set_active_pipeline(name) {
+            log::info!("Rendering with the {name} pipeline");
+        } else {
+            log::warn!("The GLSL pipeline isn't available, the shaders didn't compile");
+        }
+    }
+
+    /// Switches between multisampling and 1 sample per pixel.
+    /// The pipelines and render targets have to be recreated with the new sample count.
+    fn toggle_msaa(&mut self, ctx: &Context) {
+        if self.supported_sample_count == 1 {
+            log::warn!("Multisampling isn't supported");
+            return;
+        }
+
+        self.sample_count = if self.sample_count == 1 {
+            self.supported_sample_count
+        } else {
+            1
+        };
+        self.replace_pipelines(ctx);
+        self.replace_light_pipeline(ctx);
+        self.skybox_pipeline = Self::create_skybox_pipeline(
+            &ctx.device,
+            self.scene_format,
+            self.skybox.bind_group_layout(),
+            &self.skybox_shader,
+            self.sample_count,
+        );
+        self.grid_pipeline = Self::create_grid_pipeline(
+            &ctx.device,
+            self.scene_format,
+            &self.bind_group_layouts(),
+            &self.grid_shader,
+            self.sample_count,
+        );
+        self.translucent_pipeline = Self::create_translucent_pipeline(
+            &ctx.device,
+            self.scene_format,
+            &self.bind_group_layouts(),
+            &self.translucent_shader,
+            self.sample_count,
+        );
+        match Self::create_transparent_pipeline(
+            &ctx.device,
+            self.scene_format,
+            &self.bind_group_layouts(),
+            &self.shader,
+            self.sample_count,
+        ) {
+            Ok(pipeline) => self.transparent_pipeline = pipeline,
+            Err(error) => log::error!("Couldn't recreate the transparent pipeline: {error}"),
+        }
+        self.render_targets = Self::create_render_targets(
+            &ctx.device,
+            &ctx.config,
+            self.scene_format,
+            self.sample_count,
+        );
+        log::info!("Samples per pixel: {}", self.sample_count);
+    }
+
+    /// How much brighter the HDR scene is made before it's tonemapped, 1 without HDR
+    #[must_use]
+    pub fn exposure(&self) -> f32 {
+        self.hdr.as_ref().map_or(1.0, Hdr::exposure)
+    }
+
+    /// Makes the HDR scene darker or brighter before it's tonemapped, and logs the exposure.
+    /// Without HDR, the scene can't be brighter than the surface, so nothing changes.
+    pub fn set_exposure(&mut self, queue: &Queue, exposure: f32) {
+        if let Some(hdr) = &mut self.hdr {
+            hdr.set_exposure(queue, exposure);
+            log::info!("Exposure: {:.2}", hdr.exposure());
+        }
+    }
+
+    /// The threshold and intensity of the HDR bloom, or the defaults if the scene isn't HDR
+    #[must_use]
+    pub fn hdr_bloom_settings(&self) -> HdrBloomSettings {
+        self.hdr
+            .as_ref()
+            .map_or_else(HdrBloomSettings::default, Hdr::bloom_settings)
+    }
+
+    /// Changes how bright a pixel has to be to glow, and logs the clamped threshold.
+    /// It does nothing if the scene isn't HDR.
+    pub fn set_hdr_bloom_threshold(&mut self, queue: &Queue, threshold: f32) {
+        if let Some(hdr) = &mut self.hdr {
+            let settings = HdrBloomSettings {
+                threshold,
+                ..hdr.bloom_settings()
+            };
+            hdr.set_bloom_settings(queue, settings);
+            log::info!("HDR bloom threshold: {:.2}", hdr.bloom_settings().threshold);
+        }
+    }
+
+    /// Changes how much of the glow is added to the scene, and logs the clamped intensity.
+    /// It does nothing if the scene isn't HDR.
+    pub fn set_hdr_bloom_intensity(&mut self, queue: &Queue, intensity: f32) {
+        if let Some(hdr) = &mut self.hdr {
+            let settings = HdrBloomSettings {
+                intensity,
+                ..hdr.bloom_settings()
+            };
+            hdr.set_bloom_settings(queue, settings);
+            log::info!("HDR bloom intensity: {:.2}", hdr.bloom_settings().intensity);
+        }
+    }
+
+    /// Turns the HDR bloom on or off to compare the scene with and without it, and logs it.
+    /// It does nothing if the scene isn't HDR.
+    pub fn toggle_hdr_bloom(&mut self, queue: &Queue) {
+        if let Some(hdr) = &mut self.hdr {
+            hdr.set_bloom_enabled(queue, !hdr.bloom_enabled());
+            log::info!("HDR bloom: {}", hdr.bloom_enabled());
+        }
+    }
+
+    /// Switches between the Reinhard and the ACES tonemapper, if the scene is rendered in HDR
+    fn cycle_tonemapper(&mut self, queue: &Queue) {
+        if let Some(hdr) = &mut self.hdr {
+            hdr.set_tonemapper(queue, hdr.tonemapper().next());
+            log::info!("Tonemapper: {:?}", hdr.tonemapper());
+        }
+    }
+
+    /// Recreates every registered pipeline with the current shader and settings.
+    /// If the shader doesn't work with the new settings, the error is logged and the old
+    /// pipelines are kept.
+    fn replace_pipelines(&mut self, ctx: &Context) {
+        match self.recreate_pipelines(ctx, &self.shader) {
+            Ok(pipelines) => {
+                for (named, pipeline) in self.pipelines.iter_mut().zip(pipelines) {
+                    named.pipeline = pipeline;
+                }
+            }
+            Err(error) => log::error!("Couldn't recreate the pipelines: {error}"),
+        }
+
+        // The outline has to match the topology and sample count of the models
+        self.outline_pipeline = Self::create_outline_pipeline(
+            &ctx.device,
+            self.scene_format,
+            &self.bind_group_layouts(),
+            &self.outline_shader,
+            self.sample_count,
+            self.primitive(PolygonMode::Fill),
+        );
+    }
+
+    /// Recreates the pipeline of the light with the current settings, it's always filled
+    fn replace_light_pipeline(&mut self, ctx: &Context) {
+        self.light_pipeline = Self::create_light_pipeline(
+            &ctx.device,
+            self.scene_format,
+            &self.bind_group_layouts(),
+            &self.light_shader,
+            self.sample_count,
+            self.primitive(PolygonMode::Fill),
+        );
+    }
+
+    /// Switches to the next topology: triangles, a triangle strip, lines, a line strip, and points
+    fn cycle_topology(&mut self, ctx: &Context) {
+        let topology = match self.topology {
+            PrimitiveTopology::TriangleList => PrimitiveTopology::TriangleStrip,
+            PrimitiveTopology::TriangleStrip => PrimitiveTopology::LineList,
+            PrimitiveTopology::LineList => PrimitiveTopology::LineStrip,
+            PrimitiveTopology::LineStrip => PrimitiveTopology::PointList,
+            PrimitiveTopology::PointList => PrimitiveTopology::TriangleList,
+        };
+        self.set_topology(ctx, topology);
+        log::info!("Topology: {topology:?}");
+    }
+
+    /// Draws the models as points, lines, or triangles, the pipelines are recreated for it.
+    /// The pentagon gets indices that draw something visible with the topology.
+    pub fn set_topology(&mut self, ctx: &Context, topology: PrimitiveTopology) {
+        self.topology = topology;
+
+        // The indices are 16-bit, so the index format of the strips stays the same.
+        // The pentagon only has a handful of them.
+        let indices = model::shape_indices(topology);
+        self.index_buffer = Some(Self::create_index_buffer(&ctx.device, indices));
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.num_indices = indices.len() as u32;
+        }
+
+        self.replace_pipelines(ctx);
+        self.replace_light_pipeline(ctx);
+    }
+
+    /// Switches to culling the back faces, the front faces, or nothing, and logs which it is
+    fn cycle_cull_mode(&mut self, ctx: &Context) {
+        let cull_mode = match self.cull_mode {
+            Some(Face::Back) => Some(Face::Front),
+            Some(Face::Front) => None,
+            None => Some(Face::Back),
+        };
+        self.set_culling(ctx, cull_mode, self.front_face);
+        log::info!(
+            "Cull mode: {cull_mode:?}, front face: {:?}",
+            self.front_face
+        );
+    }
+
+    /// Changes which side of the triangles isn't drawn, and which winding order is the front.
+    /// Geometry with clockwise vertices disappears with the defaults, `FrontFace::Cw` or
+    /// disabling culling makes it visible. The pipelines are recreated for it.
+    pub fn set_culling(&mut self, ctx: &Context, cull_mode: Option<Face>, front_face: FrontFace) {
+        self.cull_mode = cull_mode;
+        self.front_face = front_face;
+        self.replace_pipelines(ctx);
+        self.replace_light_pipeline(ctx);
+    }
+
+    /// Switches between drawing filled triangles and only their edges.
+    /// Not every GPU can draw lines, then a warning is logged instead.
+    fn toggle_wireframe(&mut self, ctx: &Context) {
+        if !ctx.device.features().contains(Features::POLYGON_MODE_LINE) {
+            log::warn!("Wireframes aren't supported, the GPU can't draw polygons as lines");
+            return;
+        }
+
+        self.polygon_mode = if self.polygon_mode == PolygonMode::Fill {
+            PolygonMode::Line
+        } else {
+            PolygonMode::Fill
+        };
+        self.replace_pipelines(ctx);
+        log::info!("Polygon mode: {:?}", self.polygon_mode);
+    }
+
+    /// Reads the shader from disk again, and creates a pipeline for every fragment shader in it.
+    /// If the shader doesn't compile, the error is logged and the old pipelines are kept.
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    fn reload_shader(&mut self, ctx: &Context) {
+        // Catch the validation errors instead of letting wgpu panic on them.
+        // Hot reloading is only supported natively, so it's fine to block on the result.
+        let created =
+            pollster::block_on(shader::validate_creation(&ctx.device, SHADER_PATH, || {
+                let (shader, fragment_entry_points) =
+                    Self::create_shader(&ctx.device, Self::shader_source());
+                let pipelines = self.create_wgsl_pipelines(ctx, &shader, fragment_entry_points);
+                let transparent_pipeline = Self::create_transparent_pipeline(
+                    &ctx.device,
+                    self.scene_format,
+                    &self.bind_group_layouts(),
+                    &shader,
+                    self.sample_count,
+                );
+                (shader, pipelines, transparent_pipeline)
+            }))
+            .and_then(|(shader, pipelines, transparent_pipeline)| {
+                Ok((shader, pipelines?, transparent_pipeline?))
+            });
+        let (shader, pipelines, transparent_pipeline) = match created {
+            Ok(created) => created,
+            Err(error) => {
+                log::error!("Couldn't reload the shader, keeping the old one: {error}");
+                return;
+            }
+        };
+        if pipelines.is_empty() {
+            log::error!(
+                "Couldn't reload the shader, keeping the old one: it has no fragment shader"
+            );
+            return;
+        }
+
+        // Fragment shaders might have been added or removed, so the WGSL pipelines are replaced.
+        // The other pipelines don't use the shader, they're kept as they are.
+        let active = self.pipelines[self.active_pipeline].name.clone();
+        self.pipelines
+            .retain(|named| !matches!(named.shader, PipelineShader::Wgsl(_)));
+        self.pipelines.splice(0..0, pipelines);
+        if !self.set_active_pipeline(&active) {
+            self.active_pipeline = 0;
+        }
+        self.shader = shader;
+        self.transparent_pipeline = transparent_pipeline;
+        log::info!("Reloaded the shader");
+    }
+
+    /// Selects the pipeline registered under the given name to render with.
+    /// Returns whether a pipeline with that name exists.
+    pub fn set_active_pipeline(&mut self, name: &str) -> bool {
+        if let Some(index) = self.pipelines.iter().position(|named| named.name == name) {
+            self.active_pipeline = index;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Draws the first pipeline in the left half of the screen and the second in the right half,
+    /// or only the active pipeline on the whole screen
+    pub fn set_split_screen(&mut self, ctx: &Context, split_screen: bool) {
+        self.split_screen = split_screen;
+
+        // Every half is only half as wide, so the camera has to be narrower as well
+        self.camera.aspect = self.aspect_ratio(ctx.config.width, ctx.config.height);
+        self.write_camera_uniform(&ctx.queue);
+    }
+
+    /// Draws an outline around the models, or stops drawing it
+    pub fn set_outlines(&mut self, show_outlines: bool) {
+        self.show_outlines = show_outlines;
+        log::info!("Outlines: {show_outlines}");
+    }
+
+    /// Draws the grid on the ground and the axes, or stops drawing them
+    pub fn set_grid(&mut self, show_grid: bool) {
+        self.show_grid = show_grid;
+        log::info!("Grid: {show_grid}");
+    }
+
+    /// Draws the sky behind the scene instead of the background color, or stops drawing it
+    pub fn set_skybox(&mut self, show_skybox: bool) {
+        self.show_skybox = show_skybox;
+        log::info!("Skybox: {show_skybox}");
+    }
+
+    /// The name, backend, driver, and type of the GPU the demo renders with
+    #[must_use]
+    pub const fn adapter_info(&self) -> &AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// The name and version of the driver. OpenGL doesn't report them, they're unknown there.
+    fn driver_description(&self) -> String {
+        let info = &self.adapter_info;
+        let driver = format!("{} {}", info.driver, info.driver_info);
+        match driver.trim() {
+            "" => "unknown".to_owned(),
+            driver => driver.to_owned(),
+        }
+    }
+
+    /// Logs the GPU and how the surface is configured, to paste into bug reports
+    fn log_adapter_info(&self, ctx: &Context) {
+        let info = &self.adapter_info;
+        log::info!(
+            "Adapter: {} ({:?}, {:?}), driver: {}",
+            info.name,
+            info.backend,
+            info.device_type,
+            self.driver_description()
+        );
+        log::info!(
+            "Surface format: {:?}, present mode: {:?}",
+            ctx.config.format,
+            ctx.config.present_mode
+        );
+    }
+
+    /// Where the model is placed in the world
+    #[must_use]
+    pub const fn model_transform(&self) -> Transform {
+        self.model_transform
+    }
+
+    /// Places the model somewhere else in the world, and logs where.
+    /// The next update uploads it to the GPU.
+    pub fn set_model_transform(&mut self, transform: Transform) {
+        self.model_transform = transform;
+        self.model_transform_changed = true;
+        let position = transform.position;
+        log::info!(
+            "Model position: ({:.1}, {:.1}, {:.1}), rotation: {:.0} degrees, scale: {:.2}",
+            position.x,
+            position.y,
+            position.z,
+            transform.rotation.0,
+            transform.scale
+        );
+    }
+
+    /// The aspect ratio of a single view of the scene, when rendering to a target of the given size
+    fn aspect_ratio(&self, width: u32, height: u32) -> f32 {
+        if self.split_screen {
+            // A target that's a single pixel wide has nothing in the left half
+            camera::aspect_ratio((width / 2).max(1), height)
+        } else {
+            camera::aspect_ratio(width, height)
+        }
+    }
+
+    /// Doubles the values with the compute shader, and reads them back.
+    /// Returns `None` if the adapter doesn't support compute shaders.
+    /// This blocks until the GPU is done, so it's only supported natively.
+    ///
+    /// # Errors
+    /// Returns an error if there are more values than fit in a storage buffer binding,
+    /// or the results couldn't be read back
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_compute(
+        &self,
+        ctx: &Context,
+        values: &[f32],
+    ) -> Option<Result<Vec<f32>, ComputeError>> {
+        let kernel = self.compute.as_ref()?;
+        Some(kernel.run(&ctx.device, &ctx.queue, values))
+    }
+
+    /// Doubles the numbers up to [`COMPUTE_VALUE_COUNT`] with the compute shader,
+    /// and logs the sum of the results to compare with the expected sum
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(clippy::cast_precision_loss)]
+    fn log_compute_checksum(&self, ctx: &Context) {
+        let values: Vec<_> = (0..COMPUTE_VALUE_COUNT).map(|value| value as f32).collect();
+        match self.run_compute(ctx, &values) {
+            Some(Ok(doubled)) => {
+                let checksum: f32 = doubled.iter().sum();
+                let expected: f32 = values.iter().map(|value| value * 2.0).sum();
+                log::info!(
+                    "Doubled {} values with a compute shader, checksum {checksum} (expected {expected})",
+                    doubled.len()
+                );
+            }
+            Some(Err(error)) => log::warn!("Couldn't run the compute shader: {error}"),
+            None => log::warn!("The adapter doesn't support compute shaders"),
+        }
+    }
+
+    /// The current background color in linear space
+    #[must_use]
+    pub const fn background_color(&self) -> Color {
+        self.background_color
+    }
+
+    /// Sets the background to a color in linear space, and stops the hue animation
+    /// so the color isn't overwritten
+    pub const fn set_background_color(&mut self, color: Color) {
+        self.animate_background = false;
+        self.background_color = color;
+    }
+
+    /// How distant geometry fades into the background, `None` if it doesn't
+    #[must_use]
+    pub const fn fog(&self) -> Option<FogSettings> {
+        self.fog
+    }
+
+    /// Changes how distant geometry fades into the background color, or turns the fog off.
+    /// Densities and distances are clamped, and the next update uploads them to the GPU.
+    pub fn set_fog(&mut self, fog: Option<FogSettings>) {
+        self.fog = fog.map(FogSettings::clamped);
+        log::info!("Fog: {:?}", self.fog);
+    }
+
+    /// Makes the fog thinner or thicker, it's turned off when it gets to 0.
+    /// Linear fog is replaced by exponential fog, which only has a density.
+    fn adjust_fog_density(&mut self, step: f32) {
+        let density = match self.fog {
+            Some(FogSettings::Exponential { density }) => density,
+            _ => 0.0,
+        } + step;
+        self.set_fog((density > f32::EPSILON).then_some(FogSettings::Exponential { density }));
+    }
+
+    /// Replaces the model the instances are drawn with by a model from the resource directory.
+    /// `.gltf` and `.glb` files are loaded as glTF with the `gltf` feature, anything else as OBJ.
+    ///
+    /// # Errors
+    /// Returns an error if the model couldn't be loaded, then the current model is kept
+    pub async fn load_model(&mut self, ctx: &Context, file_name: &str) -> Result<(), LoadError> {
+        let Context { device, queue, .. } = ctx;
+        let (shared, layout) = (&self.shared_textures, &self.texture_bind_group_layout);
+        let model = match Path::new(file_name).extension().and_then(OsStr::to_str) {
+            #[cfg(feature = "gltf")]
+            Some("gltf" | "glb") => {
+                resources::load_gltf(file_name, device, queue, shared, layout).await?
+            }
+            _ => resources::load_model(file_name, device, queue, shared, layout).await?,
+        };
+        self.bounding_radius = model.bounding_radius();
+        self.obj_model = Some(model);
+
+        // Strips of the shape use 16-bit indices, the ones of models 32-bit
+        self.replace_pipelines(ctx);
+        self.replace_light_pipeline(ctx);
+        self.write_visible_instances(queue);
+        Ok(())
+    }
+
+    /// Sets the tint and scale the models are drawn with
+    pub const fn set_draw_constants(&mut self, draw_constants: DrawConstants) {
+        self.draw_constants = draw_constants;
+    }
+
+    /// Changes how much light of every light reaches the surfaces that face away from it.
+    /// Negative strengths are treated as 0.0.
+    pub fn set_ambient_strength(&mut self, strength: f32) {
+        for light in &mut self.lights {
+            light.ambient_strength = strength.max(0.0);
+        }
+    }
+
+    /// Changes how bright the reflection of every light is, 0.0 turns it off.
+    /// Negative strengths are treated as 0.0.
+    pub fn set_specular_strength(&mut self, strength: f32) {
+        for light in &mut self.lights {
+            light.specular_strength = strength.max(0.0);
+        }
+    }
+
+    /// Adds a point light that orbits around the origin like the others,
+    /// with the strengths of the first light. There can be at most [`MAX_LIGHTS`] lights.
+    /// Returns whether there was room for it.
+    pub fn add_light(&mut self, position: [f32; 3], color: [f32; 3]) -> bool {
+        if self.lights.len() >= MAX_LIGHTS {
+            return false;
+        }
+        let mut light = LightUniform::new(position, color);
+        if let Some(first) = self.lights.first() {
+            light.ambient_strength = first.ambient_strength;
+            light.specular_strength = first.specular_strength;
+        }
+        self.lights.push(light);
+        self.previous_lights.push(light);
+        true
+    }
+
+    /// Removes the light with the index, the lights after it move down one index.
+    /// Returns whether there was a light with that index.
+    pub fn remove_light(&mut self, index: usize) -> bool {
+        if index >= self.lights.len() {
+            return false;
+        }
+        self.lights.remove(index);
+        self.previous_lights.remove(index);
+        true
+    }
+
+    /// Adds a light where the cursor points, in the color the background shows for it.
+    /// It's placed where the ray through the cursor reaches the height of the first light,
+    /// or in the middle of the view if the cursor isn't in the window.
+    fn spawn_light_at_cursor(&mut self, ctx: &Context) {
+        let (x, y, color) = self
+            .cursor_position
+            .map_or((0.0, 0.0, [1.0; 3]), |position| {
+                let color = color::cursor_to_color(position, ctx.size);
+                let width = f64::from(ctx.size.width.max(1));
+                let height = f64::from(ctx.size.height.max(1));
+
+                // Normalized device coordinates point up, the cursor position points down
+                #[allow(clippy::cast_possible_truncation)]
+                (
+                    (position.x / width).mul_add(2.0, -1.0) as f32,
+                    (position.y / height).mul_add(-2.0, 1.0) as f32,
+                    [color.r as f32, color.g as f32, color.b as f32],
+                )
+            });
+
+        // Cursors above the horizon never reach the height, and neither do ones below it
+        // if the camera is below it, so those lights are placed along the ray
+        let (origin, direction) = self.camera.ray(x, y);
+        let distance = (LIGHT_SPAWN_HEIGHT - origin.y) / direction.y;
+        let distance = if distance.is_finite() && distance > 0.0 {
+            distance
+        } else {
+            LIGHT_SPAWN_DISTANCE
+        };
+        let position = origin + direction * distance;
+        if self.add_light(position.into(), color) {
+            log::info!("Lights: {}", self.lights.len());
+        } else {
+            log::warn!("There can't be more than {MAX_LIGHTS} lights");
+        }
+    }
+
+    /// Changes the keys that control the demo, [`InputMap::default`] has the standard controls
+    pub fn set_input_map(&mut self, input_map: InputMap) {
+        self.input_map = input_map;
+    }
+
+    /// Applies a post-processing effect to the rendered frames, or none to draw them directly
+    pub fn set_effect(&mut self, queue: &Queue, effect: Effect) {
+        self.post_process.set_effect(queue, effect);
+    }
+
+    /// Changes how much of the blend texture is mixed into the diffuse texture.
+    /// The factor is clamped between 0.0, only the diffuse texture, and 1.0, only the blend texture.
+    pub fn set_mix_factor(&mut self, queue: &Queue, factor: f32) {
+        let uniform = MixUniform::new(factor);
+        self.mix_factor = uniform.factor;
+        queue.write_buffer(&self.mix_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Changes how much of the scene glows with the bloom effect, and how far it spreads
+    pub fn set_bloom_settings(&mut self, queue: &Queue, settings: BloomSettings) {
+        self.post_process.set_bloom_settings(queue, settings);
+    }
+
+    /// Builds the window with the parameters that can be tweaked while the demo runs.
+    /// The sliders change the fields directly, they're uploaded to the GPU as usual.
+    #[cfg(feature = "gui")]
+    #[allow(clippy::cast_possible_truncation)]
+    fn build_gui(&mut self, ctx: &Context) {
+        let (Some(gui), Some(window)) = (&mut self.gui, ctx.window()) else {
+            return;
+        };
+
+        // egui edits colors in linear space as well
+        let mut background = [
+            self.background_color.r as f32,
+            self.background_color.g as f32,
+            self.background_color.b as f32,
+        ];
+        let mut background_changed = false;
+
+        // Every light has the same strengths, unless they were set through the uniforms
+        let (mut ambient, mut specular) = self.lights.first().map_or(
+            (
+                LightUniform::DEFAULT_AMBIENT_STRENGTH,
+                LightUniform::DEFAULT_SPECULAR_STRENGTH,
+            ),
+            |light| (light.ambient_strength, light.specular_strength),
+        );
+        let mut strengths_changed = false;
+        gui.run(window, |egui_ctx| {
+            egui::Window::new("Parameters").show(egui_ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Clear color");
+                    background_changed = ui.color_edit_button_rgb(&mut background).changed();
+                });
+                ui.add(
+                    egui::Slider::new(&mut self.camera_controller.speed, 0.0..=10.0)
+                        .text("Camera speed"),
+                );
+
+                // The lights keep orbiting from wherever they're moved to
+                for (index, light) in self.lights.iter_mut().enumerate() {
+                    ui.label(format!("Light {} position", index + 1));
+                    for (axis, value) in ["x", "y", "z"].into_iter().zip(&mut light.position) {
+                        ui.add(egui::Slider::new(value, -5.0..=5.0).text(axis));
+                    }
+                }
+                strengths_changed |= ui
+                    .add(egui::Slider::new(&mut ambient, 0.0..=1.0).text("Ambient strength"))
+                    .changed();
+                strengths_changed |= ui
+                    .add(egui::Slider::new(&mut specular, 0.0..=2.0).text("Specular strength"))
+                    .changed();
+            });
+
+            // The present mode can be changed with V, so it's read every frame
+            let info = &self.adapter_info;
+            let driver = self.driver_description();
+            egui::Window::new("Adapter").show(egui_ctx, |ui| {
+                ui.label(format!("Name: {}", info.name));
+                ui.label(format!("Backend: {:?}", info.backend));
+                ui.label(format!("Device type: {:?}", info.device_type));
+                ui.label(format!("Driver: {driver}"));
+                ui.label(format!("Surface format: {:?}", ctx.config.format));
+                ui.label(format!("Present mode: {:?}", ctx.config.present_mode));
+            });
+        });
+
+        // Picking a color stops the animation, so only set it when it was changed
+        if background_changed {
+            self.set_background_color(Color {
+                r: f64::from(background[0]),
+                g: f64::from(background[1]),
+                b: f64::from(background[2]),
+                a: 1.0,
+            });
+        }
+        if strengths_changed {
+            self.set_ambient_strength(ambient);
+            self.set_specular_strength(specular);
+        }
+    }
 
-    /// The window must be declared after the surface so
-    /// it gets dropped after after it as the surface contains
-    /// unsafe references to the window's resources.
-    window: Window,
+    /// Changes the threshold and strength of the bloom by the given amounts, and logs them
+    fn adjust_bloom(&mut self, queue: &Queue, threshold: f32, strength: f32) {
+        let settings = self.post_process.bloom_settings();
+        self.set_bloom_settings(
+            queue,
+            BloomSettings {
+                threshold: settings.threshold + threshold,
+                strength: settings.strength + strength,
+            },
+        );
+        let settings = self.post_process.bloom_settings();
+        log::info!(
+            "Bloom threshold: {:.2}, strength: {:.2}",
+            settings.threshold,
+            settings.strength
+        );
+    }
 
-    background_color: Color,
-    render_pipeline: RenderPipeline,
-    second_pipeline: RenderPipeline,
-}
+    /// Shows the resolution and frame statistics after the window title once per second,
+    /// and logs how many instances were culled
+    fn update_window_title(&mut self, ctx: &Context) {
+        if self.last_title_update.elapsed() >= TITLE_UPDATE_INTERVAL {
+            log::debug!(
+                "Culled {} of {} instances",
+                self.instances.len() - self.visible_instances as usize,
+                self.instances.len()
+            );
+            self.refresh_window_title(ctx);
+        }
+    }
 
-impl State {
-    async fn create_adapter(instance: &Instance, surface: &Surface) -> Adapter {
-        // Create an adapter to interact directly with the GPU
-        // You can also use enumerate_adapters to iterate through possible adapters
-        instance
-            .request_adapter(&RequestAdapterOptions {
-                // LowPower is favored when there is no HighPerformance option
-                power_preference: wgpu::PowerPreference::default(),
-
-                // The adapter should be compatible with the selected surface
-                compatible_surface: Some(surface),
-
-                // Don't force an adapter, the application won't run without compatible hardware
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap()
+    /// Shows the current resolution and frame statistics after the window title right away.
+    /// On the web the canvas has no title bar, so nothing is updated there.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn refresh_window_title(&mut self, ctx: &Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(window) = ctx.window() {
+            window.set_title(&format!(
+                "{} — {}x{} — {}",
+                self.window_title,
+                ctx.config.width,
+                ctx.config.height,
+                ctx.frame_stats()
+            ));
+        }
+        self.last_title_update = Instant::now();
     }
 
-    async fn request_device(adapter: &Adapter) -> (Device, Queue) {
-        adapter
-            .request_device(
-                &DeviceDescriptor {
-                    label: None,
+    /// Runs the action of a pressed key, returns whether the action is supported
+    #[allow(clippy::too_many_lines)]
+    fn run_action(&mut self, ctx: &mut Context, action: Action) -> bool {
+        // Check what the key is bound to
+        match action {
+            // Make the background blue
+            Action::SetBlue => {
+                // Picking a color stops the animation, so it isn't overwritten
+                self.set_background_color(Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 1.0,
+                    a: 1.0,
+                });
+            }
 
-                    // Extra features
-                    features: Features::empty(),
+            // Make the background green
+            Action::SetGreen => {
+                self.set_background_color(Color {
+                    r: 0.0,
+                    g: 1.0,
+                    b: 0.0,
+                    a: 1.0,
+                });
+            }
 
-                    // WebGL doesn't support all of wgpu's features, so if
-                    // we're building for the web, we'll have to disable some.
-                    limits: if cfg!(target_arch = "wasm32") {
-                        Limits::downlevel_webgl2_defaults()
-                    } else {
-                        Limits::default()
-                    },
-                },
-                None,
-            )
-            .await
-            .unwrap()
-    }
+            // Make the background red
+            Action::SetRed => {
+                self.set_background_color(Color {
+                    r: 1.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                });
+            }
 
-    fn create_pipeline(
-        device: &Device,
-        config: &SurfaceConfiguration,
-        fragment_entry_point: &str,
-    ) -> RenderPipeline {
-        // Read the shader.
-        // Can also be done with:
-        //let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
-        let shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+            // Switch to the next present mode to toggle VSync
+            Action::CyclePresentMode => ctx.cycle_present_mode(),
 
-        // Create a layout for the pipeline
-        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[],
-            push_constant_ranges: &[],
-        });
+            // Switch between a window and borderless fullscreen
+            Action::ToggleFullscreen => ctx.toggle_fullscreen(),
 
-        device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                // The function in the shader that should be the entry point.
-                // In this case for the vertex shader.
-                entry_point: "vs_main",
-
-                // The types of vertices to pass to the vertex shader
-                buffers: &[],
+            // Save the current frame as a PNG file
+            Action::Screenshot => self.save_screenshot(ctx),
+
+            // Save the albedo and normals of the G-buffer as PNG files
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::CaptureGBuffer => match self.capture_gbuffer(ctx) {
+                Ok([albedo, normal]) => log::info!(
+                    "Saved the G-buffer to {} and {}",
+                    albedo.display(),
+                    normal.display()
+                ),
+                Err(error) => log::warn!("Couldn't save the G-buffer: {error}"),
             },
 
-            // The fragment state is optional, but here it's needed to store color data
-            // to the surface
-            fragment: Some(FragmentState {
-                module: &shader,
-                entry_point: fragment_entry_point,
+            // Reset the background to the color the demo started with
+            Action::ResetBackground => {
+                self.set_background_color(self.initial_background_color);
+            }
 
-                // The color outputs to set up
-                targets: &[Some(ColorTargetState {
-                    // Using the surface's format makes copying to it easy
-                    format: config.format,
+            // Start or stop cycling the background through all hues
+            Action::ToggleBackgroundAnimation => {
+                self.animate_background = !self.animate_background;
+            }
 
-                    // Blending should replace the old data with the new data
-                    blend: Some(BlendState::REPLACE),
+            // Reload the shader from disk
+            #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+            Action::ReloadShader => self.reload_shader(ctx),
 
-                    // Write to all colors
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
+            // Turn multisampling on or off
+            Action::ToggleMsaa => self.toggle_msaa(ctx),
 
-            // How to interpret vertices when converting them into triangles
-            primitive: PrimitiveState {
-                // `PrimitiveTopology::TriangleList` means that every 3 vertices will correspond
-                // to 1 triangle.
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
+            // Switch between filled triangles and a wireframe
+            Action::ToggleWireframe => self.toggle_wireframe(ctx),
 
-                // Determine whether a triangle is facing forward.
-                // With `FrontFace::Ccw`, a triangle is facing forward if the vertices are in
-                // counter-clockwise direction.
-                // Other triangles are culled as specified by `Face::Back`.
-                front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
+            // Draw the models as triangles, lines, or points
+            Action::CycleTopology => self.cycle_topology(ctx),
 
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: PolygonMode::Fill,
+            // Apply the next post-processing effect
+            Action::NextEffect => {
+                let effect = self.post_process.effect().next();
+                self.set_effect(&ctx.queue, effect);
+                log::info!("Post-processing effect: {effect:?}");
+            }
 
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
+            // Lower or raise the brightness the bloom starts at
+            Action::LowerBloomThreshold => self.adjust_bloom(&ctx.queue, -0.05, 0.0),
+            Action::RaiseBloomThreshold => self.adjust_bloom(&ctx.queue, 0.05, 0.0),
+
+            // Spread the glow of the bloom less or further
+            Action::LowerBloomStrength => self.adjust_bloom(&ctx.queue, 0.0, -0.25),
+            Action::RaiseBloomStrength => self.adjust_bloom(&ctx.queue, 0.0, 0.25),
+            Action::LowerExposure => self.set_exposure(&ctx.queue, self.exposure() / EXPOSURE_STEP),
+            Action::RaiseExposure => self.set_exposure(&ctx.queue, self.exposure() * EXPOSURE_STEP),
+            Action::CycleTonemapper => self.cycle_tonemapper(&ctx.queue),
+            Action::ToggleHdrBloom => self.toggle_hdr_bloom(&ctx.queue),
+
+            // Switch split-screen rendering on or off
+            Action::ToggleSplitScreen => {
+                self.set_split_screen(ctx, !self.split_screen);
+                log::info!("Split screen: {}", self.split_screen);
+            }
+            Action::ToggleOutlines => self.set_outlines(!self.show_outlines),
+            Action::ToggleGrid => self.set_grid(!self.show_grid),
+            Action::ToggleSkybox => self.set_skybox(!self.show_skybox),
+            Action::TransformModel(step) => {
+                self.set_model_transform(self.model_transform.step(step));
+            }
 
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
+            // Switch between a perspective and an orthographic camera
+            Action::ToggleProjection => {
+                self.camera.toggle_projection();
+                self.write_camera_uniform(&ctx.queue);
+                log::info!("Projection: {:?}", self.camera.projection);
+            }
 
-            // The depth.stencil buffer isn't used
-            depth_stencil: None,
+            // Cull the back faces, the front faces, or nothing
+            Action::CycleCullMode => self.cycle_cull_mode(ctx),
 
-            multisample: MultisampleState {
-                // The number of samples the pipeline uses
-                count: 1,
+            // Show more of the diffuse texture or of the blend texture
+            Action::LowerMixFactor => {
+                self.set_mix_factor(&ctx.queue, self.mix_factor - MIX_FACTOR_STEP);
+                log::info!("Mix factor: {:.1}", self.mix_factor);
+            }
+            Action::RaiseMixFactor => {
+                self.set_mix_factor(&ctx.queue, self.mix_factor + MIX_FACTOR_STEP);
+                log::info!("Mix factor: {:.1}", self.mix_factor);
+            }
 
-                // Which samples should be active (all of them)
-                mask: !0,
+            // Make the fog thinner or thicker
+            Action::LowerFogDensity => self.adjust_fog_density(-FOG_DENSITY_STEP),
+            Action::RaiseFogDensity => self.adjust_fog_density(FOG_DENSITY_STEP),
 
-                // No anti aliasing is used
-                alpha_to_coverage_enabled: false,
-            },
+            // Double a list of numbers with a compute shader before the next frame
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::RunCompute => self.compute_requested = true,
 
-            // Number of array layers the render attachments can have
-            multiview: None,
-        })
-    }
+            // Switch between the WGSL and the GLSL version of the texture shader
+            #[cfg(feature = "glsl")]
+            Action::ToggleGlsl => self.toggle_glsl(),
 
-    /// Creating some of the wgpu types requires async code
-    ///
-    /// # Panics
-    /// Panics if no surface, adapter, device, or texture format could be created
-    pub async fn new(window: Window) -> Self {
-        let size = window.inner_size();
-
-        // The instance is a handle to our GPU
-        // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = Instance::new(InstanceDescriptor {
-            backends: Backends::all(),
-            ..Default::default()
-        });
+            // Grab or release the cursor to look around with the mouse
+            Action::ToggleMouseLook => {
+                if let Some(window) = ctx.window() {
+                    self.toggle_mouse_look(window);
+                }
+            }
+
+            // Switch to the next render pipeline
+            Action::SwapPipeline => {
+                if let Some(next) = (self.active_pipeline + 1).checked_rem(self.pipelines.len()) {
+                    self.active_pipeline = next;
+                }
+            }
+
+            // Add a light where the cursor points, or remove the newest one
+            Action::SpawnLight => self.spawn_light_at_cursor(ctx),
+            Action::RemoveLight => {
+                self.remove_light(self.lights.len().saturating_sub(1));
+            }
 
-        // # Safety
-        //
-        // The surface needs to live as long as the window that created it.
-        // State owns the window, so this should be safe.
-        // The surface is the part of the window we draw to.
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
+            // Switch to the pipeline with that index
+            Action::SelectPipeline(index) => {
+                if index < self.pipelines.len() {
+                    self.active_pipeline = index;
+                }
+            }
 
-        // Create an adapter
-        let adapter = Self::create_adapter(&instance, &surface).await;
+            // Movement is handled while the key is held down, and actions of features
+            // that aren't compiled in do nothing
+            _ => return false,
+        }
+        true
+    }
 
-        let (device, queue) = Self::request_device(&adapter).await;
+    /// Uploads the current view projection matrix of the camera to the GPU
+    fn write_camera_uniform(&mut self, queue: &Queue) {
+        self.camera_uniform.update_view_proj(&self.camera);
+        queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+        self.skybox.write_camera(queue, &self.camera);
+
+        // Which instances are visible, and the order of the quads depend on the camera
+        self.write_visible_instances(queue);
+        self.write_quad_instances(queue);
+    }
 
-        // Retrieve the capabilities of the surface
-        let surface_caps = surface.get_capabilities(&adapter);
+    /// Sorts the quads from the furthest to the closest to the camera, and uploads them.
+    /// Blending only works if everything behind a transparent surface is drawn before it.
+    fn write_quad_instances(&mut self, queue: &Queue) {
+        let eye = self.camera.eye;
+        self.quad_instances.sort_by(|a, b| {
+            let distance =
+                |instance: &instance::Instance| eye.distance2(Point3::from_vec(instance.position));
+            distance(b).total_cmp(&distance(a))
+        });
+        instance::write_instance_buffer(queue, &self.quad_instance_buffer, &self.quad_instances);
+    }
 
-        // Shader code in this tutorial assumes an sRGB surface texture. Using a different
-        // one will result in all the colors coming out darker. If you want to support non
-        // sRGB surfaces, you'll need to account for that when drawing to the frame.
-        let surface_format = surface_caps
-            .formats
+    /// Uploads the instances the camera can see to the start of the instance buffer, and counts
+    /// them. The draws only use that part of the buffer, so the GPU skips the other instances.
+    fn write_visible_instances(&mut self, queue: &Queue) {
+        let frustum = Frustum::from_matrix(self.camera.build_view_projection_matrix());
+
+        // The draw constants scale the models around their origin, and the model transform
+        // places all instances together
+        let model_matrix = self.model_transform.matrix();
+        let radius =
+            self.bounding_radius * self.draw_constants.scale.abs() * self.model_transform.scale;
+        let visible = self
+            .instances
             .iter()
+            .filter(|instance| {
+                let center = model_matrix.transform_point(Point3::from_vec(instance.position));
+                frustum.intersects_sphere(center, radius)
+            })
             .copied()
-            .find(TextureFormat::is_srgb)
-            .unwrap_or(surface_caps.formats[0]);
-
-        // Create a configuration for the surface.
-        // This will define how the surface creates its underlying surface textures.
-        let config = SurfaceConfiguration {
-            // How surface textures will be used, in this case to write to the screen.
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            .collect::<Vec<_>>();
+        instance::write_instance_buffer(queue, &self.instance_buffer, &visible);
+        self.visible_instances = u32::try_from(visible.len()).unwrap();
+    }
 
-            // How surface textures will be stored on the GPU.
-            format: surface_format,
+    /// Uploads the lights to the GPU. With a fixed timestep, frames fall between two updates.
+    /// Drawing the lights part of the way between them keeps them moving smoothly,
+    /// even if there are fewer updates than frames.
+    fn write_light_uniform(&self, ctx: &Context) {
+        let alpha = ctx.interpolation_alpha();
+        let lights = self
+            .previous_lights
+            .iter()
+            .zip(&self.lights)
+            .map(|(previous, light)| previous.lerp(light, alpha))
+            .collect::<Vec<_>>();
+        ctx.queue
+            .write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&lights));
+        ctx.queue.write_buffer(
+            &self.light_count_buffer,
+            0,
+            bytemuck::cast_slice(&[LightCount::new(lights.len())]),
+        );
+    }
 
-            // The dimensions of the surface texture in pixels, should always be larger than 0.
-            width: size.width,
-            height: size.height,
+    /// Grabs and hides the cursor to look around with the mouse, or releases it again
+    fn toggle_mouse_look(&mut self, window: &Window) {
+        self.mouse_look = !self.mouse_look;
+        if self.mouse_look {
+            // Not every platform supports every grab mode, so try both
+            if let Err(error) = window
+                .set_cursor_grab(CursorGrabMode::Confined)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+            {
+                log::warn!("Couldn't grab the cursor: {error}");
+            }
+        } else if let Err(error) = window.set_cursor_grab(CursorGrabMode::None) {
+            log::warn!("Couldn't release the cursor: {error}");
+        }
+        window.set_cursor_visible(!self.mouse_look);
+    }
 
-            // How to sync the surface with the display, we select the first option for simplicity.
-            // PresentMode::Fifo will cap the display rate at the display's framerate (like VSync).
-            // PresentMode::Fifo is supported on all platforms.
-            // PresentMode::AutoVsync and PresentMode::AutoNoVsync have fallback support to work
-            // on all platforms.
-            // PresentMode can also be selected at run-time with surface_caps.present_modes.
-            present_mode: surface_caps.present_modes[0],
+    /// Renders a frame with the active pipeline to an off-screen texture of the given size,
+    /// and returns its pixels as RGBA bytes. Use [`capture::save_png`] to store them.
+    /// This blocks until the GPU is done, so it's only supported natively.
+    ///
+    /// # Errors
+    /// Returns an error if the surface format can't be converted to RGBA,
+    /// or the pixels couldn't be read back
+    pub fn render_to_texture(
+        &mut self,
+        ctx: &Context,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, CaptureError> {
+        let texture = self.render_offscreen(ctx, width, height);
+        capture::read_texture(&ctx.device, &ctx.queue, &texture)
+    }
 
-            // How the alpha modes will be handled during compositing.
-            alpha_mode: surface_caps.alpha_modes[0],
+    /// Like [`Self::render_to_texture`], but waits for the GPU without blocking,
+    /// so it works on the web as well, like for thumbnails
+    ///
+    /// # Errors
+    /// Returns an error if the surface format can't be converted to RGBA,
+    /// or the pixels couldn't be read back
+    pub async fn render_to_texture_async(
+        &mut self,
+        ctx: &Context,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, CaptureError> {
+        let texture = self.render_offscreen(ctx, width, height);
+        capture::read_texture_async(&ctx.device, &ctx.queue, &texture).await
+    }
 
-            // List of TextureFormats that can be used to create TextureViews
-            view_formats: vec![],
+    /// Submits a frame rendered to a new texture of the given size, independent of the window.
+    /// The texture can be copied to a buffer to read it back.
+    fn render_offscreen(&mut self, ctx: &Context, width: u32, height: u32) -> wgpu::Texture {
+        // The last pass renders to the surface format, so the texture has to use it as well
+        let config = SurfaceConfiguration {
+            width,
+            height,
+            ..ctx.config.clone()
         };
+        let texture = ctx.device.create_texture(&TextureDescriptor {
+            label: Some("Off-screen Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: config.format,
+
+            // COPY_SRC allows the rendered pixels to be copied to a buffer
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let targets =
+            Self::create_render_targets(&ctx.device, &config, self.scene_format, self.sample_count);
+        let hdr_target = self
+            .hdr
+            .as_ref()
+            .map(|hdr| hdr.create_target(&ctx.device, &config));
+        let post_target = self
+            .post_process
+            .is_active()
+            .then(|| self.post_process.create_target(&ctx.device, &config));
+
+        // Render with the aspect ratio of the texture, and restore the camera afterwards
+        let aspect = self.camera.aspect;
+        self.camera.aspect = self.aspect_ratio(width, height);
+        self.write_camera_uniform(&ctx.queue);
+        self.write_light_uniform(ctx);
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Off-screen Encoder"),
+            });
+        self.render_frame(
+            &mut encoder,
+            &view,
+            &targets,
+            hdr_target.as_ref(),
+            post_target.as_ref(),
+        );
+        ctx.queue.submit(std::iter::once(encoder.finish()));
+
+        self.camera.aspect = aspect;
+        self.write_camera_uniform(&ctx.queue);
+        texture
+    }
 
-        // Apply the configurations
-        surface.configure(&device, &config);
+    /// Renders the current frame with the size of the surface, and returns it as RGBA bytes.
+    /// This works with a headless context as well, to render without a window.
+    ///
+    /// # Errors
+    /// Returns an error if the format can't be converted to RGBA, or the pixels couldn't be
+    /// read back
+    pub fn render_to_vec(&mut self, ctx: &Context) -> Result<Vec<u8>, CaptureError> {
+        self.render_to_texture(ctx, ctx.config.width, ctx.config.height)
+    }
 
-        let render_pipeline = Self::create_pipeline(&device, &config, "fs_main");
-        let second_pipeline = Self::create_pipeline(&device, &config, "fs_main2");
+    /// Renders the current frame with the size of the surface, and saves it as a PNG file in
+    /// the working directory. Returns the path of the file.
+    ///
+    /// # Errors
+    /// Returns an error if the frame couldn't be read back, or the file couldn't be written
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn capture_frame(&mut self, ctx: &Context) -> Result<PathBuf, CaptureError> {
+        let rgba = self.render_to_vec(ctx)?;
+
+        // Name the file after the time, so screenshots don't overwrite each other
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = PathBuf::from(format!("screenshot-{timestamp}.png"));
+        capture::save_png(&path, ctx.config.width, ctx.config.height, rgba)?;
+        Ok(path)
+    }
 
-        Self {
-            surface,
+    /// Creates a pipeline that writes the albedo and normals of the models to a G-buffer.
+    /// It's only used for captures, so it's created when it's needed, always with the current
+    /// shader and polygon mode. The G-buffer isn't multisampled.
+    ///
+    /// # Errors
+    /// Returns an error if wgpu rejected the pipeline, like when the shader has no G-buffer shader
+    fn create_gbuffer_pipeline(&self, device: &Device) -> Result<RenderPipeline, PipelineError> {
+        Self::create_pipeline(
             device,
-            queue,
-            config,
-            size,
-            window,
-            background_color: Color {
-                r: 0.1,
-                g: 0.2,
-                b: 0.3,
-                a: 1.0,
-            },
-            render_pipeline,
-            second_pipeline,
-        }
+            &self.pipeline_layout(device),
+            ShaderDesc::new(&self.shader, entry_points::VERTEX, entry_points::GBUFFER),
+            &GBuffer::color_targets(),
+            &[Vertex::desc(), InstanceRaw::desc()],
+            1,
+            self.primitive(self.polygon_mode),
+        )
     }
 
-    pub const fn window(&self) -> &Window {
-        &self.window
-    }
+    /// Renders the models to a G-buffer of the given size, and returns the albedo and normals
+    /// as RGBA bytes. The light isn't drawn, it only lights the surfaces in a later pass.
+    /// This blocks until the GPU is done, so it's only supported natively.
+    ///
+    /// # Errors
+    /// Returns an error if the shader has no G-buffer shader, or either texture couldn't be
+    /// read back
+    pub fn render_gbuffer(
+        &mut self,
+        ctx: &Context,
+        width: u32,
+        height: u32,
+    ) -> Result<(Vec<u8>, Vec<u8>), CaptureError> {
+        let config = SurfaceConfiguration {
+            width,
+            height,
+            ..ctx.config.clone()
+        };
+        let gbuffer = GBuffer::new(&ctx.device, &config);
+        let pipeline = self.create_gbuffer_pipeline(&ctx.device)?;
 
-    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            // Store the new size
-            self.size = new_size;
-            self.config.width = new_size.width;
+        // Render with the aspect ratio of the textures, and restore the camera afterwards
+        let aspect = self.camera.aspect;
+        self.camera.aspect = camera::aspect_ratio(width, height);
+        self.write_camera_uniform(&ctx.queue);
 
-            // Reconfigure the surface for the new size
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("G-buffer Encoder"),
+            });
+        {
+            // Both textures are written in the same pass, every target gets its own output
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("G-buffer Pass"),
+                color_attachments: &gbuffer.color_attachments(),
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &gbuffer.depth.view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.draw_models(&mut render_pass, &pipeline);
         }
+        ctx.queue.submit(std::iter::once(encoder.finish()));
+
+        self.camera.aspect = aspect;
+        self.write_camera_uniform(&ctx.queue);
+
+        Ok((
+            capture::read_texture(&ctx.device, &ctx.queue, &gbuffer.albedo.texture)?,
+            capture::read_texture(&ctx.device, &ctx.queue, &gbuffer.normal.texture)?,
+        ))
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            // Keyboard input received
-            WindowEvent::KeyboardInput { input, .. } => {
-                // If the user pressed a key
-                if let Some(key) = input
-                    .virtual_keycode
-                    .filter(|_| input.state == ElementState::Pressed)
-                {
-                    // Check what key the user pressed
-                    match key {
-                        // If it is B, make the background blue
-                        winit::event::VirtualKeyCode::B => {
-                            self.background_color = Color {
-                                r: 0.0,
-                                g: 0.0,
-                                b: 1.0,
-                                a: 1.0,
-                            }
-                        }
-
-                        // If it is G, make the background green
-                        winit::event::VirtualKeyCode::G => {
-                            self.background_color = Color {
-                                r: 0.0,
-                                g: 1.0,
-                                b: 0.0,
-                                a: 1.0,
-                            }
-                        }
-
-                        // If it is R, make the background red
-                        winit::event::VirtualKeyCode::R => {
-                            self.background_color = Color {
-                                r: 1.0,
-                                g: 0.0,
-                                b: 0.0,
-                                a: 1.0,
-                            }
-                        }
-
-                        // If it is space, switch the render pipelines
-                        winit::event::VirtualKeyCode::Space => {
-                            core::mem::swap(&mut self.render_pipeline, &mut self.second_pipeline);
-                        }
-                        _ => return false,
-                    }
-                }
-            }
+    /// Renders the G-buffer with the size of the surface, and saves the albedo and normals as
+    /// PNG files in the working directory. Returns the paths of the files.
+    ///
+    /// # Errors
+    /// Returns an error if the G-buffer couldn't be read back, or a file couldn't be written
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn capture_gbuffer(&mut self, ctx: &Context) -> Result<[PathBuf; 2], CaptureError> {
+        let textures: [Vec<u8>; 2] = self
+            .render_gbuffer(ctx, ctx.config.width, ctx.config.height)?
+            .into();
+
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let paths = [
+            PathBuf::from(format!("gbuffer-albedo-{timestamp}.png")),
+            PathBuf::from(format!("gbuffer-normal-{timestamp}.png")),
+        ];
+        for (path, rgba) in paths.iter().zip(textures) {
+            capture::save_png(path, ctx.config.width, ctx.config.height, rgba)?;
+        }
+        Ok(paths)
+    }
 
-            // If the cursor moved
-            WindowEvent::CursorMoved { position, .. } => {
-                // Calculate the normalized x and y positions
-                let x = position.x / f64::from(self.size.width);
-                let y = position.y / f64::from(self.size.height);
-
-                // If they are between 0 and 1, calculate and set the new background colors
-                if (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y) {
-                    self.background_color = Color {
-                        r: x,
-                        g: y,
-                        b: 1.0 - (x + y) / 2.0,
-                        a: 1.0,
-                    };
-                }
-            }
+    /// Saves a screenshot, natively as a file, and on the web as a download of the canvas
+    fn save_screenshot(&mut self, ctx: &Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        match self.capture_frame(ctx) {
+            Ok(path) => log::info!("Saved a screenshot to {}", path.display()),
+            Err(error) => log::warn!("Couldn't save a screenshot: {error}"),
+        }
 
-            // If the cursor left the screen, make the background black
-            WindowEvent::CursorLeft { .. } => {
-                self.background_color = Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 1.0,
-                }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(window) = ctx.window() {
+            if let Err(error) = capture::download_canvas(window, "screenshot.png") {
+                log::warn!("Couldn't save a screenshot: {error:?}");
             }
-            _ => return false,
         }
-        true
     }
 
-    pub fn update(&mut self) {}
+    /// Renders the scene with the active pipeline, or with the first two pipelines side by side
+    /// in split-screen mode. With HDR, the scene is rendered to the HDR target first, and then
+    /// tonemapped. With a post-processing effect, that's drawn to the post-processing target,
+    /// which is then drawn to the view with the effect applied.
+    fn render_frame(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        targets: &RenderTargets,
+        hdr_target: Option<&HdrTarget>,
+        post_target: Option<&PostTarget>,
+    ) {
+        // With a single pipeline, both halves show the same one.
+        // Without any, only the background is drawn.
+        let pipeline = |index: usize| {
+            let index = index.checked_rem(self.pipelines.len())?;
+            Some(&self.pipelines[index].pipeline)
+        };
+        let draws: Vec<_> = if self.split_screen {
+            let size = targets.depth_texture.texture.size();
+            let [left, right] = Viewport::split_horizontally(size.width, size.height);
+            [(0, left), (1, right)]
+                .into_iter()
+                .filter_map(|(index, viewport)| Some((pipeline(index)?, Some(viewport))))
+                .collect()
+        } else {
+            pipeline(self.active_pipeline)
+                .map(|pipeline| (pipeline, None))
+                .into_iter()
+                .collect()
+        };
+
+        // The effect is applied to the tonemapped scene, which has the format of the surface
+        let output = post_target.map_or(view, PostTarget::view);
+        if let (Some(hdr), Some(hdr_target)) = (&self.hdr, hdr_target) {
+            self.render_with_pipeline(encoder, hdr_target.view(), targets, &draws);
+            hdr.render(encoder, hdr_target, output);
+        } else {
+            self.render_with_pipeline(encoder, output, targets, &draws);
+        }
+        if let Some(post_target) = post_target {
+            self.post_process.render(encoder, post_target, view);
+        }
+    }
 
-    fn render_with_pipeline(&self, encoder: &mut CommandEncoder, view: &TextureView) {
+    /// Clears the view, and draws the scene once for every pipeline. Every draw is limited to its
+    /// viewport, or covers the whole view without one. Viewports are kept inside the view.
+    fn render_with_pipeline(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        targets: &RenderTargets,
+        draws: &[(&RenderPipeline, Option<Viewport>)],
+    ) {
         // Clear the screen
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("Render pass"),
 
             // Where we are going to draw our color
             color_attachments: &[Some(RenderPassColorAttachment {
-                // The texture to save the colors to
-                view,
+                // The texture to save the colors to.
+                // With multisampling, that's the multisampled texture.
+                view: targets.msaa_view.as_ref().unwrap_or(view),
 
                 // The texture that will receive the resolved output.
-                // This will be the same as view unless multisampling is enabled.
-                resolve_target: None,
+                // With multisampling, the samples are averaged into the surface texture.
+                resolve_target: targets.msaa_view.as_ref().map(|_| view),
 
                 // What to do with the colors on the screen
                 ops: Operations {
-                    // How to handle colors from the previous frame
-                    load: LoadOp::Clear(self.background_color),
+                    // How to handle colors from the previous frame.
+                    // The clear color isn't converted by a non-sRGB surface, so do it here.
+                    load: LoadOp::Clear(if self.is_srgb {
+                        self.background_color
+                    } else {
+                        color::color_to_srgb(self.background_color)
+                    }),
 
                     // Whether we want to store the renderedd results to the texture
                     store: StoreOp::Store,
                 },
             })],
-            depth_stencil_attachment: None,
+
+            // Where we are going to store the depth of the pixels
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &targets.depth_texture.view,
+
+                // Clear the depth to the furthest distance at the start of every frame
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(1.0),
+                    store: StoreOp::Store,
+                }),
+
+                // Clear the stencil, so only the models drawn this frame are marked
+                stencil_ops: Some(Operations {
+                    load: LoadOp::Clear(0),
+                    store: StoreOp::Store,
+                }),
+            }),
             occlusion_query_set: None,
             timestamp_writes: None,
         });
 
+        // The render targets have the size of the view
+        let size = targets.depth_texture.texture.size();
+        for &(pipeline, viewport) in draws {
+            let viewport = viewport
+                .unwrap_or_else(|| Viewport::full(size.width, size.height))
+                .clamped(size.width, size.height);
+            if viewport.is_empty() {
+                continue;
+            }
+
+            // Map the scene to the viewport, and cut off everything outside of it
+            render_pass.set_viewport(
+                viewport.x,
+                viewport.y,
+                viewport.width,
+                viewport.height,
+                viewport.min_depth,
+                viewport.max_depth,
+            );
+            let [x, y, width, height] = viewport.scissor_rect();
+            render_pass.set_scissor_rect(x, y, width, height);
+
+            self.draw_models(&mut render_pass, pipeline);
+            if self.show_outlines {
+                self.draw_outlines(&mut render_pass);
+            }
+            if self.show_grid {
+                render_pass.set_pipeline(&self.grid_pipeline);
+                self.grid.draw(&mut render_pass);
+            }
+            self.draw_light(&mut render_pass);
+
+            // The sky only covers the pixels nothing opaque was drawn to. Its bind group replaces
+            // the ones of the models, the transparent meshes bind those again.
+            if self.show_skybox {
+                render_pass.set_pipeline(&self.skybox_pipeline);
+                self.skybox.draw(&mut render_pass);
+            }
+            self.draw_transparent(&mut render_pass);
+
+            // Translucent geometry is drawn last, so it's blended with everything behind it
+            render_pass.set_pipeline(&self.translucent_pipeline);
+            render_pass.set_vertex_buffer(0, self.translucent_vertex_buffer.slice(..));
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Draws a small copy of the model or shape where every light is,
+    /// the bind groups of the models stay the same
+    fn draw_light<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        // The shader finds the light of a copy with its instance index
+        let lights = 0..u32::try_from(self.lights.len()).unwrap();
+        render_pass.set_pipeline(&self.light_pipeline);
+        if let Some(model) = &self.obj_model {
+            render_pass.draw_light_model(model, lights);
+        } else if let Some(index_buffer) = &self.index_buffer {
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), self.index_format);
+            render_pass.draw_indexed(0..self.num_indices, 0, lights);
+        } else {
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..self.num_vertices, lights);
+        }
+    }
+
+    /// Binds the textures, the camera, the globals, and the light,
+    /// and pushes the draw constants if there's no uniform buffer for them
+    fn set_bindings<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        // Make the texture available as group 0, the camera as group 1,
+        // the time and color handling as group 2, and the light as group 3
+        render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.globals_bind_group, &[]);
+        render_pass.set_bind_group(3, &self.light_bind_group, &[]);
+
+        // Without a uniform buffer, the draw constants are pushed with the draw commands
+        if self.draw_buffer.is_none() {
+            render_pass.set_push_constants(
+                ShaderStages::VERTEX_FRAGMENT,
+                0,
+                bytemuck::bytes_of(&self.draw_constants),
+            );
+        }
+    }
+
+    /// Draws every instance of the model or shape with the pipeline,
+    /// the render pass has to have the color targets the pipeline writes to.
+    /// Meshes with a transparent material are left for [`Self::draw_transparent`].
+    fn draw_models<'a>(&'a self, render_pass: &mut RenderPass<'a>, pipeline: &'a RenderPipeline) {
         // Add the render pipeline to the render pass
-        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_pipeline(pipeline);
+        self.set_bindings(render_pass);
+
+        // The value the pipelines write to the stencil buffer where the models are
+        render_pass.set_stencil_reference(STENCIL_REFERENCE);
+
+        // Use the whole vertex buffer for slot 0
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+        // Use the whole instance buffer for slot 1
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        // Draw every visible instance in the instance buffer
+        let instances = 0..self.visible_instances;
 
-        render_pass.draw(0..3, 0..1);
+        if let Some(model) = &self.obj_model {
+            // Every mesh binds its own vertices, indices, and material
+            render_pass.draw_model_opaque(model, instances);
+        } else if let Some(index_buffer) = &self.index_buffer {
+            // An empty indexed draw would be submitted for nothing, so skip it
+            if self.num_indices > 0 {
+                // Use the whole index buffer, the format has to match the type of the indices
+                render_pass.set_index_buffer(index_buffer.slice(..), self.index_format);
+
+                // Draw the vertices in the order the indices point to them
+                render_pass.draw_indexed(0..self.num_indices, 0, instances);
+            }
+        } else {
+            // Draw every vertex of the vertex buffer once
+            render_pass.draw(0..self.num_vertices, instances);
+        }
     }
 
-    /// # Errors
-    /// Returns an error if no render surface could be retrieved
-    pub fn render(&mut self) -> Result<(), SurfaceError> {
-        // Wait for the surface to provide a surface texture to render to
-        let output = self.surface.get_current_texture()?;
-
-        // Create a texture view with default settings.
-        let view = output
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-
-        // Create a command encoder to create the actual commands to send to the gpu.
-        // The encoder builds a command buffer that we can then send to the GPU.
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    /// Draws a larger copy of every visible instance in a single color, after the models marked
+    /// the stencil buffer. Only the parts around the models pass the stencil test.
+    fn draw_outlines<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_pipeline(&self.outline_pipeline);
+        self.set_bindings(render_pass);
+        render_pass.set_stencil_reference(STENCIL_REFERENCE);
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        let instances = 0..self.visible_instances;
+
+        // Transparent meshes don't mark the stencil buffer, so they'd be covered by their outline
+        if let Some(model) = &self.obj_model {
+            render_pass.draw_model_opaque(model, instances);
+        } else if let Some(index_buffer) = &self.index_buffer {
+            render_pass.set_index_buffer(index_buffer.slice(..), self.index_format);
+            render_pass.draw_indexed(0..self.num_indices, 0, instances);
+        } else {
+            render_pass.draw(0..self.num_vertices, instances);
+        }
+    }
 
-        self.render_with_pipeline(&mut encoder, &view);
+    /// Draws the transparent meshes of the model, and the quads, after everything opaque.
+    /// They don't write to the depth buffer, so they're drawn from back to front.
+    fn draw_transparent<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_pipeline(&self.transparent_pipeline);
+        self.set_bindings(render_pass);
 
-        // Submit will accept anything that implements IntoIter.
-        // Send the render pass(es) to the GPU
-        self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(model) = &self.obj_model {
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            render_pass.draw_model_transparent(model, 0..self.visible_instances, self.camera.eye);
+        }
 
-        // Display the image
-        output.present();
+        // The quads use the texture of the shape, its transparent corners show what's behind it
+        render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.quad_instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.quad_index_buffer.slice(..), IndexFormat::Uint16);
+        render_pass.draw_indexed(
+            0..u32::try_from(QUAD_INDICES.len()).unwrap(),
+            0,
+            0..u32::try_from(self.quad_instances.len()).unwrap(),
+        );
+    }
+}
 
-        Ok(())
+impl App for State {
+    #[cfg(target_arch = "wasm32")]
+    fn load<'a>(&'a mut self, ctx: &'a Context) -> impl Future<Output = ()> + 'a {
+        async move {
+            if let Err(error) = self.load_model(ctx, MODEL_FILE).await {
+                log::warn!("Couldn't load {MODEL_FILE}: {error}");
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: Duration) {
+        // Rebuild the pipelines when the shader file was saved
+        #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+        if self
+            .shader_watcher
+            .as_ref()
+            .is_some_and(FileWatcher::changed)
+        {
+            self.reload_shader(ctx);
+        }
+
+        // Cycle the background through all hues every 10 seconds, unless the cursor paused it.
+        // Moving by the time since the last update keeps the speed the same at any frame rate.
+        let is_paused = self
+            .background_paused_until
+            .is_some_and(|until| Instant::now() < until);
+        if self.animate_background && !is_paused {
+            self.background_hue = dt.as_secs_f64().mul_add(36.0, self.background_hue) % 360.0;
+            self.background_color = color::hsv_to_linear(self.background_hue, 0.6, 0.5);
+        }
+
+        // Slowly spin every instance around the y-axis
+        let rotation = Quaternion::from_angle_y(Rad(INSTANCE_ROTATION_SPEED * dt.as_secs_f32()));
+        for instance in &mut self.instances {
+            instance.rotation = rotation * instance.rotation;
+        }
+
+        // Upload the model matrix only if a key changed it since the last update
+        if std::mem::take(&mut self.model_transform_changed) {
+            ctx.queue.write_buffer(
+                &self.model_transform_buffer,
+                0,
+                bytemuck::cast_slice(&[TransformUniform::from(self.model_transform)]),
+            );
+        }
+
+        // Move the camera, and upload its new matrix and the instances it can see to the GPU
+        self.camera_controller.update_camera(&mut self.camera, dt);
+        self.write_camera_uniform(&ctx.queue);
+
+        // Move the lights around the shapes, they're uploaded when the frame is rendered
+        self.previous_lights.clone_from(&self.lights);
+        for light in &mut self.lights {
+            light.orbit(Rad(LIGHT_ORBIT_SPEED * dt.as_secs_f32()));
+        }
+
+        // The fog fades into the background, which can change every update
+        ctx.queue.write_buffer(
+            &self.fog_buffer,
+            0,
+            bytemuck::cast_slice(&[FogUniform::new(self.fog, self.background_color)]),
+        );
+
+        // Upload the time of this frame to the GPU
+        self.time_uniform.time = self.start_time.elapsed().as_secs_f32();
+        ctx.queue.write_buffer(
+            &self.time_buffer,
+            0,
+            bytemuck::cast_slice(&[self.time_uniform]),
+        );
+
+        // Push constants are set while rendering, the uniform buffer has to be updated here
+        if let Some(draw_buffer) = &self.draw_buffer {
+            ctx.queue
+                .write_buffer(draw_buffer, 0, bytemuck::cast_slice(&[self.draw_constants]));
+        }
+
+        // Run the compute pass that was requested, after updating and before rendering
+        #[cfg(not(target_arch = "wasm32"))]
+        if std::mem::take(&mut self.compute_requested) {
+            self.log_compute_checksum(ctx);
+        }
+
+        // Build the GUI with the input since the last update, it's drawn with the next frame
+        #[cfg(feature = "gui")]
+        self.build_gui(ctx);
+    }
+
+    fn render(&mut self, ctx: &mut Context, view: &TextureView, encoder: &mut CommandEncoder) {
+        self.update_window_title(ctx);
+        self.write_light_uniform(ctx);
+
+        // Render with the pipeline the user selected, in HDR if it's supported,
+        // and the effect if there is one
+        let hdr_target = self.hdr.as_ref().map(Hdr::target);
+        let post_target = self
+            .post_process
+            .is_active()
+            .then(|| self.post_process.target());
+        self.render_frame(encoder, view, &self.render_targets, hdr_target, post_target);
+
+        // Draw the frame rate in the top-left corner, after the post-processing as well
+        #[cfg(feature = "text")]
+        if let Some(text) = &mut self.text {
+            if let Some(fps) = ctx.frame_stats().fps() {
+                text.queue(
+                    &format!("{fps:.0} FPS"),
+                    TEXT_POSITION,
+                    TEXT_SCALE,
+                    TEXT_COLOR,
+                );
+            }
+            text.render(&ctx.device, &ctx.queue, encoder, view);
+        }
+
+        // Draw the GUI on top, after the post-processing, so the effect isn't applied to it
+        #[cfg(feature = "gui")]
+        if let Some(gui) = &mut self.gui {
+            gui.render(&ctx.device, &ctx.queue, &ctx.config, encoder, view);
+        }
+    }
+
+    fn input(&mut self, ctx: &mut Context, event: &WindowEvent) -> bool {
+        // The GUI gets the events first, so dragging a slider doesn't turn the camera
+        #[cfg(feature = "gui")]
+        if self.gui.as_mut().is_some_and(|gui| gui.handle_input(event)) {
+            return true;
+        }
+
+        match event {
+            // Keyboard input received for a key that's bound to an action
+            WindowEvent::KeyboardInput { input, .. } => {
+                let Some(action) = input
+                    .virtual_keycode
+                    .and_then(|key| self.input_map.action(key))
+                else {
+                    return false;
+                };
+                let is_pressed = input.state == ElementState::Pressed;
+
+                // The camera moves as long as a movement key is held down
+                if let Some(direction) = action.movement() {
+                    self.camera_controller.set_moving(direction, is_pressed);
+                    return true;
+                }
+
+                // Other actions happen once when the key is pressed
+                return is_pressed && self.run_action(ctx, action);
+            }
+
+            // If the left mouse button was pressed or released, start or stop dragging
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => self.mouse_pressed = *state == ElementState::Pressed,
+
+            // Scrolling zooms the camera in or out, the next update uploads the new position
+            WindowEvent::MouseWheel { delta, .. } => self.camera_controller.process_scroll(delta),
+
+            // Dragging orbits the camera, it shouldn't change the background as well
+            WindowEvent::CursorMoved { position, .. } if self.mouse_pressed => {
+                self.cursor_position = Some(*position);
+            }
+
+            // If the cursor moved
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(*position);
+
+                // The stored size can lag behind after the scale factor changed,
+                // so use the current size of the window
+                let size = ctx.window().map_or(ctx.size, Window::inner_size);
+                let color = color::cursor_to_color(*position, size);
+
+                // While cycling, show the cursor's color for a moment and then continue
+                if self.animate_background {
+                    self.background_color = color;
+                    self.background_paused_until = Some(Instant::now() + BACKGROUND_CURSOR_PAUSE);
+                } else {
+                    self.set_background_color(color);
+                }
+            }
+
+            // If the cursor left the screen, make the background black
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+                self.set_background_color(Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                });
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn device_input(&mut self, _ctx: &mut Context, event: &DeviceEvent) -> bool {
+        match event {
+            // Rotate the camera with the mouse, but only if the cursor is grabbed
+            DeviceEvent::MouseMotion { delta } if self.mouse_look => {
+                self.camera_controller.process_mouse(delta.0, delta.1);
+                true
+            }
+
+            // Orbit the camera around its target while the left mouse button is held
+            DeviceEvent::MouseMotion { delta } if self.mouse_pressed => {
+                self.camera_controller.process_drag(delta.0, delta.1);
+                true
+            }
+            _ => false,
+        }
     }
 
-    pub const fn size(&self) -> PhysicalSize<u32> {
-        self.size
+    fn resize(&mut self, ctx: &mut Context) {
+        // The depth, multisampled, and HDR textures have to be the same size as the surface
+        self.render_targets = Self::create_render_targets(
+            &ctx.device,
+            &ctx.config,
+            self.scene_format,
+            self.sample_count,
+        );
+        if let Some(hdr) = &mut self.hdr {
+            hdr.resize(&ctx.device, &ctx.config);
+        }
+        self.post_process.resize(&ctx.device, &ctx.config);
+
+        // The text keeps its size in pixels
+        #[cfg(feature = "text")]
+        if let Some(text) = &self.text {
+            text.resize(&ctx.queue, ctx.config.width, ctx.config.height);
+        }
+
+        // Update the aspect ratio of the camera, so the view doesn't stretch
+        self.camera.aspect = self.aspect_ratio(ctx.size.width, ctx.size.height);
+        self.write_camera_uniform(&ctx.queue);
+
+        // Show the new resolution without waiting for the next statistics update
+        self.refresh_window_title(ctx);
     }
 }
@@ -0,0 +1,457 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{ortho, Matrix4};
+use glyph_brush::{
+    ab_glyph::{FontArc, InvalidFont},
+    BrushAction, BrushError, GlyphBrush, GlyphBrushBuilder, GlyphVertex, Rectangle, Section, Text,
+};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, ColorTargetState,
+    ColorWrites, CommandEncoder, Device, Extent3d, FilterMode, FragmentState, ImageCopyTexture,
+    ImageDataLayout, LoadOp, MultisampleState, Operations, Origin3d, PipelineLayoutDescriptor,
+    PrimitiveState, PrimitiveTopology, Queue, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp, SurfaceConfiguration, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension, VertexAttribute,
+    VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+use crate::{camera::OPENGL_TO_WGPU_MATRIX, color, entry_points};
+
+/// The font every text is drawn with, a monospaced font keeps numbers from jumping around.
+/// Its license is in `res/DejaVuSansMono-LICENSE.txt`.
+const FONT: &[u8] = include_bytes!("../res/DejaVuSansMono.ttf");
+
+/// How many glyphs fit in the instance buffer at first, it grows when more are drawn
+const INITIAL_CAPACITY: BufferAddress = 64;
+
+/// A glyph as it's stored in the instance buffer, its quad is made in the vertex shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct GlyphInstance {
+    /// The corners of the quad in pixels, from the top-left corner of the surface
+    left_top: [f32; 2],
+    right_bottom: [f32; 2],
+
+    /// The corners of the glyph in the cache texture
+    tex_left_top: [f32; 2],
+    tex_right_bottom: [f32; 2],
+
+    /// The linear color of the text
+    color: [f32; 4],
+}
+
+impl GlyphInstance {
+    const ATTRIBUTES: [VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32x2,
+        4 => Float32x4,
+    ];
+
+    /// Describes how a buffer of glyphs is laid out in memory, every glyph is an instance
+    const fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    /// Converts a glyph that was laid out and cached by the brush.
+    /// The text isn't clipped to the bounds of its section, as it's unbounded by default.
+    /// The brush passes the glyph by value, so it can't take a reference.
+    #[allow(clippy::needless_pass_by_value)]
+    const fn new(vertex: GlyphVertex) -> Self {
+        let GlyphVertex {
+            tex_coords,
+            pixel_coords,
+            extra,
+            ..
+        } = vertex;
+        Self {
+            left_top: [pixel_coords.min.x, pixel_coords.min.y],
+            right_bottom: [pixel_coords.max.x, pixel_coords.max.y],
+            tex_left_top: [tex_coords.min.x, tex_coords.min.y],
+            tex_right_bottom: [tex_coords.max.x, tex_coords.max.y],
+            color: extra.color,
+        }
+    }
+}
+
+/// The projection and the gamma correction as they're stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct TextUniform {
+    projection: [[f32; 4]; 4],
+
+    /// 1 if the surface isn't sRGB, so the shader has to gamma correct the color itself
+    gamma_correct: u32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [u32; 3],
+}
+
+impl TextUniform {
+    /// Maps pixels to clip space. The y-axis points down, like the positions of the text,
+    /// so (0, 0) is the top-left corner of the surface and (width, height) the bottom-right one.
+    #[allow(clippy::cast_precision_loss)]
+    fn new(width: u32, height: u32, is_srgb: bool) -> Self {
+        let projection: Matrix4<f32> =
+            OPENGL_TO_WGPU_MATRIX * ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+        Self {
+            projection: projection.into(),
+            gamma_correct: u32::from(!is_srgb),
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Draws text on top of the frame with glyph brush.
+///
+/// Text is queued every frame and drawn by [`Self::render`]. The brush lays it out and
+/// rasterizes the glyphs that aren't in the cache texture yet, and every glyph is drawn as a
+/// quad with its part of the texture. When the text didn't change, the last glyphs are drawn again.
+pub struct TextRenderer {
+    brush: GlyphBrush<GlyphInstance>,
+
+    /// Has the coverage of every cached glyph, it grows when they don't fit anymore
+    texture: Texture,
+    sampler: Sampler,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    uniform_buffer: Buffer,
+    is_srgb: bool,
+
+    /// The glyphs of the last text that was drawn, and how many there are
+    instance_buffer: Buffer,
+    instance_count: u32,
+    pipeline: RenderPipeline,
+}
+
+impl TextRenderer {
+    /// Loads the bundled font, and creates the pipeline that draws text to the surface
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bundled font couldn't be parsed
+    pub fn new(device: &Device, config: &SurfaceConfiguration) -> Result<Self, InvalidFont> {
+        let font = FontArc::try_from_slice(FONT)?;
+        let brush = GlyphBrushBuilder::using_font(font).build();
+
+        let is_srgb = config.format.is_srgb();
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Text Buffer"),
+            contents: bytemuck::cast_slice(&[TextUniform::new(
+                config.width,
+                config.height,
+                is_srgb,
+            )]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Text Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // The glyphs are rasterized at the size they're drawn at, so the sampler only smooths
+        // the edges when a glyph doesn't line up with the pixels
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Text Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let (width, height) = brush.texture_dimensions();
+        let texture = Self::create_texture(device, width, height);
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            &texture,
+            &sampler,
+        );
+
+        Ok(Self {
+            brush,
+            texture,
+            sampler,
+            pipeline: Self::create_pipeline(device, config, &bind_group_layout),
+            bind_group_layout,
+            bind_group,
+            uniform_buffer,
+            is_srgb,
+            instance_buffer: Self::create_instance_buffer(device, INITIAL_CAPACITY),
+            instance_count: 0,
+        })
+    }
+
+    /// Creates the pipeline that blends the glyphs over the surface. The text is drawn after the
+    /// frame was resolved, so without multisampling or depth.
+    fn create_pipeline(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bind_group_layout: &BindGroupLayout,
+    ) -> RenderPipeline {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("text.wgsl"),
+            source: ShaderSource::Wgsl(color::shader_source(include_str!("text.wgsl"))),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: entry_points::VERTEX,
+                buffers: &[GlyphInstance::desc()],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: entry_points::TEXT,
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// Creates the cache texture, only the coverage of the glyphs is stored
+    fn create_texture(device: &Device, width: u32, height: u32) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("Text Cache Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+
+    /// Binds the uniform buffer and the cache texture, it's created again with the texture
+    fn create_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        uniform_buffer: &Buffer,
+        texture: &Texture,
+        sampler: &Sampler,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Text Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    /// Creates an instance buffer with room for the given number of glyphs
+    fn create_instance_buffer(device: &Device, capacity: BufferAddress) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("Text Instance Buffer"),
+            size: capacity * std::mem::size_of::<GlyphInstance>() as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Updates the projection to a surface of the new size, so the text keeps its size in pixels
+    pub fn resize(&self, queue: &Queue, width: u32, height: u32) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[TextUniform::new(width, height, self.is_srgb)]),
+        );
+    }
+
+    /// Queues text to be drawn by the next call to [`Self::render`]. The position of its
+    /// top-left corner and the scale are in pixels, and the color is linear with alpha.
+    pub fn queue(&mut self, text: &str, position: (f32, f32), scale: f32, color: [f32; 4]) {
+        self.brush.queue(
+            Section::default()
+                .add_text(Text::new(text).with_scale(scale).with_color(color))
+                .with_screen_position(position),
+        );
+    }
+
+    /// Draws the queued text on top of the view, in a render pass of its own.
+    /// Glyphs that aren't cached yet are rasterized and uploaded to the cache texture first.
+    pub fn render(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+    ) {
+        loop {
+            let texture = &self.texture;
+            let update_texture = |rect: Rectangle<u32>, data: &[u8]| {
+                queue.write_texture(
+                    ImageCopyTexture {
+                        texture,
+                        mip_level: 0,
+                        origin: Origin3d {
+                            x: rect.min[0],
+                            y: rect.min[1],
+                            z: 0,
+                        },
+                        aspect: TextureAspect::All,
+                    },
+                    data,
+                    ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(rect.width()),
+                        rows_per_image: Some(rect.height()),
+                    },
+                    Extent3d {
+                        width: rect.width(),
+                        height: rect.height(),
+                        depth_or_array_layers: 1,
+                    },
+                );
+            };
+
+            match self
+                .brush
+                .process_queued(update_texture, GlyphInstance::new)
+            {
+                Ok(BrushAction::Draw(instances)) => {
+                    self.upload_instances(device, queue, &instances);
+                    break;
+                }
+
+                // Nothing changed since the last frame, so the same glyphs are drawn again
+                Ok(BrushAction::ReDraw) => break,
+
+                // The glyphs don't fit in the cache, so it's replaced by a bigger one.
+                // The brush forgets what it cached, and rasterizes everything again.
+                Err(BrushError::TextureTooSmall { suggested }) => {
+                    let max_size = device.limits().max_texture_dimension_2d;
+                    let (width, height) = (suggested.0.min(max_size), suggested.1.min(max_size));
+                    if (width, height) == self.brush.texture_dimensions() {
+                        log::warn!("The text doesn't fit in a {width}x{height} glyph cache");
+                        return;
+                    }
+                    log::info!("Resizing the glyph cache to {width}x{height}");
+                    self.texture = Self::create_texture(device, width, height);
+                    self.bind_group = Self::create_bind_group(
+                        device,
+                        &self.bind_group_layout,
+                        &self.uniform_buffer,
+                        &self.texture,
+                        &self.sampler,
+                    );
+                    self.brush.resize_texture(width, height);
+                }
+            }
+        }
+
+        if self.instance_count == 0 {
+            return;
+        }
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Text Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+
+                // Keep the frame, the text is blended over it
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+
+        // Every glyph is a quad of 4 vertices in a triangle strip
+        render_pass.draw(0..4, 0..self.instance_count);
+    }
+
+    /// Uploads the glyphs to the instance buffer, which grows to the next power of two
+    /// when they don't fit
+    fn upload_instances(&mut self, device: &Device, queue: &Queue, instances: &[GlyphInstance]) {
+        let count = instances.len() as BufferAddress;
+        let capacity =
+            self.instance_buffer.size() / std::mem::size_of::<GlyphInstance>() as BufferAddress;
+        if count > capacity {
+            self.instance_buffer = Self::create_instance_buffer(device, count.next_power_of_two());
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instances));
+        self.instance_count = u32::try_from(instances.len()).unwrap_or(u32::MAX);
+    }
+}
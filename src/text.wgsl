@@ -0,0 +1,55 @@
+// Draws text on top of the frame. Every glyph is an instance, which is drawn as a quad
+// with the part of the cache texture that has the glyph in it.
+
+struct TextUniform {
+    // Maps pixels to clip space, with the origin in the top-left corner of the surface
+    projection: mat4x4<f32>,
+    gamma_correct: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> text: TextUniform;
+
+// The cache with every glyph that was drawn recently, only the coverage is stored
+@group(0) @binding(1)
+var glyph_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var glyph_sampler: sampler;
+
+struct GlyphInput {
+    @location(0) left_top: vec2<f32>,
+    @location(1) right_bottom: vec2<f32>,
+    @location(2) tex_left_top: vec2<f32>,
+    @location(3) tex_right_bottom: vec2<f32>,
+    @location(4) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+// The quad is drawn as a triangle strip: top-left, top-right, bottom-left, and bottom-right
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, glyph: GlyphInput) -> VertexOutput {
+    let corner = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    let position = mix(glyph.left_top, glyph.right_bottom, corner);
+
+    var out: VertexOutput;
+    out.clip_position = text.projection * vec4<f32>(position, 0.0, 1.0);
+    out.tex_coords = mix(glyph.tex_left_top, glyph.tex_right_bottom, corner);
+    out.color = glyph.color;
+    return out;
+}
+
+// The coverage of the glyph is the alpha, so the edges are blended with the frame
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(glyph_texture, glyph_sampler, in.tex_coords).r;
+    var color = in.color.rgb;
+    if text.gamma_correct != 0u {
+        color = to_srgb(color);
+    }
+    return vec4<f32>(color, in.color.a * coverage);
+}
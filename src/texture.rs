@@ -0,0 +1,618 @@
+use bytemuck::{Pod, Zeroable};
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    imageops::FilterType,
+    DynamicImage, GenericImageView, ImageError,
+};
+use wgpu::{
+    Adapter, AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, CompareFunction,
+    Device, Extent3d, FilterMode, ImageCopyTexture, ImageDataLayout, Origin3d, Queue, Sampler,
+    SamplerBindingType, SamplerDescriptor, ShaderStages, SurfaceConfiguration, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+/// A texture on the GPU, together with everything needed to sample it in a shader
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: TextureView,
+    pub sampler: Sampler,
+}
+
+impl Texture {
+    /// The format of the depth texture, the pipelines have to use the same format.
+    /// It also has 8 bits of stencil per pixel. wgpu supports it on every backend, WebGL2
+    /// included, so it doesn't need a feature or a depth-only fallback.
+    pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth24PlusStencil8;
+
+    /// Creates a depth texture with the same size as the surface.
+    /// It has to be recreated whenever the surface is resized.
+    /// The sample count has to match the color attachment it's used with.
+    #[must_use]
+    pub fn create_depth_texture(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        sample_count: u32,
+        label: &str,
+    ) -> Self {
+        // The depth texture has to be as large as the surface to have a depth for every pixel
+        let size = Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+
+            // It's rendered to, and could be sampled to visualize the depth. Multisampled depth
+            // can't be sampled with a regular sampler, and the OpenGL backend fails to create
+            // it with this usage, which leaves every frame empty.
+            usage: if sample_count == 1 {
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING
+            } else {
+                TextureUsages::RENDER_ATTACHMENT
+            },
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        // A comparison sampler is only needed when sampling the depth texture in a shader
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            compare: Some(CompareFunction::LessEqual),
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Creates a color texture with the size of the surface, in the given format.
+    /// It can be rendered to, sampled by a later pass, and copied to read it back.
+    #[must_use]
+    pub fn create_render_target(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        format: TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        // Every pixel of the target matches a pixel on the screen, so it doesn't need filtering
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Creates a multisampled color texture with the same size as the surface, in the format
+    /// the scene is rendered in. Frames are rendered to it, and resolved to the surface texture
+    /// or the HDR texture afterwards. It has to be recreated whenever the surface is resized.
+    #[must_use]
+    pub fn create_multisampled_framebuffer(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> TextureView {
+        device
+            .create_texture(&TextureDescriptor {
+                label: Some("Multisampled Framebuffer"),
+                size: Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: TextureDimension::D2,
+                format,
+
+                // It's only rendered to, the resolved texture is displayed
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default())
+    }
+
+    /// Decodes an image file (PNG or JPEG) and uploads it to the GPU.
+    /// Normal maps store directions instead of colors, see [`Texture::from_image`].
+    ///
+    /// # Errors
+    /// Returns an error if the bytes couldn't be decoded as an image
+    pub fn from_bytes(
+        device: &Device,
+        queue: &Queue,
+        bytes: &[u8],
+        label: &str,
+        is_normal_map: bool,
+    ) -> Result<Self, ImageError> {
+        let image = image::load_from_memory(bytes)?;
+        Ok(Self::from_image(
+            device,
+            queue,
+            &image,
+            Some(label),
+            is_normal_map,
+        ))
+    }
+
+    /// Creates a 1x1 texture of a single color, useful when a mesh doesn't have a texture
+    #[must_use]
+    pub fn from_color(
+        device: &Device,
+        queue: &Queue,
+        color: [u8; 4],
+        label: &str,
+        is_normal_map: bool,
+    ) -> Self {
+        let image =
+            DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(color)));
+        Self::from_image(device, queue, &image, Some(label), is_normal_map)
+    }
+
+    /// Creates a texture with a pattern of light and dark squares, `cells` by `cells` of
+    /// `cell_size` pixels. It's blended with the diffuse texture to show multi-texturing.
+    #[must_use]
+    pub fn checkerboard(
+        device: &Device,
+        queue: &Queue,
+        cells: u32,
+        cell_size: u32,
+        label: &str,
+    ) -> Self {
+        let size = cells * cell_size;
+        let image = image::RgbaImage::from_fn(size, size, |x, y| {
+            if (x / cell_size + y / cell_size).is_multiple_of(2) {
+                image::Rgba([230, 230, 230, 255])
+            } else {
+                image::Rgba([40, 40, 120, 255])
+            }
+        });
+        Self::from_image(
+            device,
+            queue,
+            &DynamicImage::ImageRgba8(image),
+            Some(label),
+            false,
+        )
+    }
+
+    /// Creates a normal map where every normal points straight out of the surface,
+    /// for meshes without a normal map
+    #[must_use]
+    pub fn flat_normal_map(device: &Device, queue: &Queue) -> Self {
+        // The directions -1.0..1.0 are stored as 0..255, so 128 is 0.0
+        Self::from_color(device, queue, [128, 128, 255, 255], "Flat Normal Map", true)
+    }
+
+    /// Uploads an already decoded image to the GPU.
+    /// Normal maps aren't colors, so they're stored without the sRGB conversion.
+    #[must_use]
+    pub fn from_image(
+        device: &Device,
+        queue: &Queue,
+        image: &DynamicImage,
+        label: Option<&str>,
+        is_normal_map: bool,
+    ) -> Self {
+        // The GPU expects 4 bytes per pixel in RGBA order
+        let rgba = image.to_rgba8();
+        let dimensions = image.dimensions();
+
+        // All textures are stored as 3D, a 2D texture has a depth of 1
+        let size = Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label,
+            size,
+
+            // Only 1 level of detail is stored, no mipmaps
+            mip_level_count: 1,
+
+            // Multisampling isn't used
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+
+            // Image files are stored in sRGB, so the GPU has to convert them to linear colors.
+            // Normal maps store directions, converting them would bend the normals.
+            format: if is_normal_map {
+                TextureFormat::Rgba8Unorm
+            } else {
+                TextureFormat::Rgba8UnormSrgb
+            },
+
+            // TEXTURE_BINDING allows the texture to be used in shaders,
+            // COPY_DST allows us to copy the image data to it
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        // Copy the pixels to the texture
+        queue.write_texture(
+            // Where to copy the pixel data to
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            // The actual pixel data
+            &rgba,
+            // The layout of the pixel data.
+            // Unlike buffer to texture copies, write_texture doesn't require the rows to be
+            // padded to COPY_BYTES_PER_ROW_ALIGNMENT (256 bytes), wgpu handles the alignment.
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * dimensions.0),
+                rows_per_image: Some(dimensions.1),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = Self::create_sampler(device);
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Decodes several image files and uploads them as the layers of a texture array, in order.
+    /// Every layer has to be the same size, so the images are scaled to the size of the first one.
+    ///
+    /// OpenGL treats textures with a single layer as 2D textures, they can't be used as arrays.
+    /// A single image is uploaded twice instead. Indices past the last layer are clamped to it,
+    /// so the array still looks like it has one layer.
+    ///
+    /// # Errors
+    /// Returns an error if no images are given, or any of the bytes couldn't be decoded as an image
+    ///
+    /// # Panics
+    /// Panics if there are more than `u32::MAX` images
+    pub fn from_bytes_array(
+        device: &Device,
+        queue: &Queue,
+        images: &[&[u8]],
+        label: &str,
+    ) -> Result<Self, ImageError> {
+        let mut images = images
+            .iter()
+            .map(|bytes| image::load_from_memory(bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some(first) = images.first() else {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic("a texture array needs at least 1 layer".to_owned()),
+            )));
+        };
+        let size = first.dimensions();
+        if images.len() == 1 {
+            images.push(images[0].clone());
+        }
+
+        // The view has to include every layer, not just the first one
+        Ok(Self::from_layers(
+            device,
+            queue,
+            &images,
+            size,
+            TextureViewDimension::D2Array,
+            label,
+        ))
+    }
+
+    /// Uploads 6 images as the faces of a cube map, in the order +x, -x, +y, -y, +z, -z.
+    /// The faces have to be square, so they're all scaled to the width of the first one.
+    #[must_use]
+    pub fn from_cubemap(
+        device: &Device,
+        queue: &Queue,
+        faces: &[DynamicImage; 6],
+        label: &str,
+    ) -> Self {
+        let size = faces[0].width();
+
+        // A cube map is stored as a texture array with 6 layers, the view samples it by direction
+        Self::from_layers(
+            device,
+            queue,
+            faces,
+            (size, size),
+            TextureViewDimension::Cube,
+            label,
+        )
+    }
+
+    /// Uploads the images as the layers of a texture with the given view dimension, every image
+    /// is scaled to the given size
+    fn from_layers(
+        device: &Device,
+        queue: &Queue,
+        images: &[DynamicImage],
+        (width, height): (u32, u32),
+        view_dimension: TextureViewDimension,
+        label: &str,
+    ) -> Self {
+        // Every layer is a 2D texture, stacked along the depth
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: u32::try_from(images.len()).unwrap(),
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        // Copy the pixels of every image to its own layer
+        for (layer, image) in (0..).zip(images) {
+            let rgba = if image.dimensions() == (width, height) {
+                image.to_rgba8()
+            } else {
+                image
+                    .resize_exact(width, height, FilterType::Triangle)
+                    .to_rgba8()
+            };
+            queue.write_texture(
+                ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                &rgba,
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                Extent3d {
+                    depth_or_array_layers: 1,
+                    ..size
+                },
+            );
+        }
+
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
+        let sampler = Self::create_sampler(device);
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Creates the sampler the textures of materials are read with
+    fn create_sampler(device: &Device) -> Sampler {
+        // The sampler decides how a texture coordinate is turned into a color
+        device.create_sampler(&SamplerDescriptor {
+            // Coordinates outside of the texture use the color of the nearest edge
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+
+            // Blend the pixels when the texture is magnified,
+            // pick the nearest pixel when it's minified
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        })
+    }
+
+    /// Describes the bind group the textures of a material are bound with.
+    /// `@binding(0)` is the diffuse texture, `@binding(1)` its sampler,
+    /// `@binding(2)` is the normal map, and `@binding(3)` its sampler.
+    /// `@binding(4)` is the texture the diffuse texture is blended with, `@binding(5)` its sampler.
+    /// `@binding(6)` is the texture array instances can pick from, `@binding(7)` its sampler.
+    #[must_use]
+    pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+        // The textures can only be read by the fragment shader
+        let texture_entry = |binding, view_dimension| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                multisampled: false,
+                view_dimension,
+                sample_type: TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+
+        // The samplers have to be able to filter, as the textures are filterable
+        let sampler_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Texture Bind Group Layout"),
+            entries: &[
+                texture_entry(0, TextureViewDimension::D2),
+                sampler_entry(1),
+                texture_entry(2, TextureViewDimension::D2),
+                sampler_entry(3),
+                texture_entry(4, TextureViewDimension::D2),
+                sampler_entry(5),
+                texture_entry(6, TextureViewDimension::D2Array),
+                sampler_entry(7),
+            ],
+        })
+    }
+
+    /// Binds the diffuse texture, the normal map, the shared textures, and their samplers
+    /// to a bind group created with [`Texture::create_bind_group_layout`]
+    #[must_use]
+    pub fn create_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        diffuse_texture: &Self,
+        normal_texture: &Self,
+        shared: &SharedTextures,
+        label: &str,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&diffuse_texture.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&normal_texture.view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Sampler(&normal_texture.sampler),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(&shared.blend.view),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::Sampler(&shared.blend.sampler),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: BindingResource::TextureView(&shared.instances.view),
+                },
+                BindGroupEntry {
+                    binding: 7,
+                    resource: BindingResource::Sampler(&shared.instances.sampler),
+                },
+            ],
+        })
+    }
+}
+
+/// The textures every material is drawn with besides its own, in group 0
+pub struct SharedTextures {
+    /// Blended with the diffuse texture by the mix factor
+    pub blend: Texture,
+
+    /// The texture array instances can pick a layer of instead of the diffuse texture.
+    /// OpenGL can only use textures with at least 2 layers as arrays.
+    pub instances: Texture,
+}
+
+/// How much of the blend texture is mixed into the diffuse texture, as it is stored in the
+/// uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct MixUniform {
+    /// 0.0 only shows the diffuse texture, 1.0 only the blend texture
+    pub factor: f32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [f32; 3],
+}
+
+impl MixUniform {
+    /// Creates the uniform, the factor is clamped between 0.0 and 1.0
+    #[must_use]
+    pub const fn new(factor: f32) -> Self {
+        Self {
+            factor: factor.clamp(0.0, 1.0),
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Whether every pixel of the image is fully opaque, images without an alpha channel always are.
+/// Meshes with a texture that isn't have to be blended with what's behind them.
+#[must_use]
+pub fn is_opaque(image: &DynamicImage) -> bool {
+    !image.color().has_alpha() || image.to_rgba8().pixels().all(|pixel| pixel[3] == u8::MAX)
+}
+
+/// Finds the highest sample count up to `requested` that every format supports on the adapter.
+/// Falls back to 1, which disables multisampling.
+#[must_use]
+pub fn supported_sample_count(adapter: &Adapter, formats: &[TextureFormat], requested: u32) -> u32 {
+    [16, 8, 4, 2]
+        .into_iter()
+        .filter(|&count| count <= requested)
+        .find(|&count| {
+            formats.iter().all(|&format| {
+                adapter
+                    .get_texture_format_features(format)
+                    .flags
+                    .sample_count_supported(count)
+            })
+        })
+        .unwrap_or(1)
+}
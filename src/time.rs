@@ -0,0 +1,326 @@
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use bytemuck::{Pod, Zeroable};
+use web_time::Instant;
+
+/// The time as it is stored in the uniform buffer, so shaders can animate.
+/// The shaders bind it as `u_time`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct TimeUniform {
+    /// The number of seconds since the application started
+    pub time: f32,
+
+    /// Uniform buffers have to be a multiple of 16 bytes on WebGL
+    _padding: [f32; 3],
+}
+
+impl TimeUniform {
+    /// Creates a uniform for the start of the application
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            time: 0.0,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+impl Default for TimeUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits the time between frames into updates of a fixed length, so the simulation runs at
+/// the same rate no matter how fast frames are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedTimestep {
+    /// How much time a single update simulates
+    step: Duration,
+
+    /// The time that passed, but wasn't simulated yet
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    /// The most updates run in a single frame. After a long stall (like a breakpoint or a
+    /// dragged window), catching up on every step would make the next frames slow as well.
+    pub const MAX_STEPS_PER_FRAME: u32 = 10;
+
+    /// Creates a timestep that updates `ticks_per_second` times every second.
+    /// A tick rate of 0 is treated as 1.
+    #[must_use]
+    pub fn new(ticks_per_second: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / ticks_per_second.max(1),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// The time every update simulates
+    #[must_use]
+    pub const fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Adds the time since the previous frame, and returns how many updates should run
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulator += dt;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < Self::MAX_STEPS_PER_FRAME {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        // Drop the time that couldn't be caught up on
+        if steps == Self::MAX_STEPS_PER_FRAME {
+            self.accumulator = self.accumulator.min(self.step);
+        }
+        steps
+    }
+
+    /// How far the current frame is between the last update and the next one, from 0.0 to 1.0.
+    /// Rendering can interpolate between the previous and current state with it.
+    #[must_use]
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f32() / self.step.as_secs_f32()).min(1.0)
+    }
+}
+
+/// Caps the frame rate by waiting out the rest of every frame. Without vertical sync,
+/// like with `PresentMode::Immediate`, frames are rendered as fast as possible otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLimiter {
+    /// The shortest time a frame can take
+    frame_time: Duration,
+}
+
+impl FrameLimiter {
+    /// How long before the end of the frame waiting stops sleeping, and spins instead.
+    /// Sleeping often takes a millisecond or two longer than asked, spinning doesn't.
+    pub const SPIN_TIME: Duration = Duration::from_millis(2);
+
+    /// Creates a limiter for `fps` frames per second, `None` if that isn't a positive number.
+    /// Rates so low that the frame time overflows a `Duration`, or so high that it rounds to 0,
+    /// return `None` as well.
+    #[must_use]
+    pub fn new(fps: f32) -> Option<Self> {
+        // Fails for negative, infinite, and NaN frame times, so for every rate that isn't positive
+        Duration::try_from_secs_f32(1.0 / fps)
+            .ok()
+            .filter(|frame_time| !frame_time.is_zero())
+            .map(|frame_time| Self { frame_time })
+    }
+
+    /// The most frames per second
+    #[must_use]
+    pub fn fps(&self) -> f32 {
+        1.0 / self.frame_time.as_secs_f32()
+    }
+
+    /// Blocks until the frame that started at `frame_start` took the whole frame time.
+    /// It sleeps for most of the time, and spins for the last part to end on time.
+    pub fn wait(&self, frame_start: Instant) {
+        let end = frame_start + self.frame_time;
+        let remaining = end.saturating_duration_since(Instant::now());
+        if let Some(sleep_time) = remaining.checked_sub(Self::SPIN_TIME) {
+            std::thread::sleep(sleep_time);
+        }
+        while Instant::now() < end {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Keeps the times of the most recent frames, to show how smooth rendering is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The frame times, the oldest is removed once the window is full
+    frame_times: VecDeque<Duration>,
+    window: usize,
+}
+
+impl FrameStats {
+    /// The number of frames the statistics are calculated over by default
+    pub const DEFAULT_WINDOW: usize = 120;
+
+    /// Creates statistics over the last `window` frames, at least 1
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            frame_times: VecDeque::with_capacity(window),
+            window,
+        }
+    }
+
+    /// Records a frame that took `frame_time`, dropping the oldest one if the window is full
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == self.window {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// The number of frames the statistics are currently calculated over
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    /// Whether no frames were recorded yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.frame_times.is_empty()
+    }
+
+    /// The average frame time, `None` if no frames were recorded
+    #[must_use]
+    pub fn average(&self) -> Option<Duration> {
+        let count = u32::try_from(self.frame_times.len())
+            .ok()
+            .filter(|&count| count > 0)?;
+        Some(self.frame_times.iter().sum::<Duration>() / count)
+    }
+
+    /// The shortest frame time, `None` if no frames were recorded
+    #[must_use]
+    pub fn min(&self) -> Option<Duration> {
+        self.frame_times.iter().min().copied()
+    }
+
+    /// The longest frame time, `None` if no frames were recorded
+    #[must_use]
+    pub fn max(&self) -> Option<Duration> {
+        self.frame_times.iter().max().copied()
+    }
+
+    /// The frame time that `percent` percent of the frames are at most as long as.
+    /// `percentile(99.0)` shows how long the slowest frames take, ignoring single hitches.
+    /// Returns `None` if no frames were recorded.
+    #[must_use]
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        if self.frame_times.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<_> = self.frame_times.iter().copied().collect();
+        sorted.sort_unstable();
+
+        // The nearest rank, the window is small enough to be exact as a float
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+
+    /// The average number of frames per second, `None` if no time was recorded
+    #[must_use]
+    pub fn fps(&self) -> Option<f64> {
+        let seconds = self.average()?.as_secs_f64();
+        (seconds > 0.0).then(|| 1.0 / seconds)
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+/// Formats the statistics like "144.2 fps (6.9 ms avg, 12.1 ms p99)"
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.fps(), self.average(), self.percentile(99.0)) {
+            (Some(fps), Some(average), Some(p99)) => write!(
+                f,
+                "{fps:.1} fps ({:.1} ms avg, {:.1} ms p99)",
+                average.as_secs_f64() * 1000.0,
+                p99.as_secs_f64() * 1000.0
+            ),
+            _ => write!(f, "no frames yet"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_limiter_accepts_positive_rates() {
+        let limiter = FrameLimiter::new(60.0).unwrap();
+        assert!((limiter.fps() - 60.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn frame_limiter_rejects_invalid_rates() {
+        for fps in [0.0, -0.0, -60.0, f32::NAN, f32::INFINITY, 1e-30, 1e30] {
+            assert_eq!(FrameLimiter::new(fps), None, "{fps} FPS was accepted");
+        }
+    }
+
+    #[test]
+    fn fixed_timestep_keeps_the_remainder() {
+        let mut timestep = FixedTimestep::new(10);
+        assert_eq!(timestep.step(), Duration::from_millis(100));
+        assert_eq!(timestep.advance(Duration::from_millis(250)), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+
+        // The remainder of the previous frame counts towards the next update
+        assert_eq!(timestep.advance(Duration::from_millis(50)), 1);
+        assert_eq!(timestep.advance(Duration::from_millis(50)), 0);
+    }
+
+    #[test]
+    fn fixed_timestep_caps_the_updates_per_frame() {
+        let mut timestep = FixedTimestep::new(10);
+        assert_eq!(
+            timestep.advance(Duration::from_secs(5)),
+            FixedTimestep::MAX_STEPS_PER_FRAME
+        );
+
+        // The time that couldn't be caught up on is dropped, except for a single step
+        assert_eq!(timestep.advance(Duration::ZERO), 1);
+        assert_eq!(timestep.advance(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn fixed_timestep_treats_zero_ticks_as_one() {
+        assert_eq!(FixedTimestep::new(0).step(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn frame_stats_percentile() {
+        let mut stats = FrameStats::new(100);
+        assert_eq!(stats.percentile(50.0), None);
+
+        // Recorded out of order, the percentile sorts them
+        for millis in (1..=100).rev() {
+            stats.record(Duration::from_millis(millis));
+        }
+        assert_eq!(stats.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(stats.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(stats.percentile(100.0), Some(Duration::from_millis(100)));
+
+        // Percentages outside of 0 to 100 are clamped
+        assert_eq!(stats.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(stats.percentile(150.0), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn frame_stats_drop_the_oldest_frames() {
+        let mut stats = FrameStats::new(3);
+        for millis in [40, 10, 20, 30] {
+            stats.record(Duration::from_millis(millis));
+        }
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.max(), Some(Duration::from_millis(30)));
+        assert_eq!(stats.percentile(100.0), Some(Duration::from_millis(30)));
+    }
+}
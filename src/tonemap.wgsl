@@ -0,0 +1,86 @@
+// Tonemapping: the scene is rendered to a float texture first, where colors can be brighter
+// than 1. This shader maps them back to the range the surface can show.
+
+// Will store the output of the vertex shader
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+
+    // Where to read the scene texture, from (0, 0) in the top left to (1, 1) in the bottom right
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// Draws a single triangle that covers the whole screen without a vertex buffer,
+// like post_process.wgsl
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput{
+    // 0 -> (0, 0), 1 -> (2, 0), 2 -> (0, 2)
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coords = uv;
+    return out;
+}
+
+// The rendered scene and the sampler to read it with
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+
+// The settings of the pass, as stored in `HdrUniform`
+struct HdrUniform{
+    exposure: f32,
+
+    // 0 is Reinhard, 1 is ACES
+    tonemapper: u32,
+    gamma_correct: u32,
+
+    // How much of the bloom is added, 0 while it's off
+    bloom_intensity: f32,
+};
+
+@group(0) @binding(2)
+var<uniform> hdr: HdrUniform;
+
+// The glow of the bright parts of the scene at half its resolution, see hdr_bloom.wgsl.
+// It has its own sampler, which interpolates while stretching it over the screen.
+@group(0) @binding(3)
+var t_bloom: texture_2d<f32>;
+@group(0) @binding(4)
+var s_bloom: sampler;
+
+// Every channel is divided by itself plus 1, so it approaches 1 without ever reaching it
+fn reinhard(color: vec3<f32>) -> vec3<f32>{
+    return color / (1.0 + color);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(color: vec3<f32>) -> vec3<f32>{
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
+    let color = textureSample(t_scene, s_scene, in.tex_coords);
+    let bloom = textureSample(t_bloom, s_bloom, in.tex_coords).rgb;
+    let exposed = (color.rgb + bloom * hdr.bloom_intensity) * hdr.exposure;
+
+    var mapped: vec3<f32>;
+    if hdr.tonemapper == 0u {
+        mapped = reinhard(exposed);
+    } else {
+        mapped = aces(exposed);
+    }
+
+    // The scene is stored in linear space, a surface that isn't sRGB doesn't convert it
+    if hdr.gamma_correct != 0u {
+        mapped = to_srgb(mapped);
+    }
+    return vec4<f32>(mapped, color.a);
+}
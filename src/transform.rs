@@ -0,0 +1,116 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Deg, Matrix4, Vector3};
+
+/// How far a single step moves the model
+const TRANSLATION_STEP: f32 = 0.5;
+
+/// How far a single step turns the model around the y-axis
+const ROTATION_STEP: Deg<f32> = Deg(15.0);
+
+/// How much a single step grows or shrinks the model by
+const SCALE_STEP: f32 = 1.1;
+
+/// The smallest and largest the model can be scaled, so it can't disappear or fill the screen
+pub const SCALE_RANGE: (f32, f32) = (0.1, 5.0);
+
+/// A single change to the transform of the model, every key press makes one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransformStep {
+    /// Move along the x-axis
+    Left,
+    Right,
+
+    /// Move along the z-axis, forward is away from where the camera starts
+    Forward,
+    Backward,
+
+    /// Move along the y-axis
+    Up,
+    Down,
+
+    /// Turn around the y-axis, left is counterclockwise seen from above
+    RotateLeft,
+    RotateRight,
+
+    /// Scale the model around its origin
+    Grow,
+    Shrink,
+
+    /// Go back to where the model started
+    Reset,
+}
+
+/// Where the model is placed, split in its translation, rotation, and scale.
+///
+/// Together they make the model matrix, which places the model in the world before the view
+/// matrix moves the world in front of the camera and the projection matrix flattens it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub position: Vector3<f32>,
+
+    /// Only around the y-axis, so the model stays upright
+    pub rotation: Deg<f32>,
+
+    /// The same in every direction, so normals only have to be normalized again
+    pub scale: f32,
+}
+
+impl Transform {
+    /// Leaves the model where it is
+    pub const IDENTITY: Self = Self {
+        position: Vector3::new(0.0, 0.0, 0.0),
+        rotation: Deg(0.0),
+        scale: 1.0,
+    };
+
+    /// The transform after the step, the scale stays within [`SCALE_RANGE`]
+    #[must_use]
+    pub fn step(self, step: TransformStep) -> Self {
+        let mut transform = self;
+        match step {
+            TransformStep::Left => transform.position.x -= TRANSLATION_STEP,
+            TransformStep::Right => transform.position.x += TRANSLATION_STEP,
+            TransformStep::Forward => transform.position.z -= TRANSLATION_STEP,
+            TransformStep::Backward => transform.position.z += TRANSLATION_STEP,
+            TransformStep::Up => transform.position.y += TRANSLATION_STEP,
+            TransformStep::Down => transform.position.y -= TRANSLATION_STEP,
+            TransformStep::RotateLeft => transform.rotation += ROTATION_STEP,
+            TransformStep::RotateRight => transform.rotation -= ROTATION_STEP,
+            TransformStep::Grow => transform.scale *= SCALE_STEP,
+            TransformStep::Shrink => transform.scale /= SCALE_STEP,
+            TransformStep::Reset => return Self::IDENTITY,
+        }
+        transform.scale = transform.scale.clamp(SCALE_RANGE.0, SCALE_RANGE.1);
+        transform
+    }
+
+    /// The model matrix: scale first, then rotate, and move last.
+    /// Matrices are applied from right to left, so they're multiplied in the opposite order.
+    #[must_use]
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position)
+            * Matrix4::from_angle_y(self.rotation)
+            * Matrix4::from_scale(self.scale)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// The model matrix as it's stored in the uniform buffer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct TransformUniform {
+    model: [[f32; 4]; 4],
+}
+
+impl From<Transform> for TransformUniform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            model: transform.matrix().into(),
+        }
+    }
+}
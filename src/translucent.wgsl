@@ -0,0 +1,44 @@
+// Draws a triangle at half opacity over the models, to show alpha blending.
+// The bind groups match shader.wgsl, so they don't have to be switched.
+
+// The camera data, as stored in `CameraUniform`
+struct CameraUniform{
+    view_position: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// How colors have to be written to the surface, as stored in `ColorUniform`
+struct ColorUniform{
+    gamma_correct: u32,
+};
+
+@group(2) @binding(1)
+var<uniform> color_info: ColorUniform;
+
+// Only the position of the vertices is used, but the layout has to match `Vertex::desc`
+struct VertexInput{
+    @location(0) position: vec3<f32>,
+};
+
+// The linear color of the triangle, the alpha is how much of it covers what's behind it
+const COLOR: vec4<f32> = vec4<f32>(1.0, 0.4, 0.0, 0.5);
+
+@vertex
+fn vs_main(model: VertexInput) -> @builtin(position) vec4<f32>{
+    return camera.view_proj * vec4<f32>(model.position, 1.0);
+}
+
+// The GPU blends the output with what's already in the target. An sRGB surface converts the
+// stored colors to linear first, so they're blended in linear space. A surface that isn't sRGB
+// blends the gamma corrected colors, which makes the mix look a bit darker. Alpha is never
+// gamma corrected.
+@fragment
+fn fs_main() -> @location(0) vec4<f32>{
+    if color_info.gamma_correct != 0u {
+        return vec4<f32>(to_srgb(COLOR.rgb), COLOR.a);
+    }
+    return COLOR;
+}
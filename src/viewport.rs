@@ -0,0 +1,78 @@
+/// The part of the render target a draw covers, in pixels from the top left,
+/// and the range of depth values it writes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub min_depth: f32,
+    pub max_depth: f32,
+}
+
+impl Viewport {
+    /// Covers the whole target with the full depth range
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn full(width: u32, height: u32) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: width as f32,
+            height: height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
+
+    /// Splits the target in a left and a right half, for split-screen rendering
+    #[must_use]
+    pub const fn split_horizontally(width: u32, height: u32) -> [Self; 2] {
+        let left = Self::full(width / 2, height);
+        let right = Self {
+            x: left.width,
+            ..Self::full(width - width / 2, height)
+        };
+        [left, right]
+    }
+
+    /// Keeps the viewport inside a target of the given size, and the depth between 0 and 1.
+    /// wgpu rejects viewports outside of the target, like after it was made smaller.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn clamped(self, width: u32, height: u32) -> Self {
+        let (width, height) = (width as f32, height as f32);
+        let x = self.x.clamp(0.0, width);
+        let y = self.y.clamp(0.0, height);
+        let min_depth = self.min_depth.clamp(0.0, 1.0);
+        Self {
+            x,
+            y,
+            width: self.width.clamp(0.0, width - x),
+            height: self.height.clamp(0.0, height - y),
+            min_depth,
+            max_depth: self.max_depth.clamp(min_depth, 1.0),
+        }
+    }
+
+    /// Whether the viewport doesn't cover a single pixel, then there's nothing to draw
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.width < 1.0 || self.height < 1.0
+    }
+
+    /// The pixels the viewport covers, as x, y, width, and height for a scissor rectangle.
+    /// Drawing is cut off at the edges of the viewport, so nothing spills into the other half.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn scissor_rect(&self) -> [u32; 4] {
+        let x = self.x.floor();
+        let y = self.y.floor();
+        [
+            x as u32,
+            y as u32,
+            ((self.x + self.width).ceil() - x) as u32,
+            ((self.y + self.height).ceil() - y) as u32,
+        ]
+    }
+}
@@ -0,0 +1,81 @@
+//! Runs the compute shader without a window. Machines without a graphics adapter, or with one
+//! that can't run compute shaders, skip these tests instead of failing them.
+#![cfg(not(target_arch = "wasm32"))]
+
+use learn_wgpu::{
+    app::{Context, StateBuilder, StateError},
+    compute::{self, DoubleKernel, WORKGROUP_SIZE},
+    state::State,
+};
+
+/// Creates a headless context that can run compute shaders, or `None` without one
+fn context() -> Option<Context> {
+    let ctx = match pollster::block_on(StateBuilder::new().inner_size(64, 64).build_headless()) {
+        Ok(ctx) => ctx,
+        Err(StateError::NoAdapter) => {
+            eprintln!("Skipped, there's no graphics adapter");
+            return None;
+        }
+        Err(error) => panic!("couldn't create a headless context: {error}"),
+    };
+    if !compute::is_supported(&ctx.adapter) {
+        eprintln!("Skipped, the adapter doesn't support compute shaders");
+        return None;
+    }
+    Some(ctx)
+}
+
+/// The numbers from 0 up to `len` as floats, they're exact up to 2^24
+#[allow(clippy::cast_precision_loss)]
+fn numbers(len: u32) -> Vec<f32> {
+    (0..len).map(|value| value as f32).collect()
+}
+
+#[test]
+fn run_compute_doubles_every_value() {
+    let Some(ctx) = context() else {
+        return;
+    };
+    let state = State::with_background_color(&ctx, State::DEFAULT_BACKGROUND_COLOR);
+
+    // Empty, a single value, exactly one workgroup, one more, and a partly used last workgroup
+    for len in [0, 1, WORKGROUP_SIZE, WORKGROUP_SIZE + 1, 1000] {
+        let values = numbers(len);
+        let doubled = state.run_compute(&ctx, &values).unwrap().unwrap();
+        let expected: Vec<_> = values.iter().map(|value| value * 2.0).collect();
+        assert_eq!(doubled, expected, "{len} values weren't doubled");
+    }
+}
+
+#[test]
+fn splits_dispatches_past_the_workgroup_limit() {
+    let Some(ctx) = context() else {
+        return;
+    };
+    let limits = ctx.device.limits();
+
+    // One value more than a single row of workgroups can cover
+    let len = limits.max_compute_workgroups_per_dimension * WORKGROUP_SIZE + 1;
+    let size = u64::from(len) * std::mem::size_of::<f32>() as u64;
+    if size > u64::from(limits.max_storage_buffer_binding_size) {
+        eprintln!("Skipped, {len} values don't fit in a storage buffer");
+        return;
+    }
+    assert_eq!(
+        compute::dispatch_size(len, limits.max_compute_workgroups_per_dimension),
+        (limits.max_compute_workgroups_per_dimension, 2)
+    );
+
+    let values = numbers(len);
+    let doubled = DoubleKernel::new(&ctx.device)
+        .run(&ctx.device, &ctx.queue, &values)
+        .unwrap();
+    assert_eq!(doubled.len(), values.len());
+    assert!(
+        doubled
+            .iter()
+            .zip(&values)
+            .all(|(doubled, value)| *doubled == value * 2.0),
+        "not every value was doubled"
+    );
+}
@@ -0,0 +1,166 @@
+//! Renders without a window, so the GPU code can be tested on CI.
+//! Machines without a graphics adapter skip these tests instead of failing them.
+
+use learn_wgpu::{
+    app::{Context, StateBuilder, StateError},
+    capture,
+    state::State,
+    texture::Texture,
+};
+use wgpu::{
+    Color, CommandEncoderDescriptor, Extent3d, LoadOp, Operations, RenderPassColorAttachment,
+    RenderPassDescriptor, StoreOp, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor,
+};
+
+/// The size of the frames, big enough that the scene doesn't reach the corners
+const SIZE: u32 = 256;
+
+/// Creates a headless context with frames of the given size, or `None` without an adapter
+fn context(width: u32, height: u32) -> Option<Context> {
+    match pollster::block_on(
+        StateBuilder::new()
+            .inner_size(width, height)
+            .build_headless(),
+    ) {
+        Ok(ctx) => Some(ctx),
+        Err(StateError::NoAdapter) => {
+            eprintln!("Skipped, there's no graphics adapter");
+            None
+        }
+        Err(error) => panic!("couldn't create a headless context: {error}"),
+    }
+}
+
+/// The RGBA bytes of the pixel at `(x, y)` in a frame that's `width` pixels wide
+fn pixel(rgba: &[u8], width: u32, x: u32, y: u32) -> &[u8] {
+    let start = ((y * width + x) * 4) as usize;
+    &rgba[start..start + 4]
+}
+
+#[test]
+fn renders_the_scene_over_the_clear_color() {
+    let Some(ctx) = context(SIZE, SIZE) else {
+        return;
+    };
+    let mut state = State::with_background_color(&ctx, State::DEFAULT_BACKGROUND_COLOR);
+    let rgba = state.render_to_vec(&ctx).unwrap();
+    assert_eq!(rgba.len(), (SIZE * SIZE * 4) as usize);
+
+    // The clear color is tonemapped in HDR, so it's read from a corner instead of converted
+    let clear = pixel(&rgba, SIZE, 0, 0);
+    let center = pixel(&rgba, SIZE, SIZE / 2, SIZE / 2);
+    assert_ne!(center, clear, "nothing was drawn in the center");
+}
+
+/// Clears a texture to a color every 8-bit format stores exactly, and reads it back
+fn clear_and_read(ctx: &Context, format: TextureFormat, width: u32, height: u32) -> Vec<u8> {
+    let texture = ctx.device.create_texture(&TextureDescriptor {
+        label: Some("Cleared Texture"),
+        size: Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Clear Pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view: &view,
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(Color {
+                    r: 1.0,
+                    g: 0.0,
+                    b: 51.0 / 255.0,
+                    a: 1.0,
+                }),
+                store: StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+    ctx.queue.submit(std::iter::once(encoder.finish()));
+    pollster::block_on(capture::read_texture_async(
+        &ctx.device,
+        &ctx.queue,
+        &texture,
+    ))
+    .unwrap()
+}
+
+#[test]
+fn reads_back_unpadded_rgba_bytes() {
+    let Some(ctx) = context(SIZE, SIZE) else {
+        return;
+    };
+
+    // 13 pixels are 52 bytes, so every row is padded to 256 bytes in the buffer
+    let (width, height) = (13, 3);
+    let expected = [255, 0, 51, 255].repeat((width * height) as usize);
+
+    // BGRA has its red and blue channels swapped back
+    for format in [TextureFormat::Rgba8Unorm, TextureFormat::Bgra8Unorm] {
+        let rgba = clear_and_read(&ctx, format, width, height);
+        assert_eq!(rgba, expected, "{format:?} wasn't read back correctly");
+    }
+}
+
+#[test]
+fn renders_to_texture_of_any_size() {
+    let Some(ctx) = context(SIZE, SIZE) else {
+        return;
+    };
+    let mut state = State::with_background_color(&ctx, State::DEFAULT_BACKGROUND_COLOR);
+
+    // A size that differs from the context, with rows that aren't 256-byte aligned
+    let (width, height) = (100, 60);
+    let rgba = state.render_to_texture(&ctx, width, height).unwrap();
+    assert_eq!(rgba.len(), (width * height * 4) as usize);
+    assert_ne!(
+        pixel(&rgba, width, width / 2, height / 2),
+        pixel(&rgba, width, 0, 0),
+        "nothing was drawn in the center"
+    );
+}
+
+#[test]
+fn texture_arrays_need_a_layer_and_have_at_least_two() {
+    let Some(ctx) = context(SIZE, SIZE) else {
+        return;
+    };
+    let image: &[u8] = include_bytes!("../src/happy-tree.png");
+    let create =
+        |images: &[&[u8]]| Texture::from_bytes_array(&ctx.device, &ctx.queue, images, "Test Array");
+
+    assert!(create(&[]).is_err());
+
+    // A single layer is repeated, so OpenGL doesn't treat it as a 2D texture
+    let single = create(&[image]).unwrap();
+    assert_eq!(single.texture.depth_or_array_layers(), 2);
+    let triple = create(&[image, image, image]).unwrap();
+    assert_eq!(triple.texture.depth_or_array_layers(), 3);
+}
+
+#[test]
+fn rejects_pipelines_with_a_missing_entry_point() {
+    let Some(ctx) = context(SIZE, SIZE) else {
+        return;
+    };
+    let mut state = State::with_background_color(&ctx, State::DEFAULT_BACKGROUND_COLOR);
+
+    assert!(state.add_pipeline(&ctx, "missing", "fs_missing").is_err());
+    assert!(!state.set_active_pipeline("missing"));
+}